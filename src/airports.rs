@@ -0,0 +1,215 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+};
+
+use crate::{
+    fir_boundaries::{with_extentions, FIRBoundary},
+    geodesy::{self, Coord},
+    vatspy_dat::{Airport, VatSpyDat},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Problem {
+    /// The FIR field is not in the [FIRs] section.
+    UnknownFir,
+    /// The boundary of the FIR is not in the boundary file, so the airport can not be checked.
+    MissingBoundary(String),
+    /// The airport is not inside the boundary of its FIR or any of its extentions.
+    Outside,
+}
+
+/// An airport with a FIR field that does not match the boundaries.
+#[derive(Debug, Clone)]
+pub(crate) struct Diagnostic {
+    pub(crate) airport: String,
+    pub(crate) name: String,
+    pub(crate) line: usize,
+    /// The FIR field as written.
+    pub(crate) declared: String,
+    pub(crate) problem: Problem,
+    /// The FIR from the [FIRs] section that contains the airport, used when fixing.
+    pub(crate) actual: Option<String>,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Airport {} ({}) on line {}: ",
+            self.airport, self.name, self.line
+        )?;
+        match &self.problem {
+            Problem::UnknownFir => write!(f, "FIR {} is not in the [FIRs] section", self.declared)?,
+            Problem::MissingBoundary(boundary) => {
+                return write!(
+                    f,
+                    "FIR {} uses boundary {}, which is not in the boundary file",
+                    self.declared, boundary
+                )
+            }
+            Problem::Outside => write!(f, "is outside its FIR {}", self.declared)?,
+        }
+        match &self.actual {
+            Some(fir) => write!(f, ", it is inside {}", fir),
+            None => write!(f, ", and not inside any FIR"),
+        }
+    }
+}
+
+/// The boundaries of every FIR with its extentions, by ICAO of the boundary.
+struct Boundaries {
+    groups: Vec<(String, Vec<Vec<Coord>>)>,
+}
+
+impl Boundaries {
+    fn new(firs: &[FIRBoundary]) -> Self {
+        let groups = with_extentions(firs)
+            .into_iter()
+            .map(|g| {
+                (
+                    firs[g[0]].icao.clone(),
+                    g.iter()
+                        .map(|n| geodesy::ring_coords(&firs[*n].boundary_corners))
+                        .collect(),
+                )
+            })
+            .collect();
+        Self { groups }
+    }
+
+    fn has(&self, boundary: &str) -> bool {
+        self.groups.iter().any(|(icao, _)| icao == boundary)
+    }
+
+    /// Tells if the point is inside the boundary with this ICAO. The same ICAO can be used by more than one group,
+    /// like an oceanic and a land FIR, then any of them will do.
+    fn contains(&self, boundary: &str, p: Coord) -> bool {
+        self.groups
+            .iter()
+            .filter(|(icao, _)| icao == boundary)
+            .any(|(_, rings)| rings.iter().any(|r| geodesy::contains(r, p)))
+    }
+
+    /// ICAO of the boundaries containing the point, the smallest first.
+    fn containing(&self, p: Coord) -> Vec<&str> {
+        let mut found: Vec<(&str, f64)> = self
+            .groups
+            .iter()
+            .filter_map(|(icao, rings)| {
+                rings
+                    .iter()
+                    .find(|r| geodesy::contains(r, p))
+                    .map(|r| (icao.as_str(), geodesy::area(r)))
+            })
+            .collect();
+        found.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        found.into_iter().map(|(icao, _)| icao).collect()
+    }
+}
+
+/// The FIR in the [FIRs] section containing the airport. A FIR named like its boundary is taken before other
+/// FIRs using the same boundary.
+fn actual_fir(dat: &VatSpyDat, boundaries: &Boundaries, airport: &Airport) -> Option<String> {
+    boundaries
+        .containing(Coord::from(&airport.position))
+        .into_iter()
+        .find_map(|boundary| {
+            let firs: Vec<_> = dat.firs_with_boundary(boundary).collect();
+            firs.iter()
+                .find(|f| f.icao == boundary)
+                .or_else(|| firs.first())
+                .map(|f| f.icao.clone())
+        })
+}
+
+/// Checks that every airport is inside the boundary of the FIR in its FIR field, or one of its extentions.
+pub(crate) fn check(dat: &VatSpyDat, firs: &[FIRBoundary]) -> Vec<Diagnostic> {
+    let boundaries = Boundaries::new(firs);
+    dat.airports
+        .iter()
+        .filter_map(|airport| {
+            let problem = match dat.fir(&airport.fir) {
+                None => Problem::UnknownFir,
+                Some(fir) if !boundaries.has(fir.boundary_icao()) => {
+                    Problem::MissingBoundary(fir.boundary_icao().to_string())
+                }
+                Some(fir)
+                    if boundaries.contains(fir.boundary_icao(), Coord::from(&airport.position)) =>
+                {
+                    return None
+                }
+                Some(_) => Problem::Outside,
+            };
+            let actual = match problem {
+                Problem::MissingBoundary(_) => None,
+                _ => actual_fir(dat, &boundaries, airport),
+            };
+            Some(Diagnostic {
+                airport: airport.icao.clone(),
+                name: airport.name.clone(),
+                line: airport.line,
+                declared: airport.fir.clone(),
+                problem,
+                actual,
+            })
+        })
+        .collect()
+}
+
+/// The new FIR field for every line that can be fixed, by line number.
+pub(crate) fn fixes(diagnostics: &[Diagnostic]) -> HashMap<usize, String> {
+    diagnostics
+        .iter()
+        .filter_map(|d| d.actual.clone().map(|fir| (d.line, fir)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fir_boundaries::fir, vatspy_dat};
+
+    const DAT: &str = "[Airports]\r
+ZAAA|Alpha|0.5|0.5|AAA|ZZZZ|0\r
+ZBBB|Bravo|0.5|1.5|BBB|ZZZZ|0\r
+ZCCC|Charlie|0.5|2.5|CCC|ZZZO|0\r
+ZDDD|Delta|0.5|5.5|DDD|ZZZZ|0\r
+YEEE|Echo|0.5|1.5|EEE|QQQQ|0\r
+[FIRs]\r
+ZZZZ|Zulu Control||\r
+ZZZO|Zulu Oceanic||ZZZZ\r
+YYYY|Yankee Control||\r
+";
+
+    #[test]
+    fn airports_outside_their_fir() {
+        let firs = vec![
+            fir("ZZZZ", false, &[(0, 0), (1, 0), (1, 1), (0, 1)]),
+            fir("YYYY", false, &[(0, 1), (1, 1), (1, 2), (0, 2)]),
+            fir("ZZZZ", true, &[(0, 2), (1, 2), (1, 3), (0, 3)]),
+        ];
+        let dat = VatSpyDat::parse(DAT).unwrap();
+        let diagnostics = check(&dat, &firs);
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Airport ZBBB (Bravo) on line 3: is outside its FIR ZZZZ, it is inside YYYY",
+                "Airport ZDDD (Delta) on line 5: is outside its FIR ZZZZ, and not inside any FIR",
+                "Airport YEEE (Echo) on line 6: FIR QQQQ is not in the [FIRs] section, it is inside YYYY",
+            ]
+        );
+
+        let fixed = vatspy_dat::set_airport_firs(DAT, &fixes(&diagnostics));
+        assert_eq!(
+            fixed,
+            DAT.replace(
+                "ZBBB|Bravo|0.5|1.5|BBB|ZZZZ|",
+                "ZBBB|Bravo|0.5|1.5|BBB|YYYY|"
+            )
+            .replace("YEEE|Echo|0.5|1.5|EEE|QQQQ|", "YEEE|Echo|0.5|1.5|EEE|YYYY|")
+        );
+        assert_eq!(check(&VatSpyDat::parse(&fixed).unwrap(), &firs).len(), 1);
+    }
+}
@@ -0,0 +1,404 @@
+use std::{
+    fmt::{self, Display},
+    fs,
+    path::Path,
+};
+
+use roxmltree::{Document, Node};
+
+use crate::{
+    fir_boundaries::{FIRBoundary, FIRParsingError, FIRResult, Point},
+    geodesy::{self, Coord, RingBuilder, METRES_PER_NM},
+    lable,
+};
+
+/// Airspace types imported when no types are given.
+pub(crate) const DEFAULT_TYPES: [&str; 3] = ["FIR", "UIR", "CTA"];
+
+/// A part of the AIXM file that could not be imported.
+#[derive(Debug, Clone)]
+pub(crate) struct Skipped {
+    pub(crate) designator: String,
+    pub(crate) reason: String,
+}
+
+impl Display for Skipped {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.designator, self.reason)
+    }
+}
+
+fn aixm_error<S: Display>(s: S) -> FIRParsingError {
+    FIRParsingError::FIRParsing(format!("AIXM: {}", s))
+}
+
+fn is(node: &Node, name: &str) -> bool {
+    node.is_element() && node.tag_name().name() == name
+}
+
+/// First child element with the local name `name`, whatever the namespace prefix is.
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| is(n, name))
+}
+
+fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children().filter(move |n| is(n, name))
+}
+
+fn text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name).and_then(|n| n.text()).map(str::trim)
+}
+
+fn number(node: Node, name: &str) -> FIRResult<f64> {
+    let s = text(node, name).ok_or_else(|| aixm_error(format!("missing gml:{}", name)))?;
+    s.parse()
+        .map_err(|_| aixm_error(format!("gml:{} is not a number: {}", name, s)))
+}
+
+/// The time slice to use: the baseline with the highest sequence and correction number.
+fn time_slice<'a, 'input>(airspace: Node<'a, 'input>) -> Option<Node<'a, 'input>> {
+    let number =
+        |n: Node, name: &str| -> u64 { text(n, name).and_then(|s| s.parse().ok()).unwrap_or(0) };
+    children(airspace, "timeSlice")
+        .filter_map(|n| child(n, "AirspaceTimeSlice"))
+        .max_by_key(|n| {
+            (
+                text(*n, "interpretation") == Some("BASELINE"),
+                number(*n, "sequenceNumber"),
+                number(*n, "correctionNumber"),
+            )
+        })
+}
+
+/// Tells if coordinates are given as longitude first. EPSG:4326, the default in AIXM, is latitude first.
+fn lon_first(node: Node) -> bool {
+    node.ancestors()
+        .find_map(|n| n.attribute("srsName"))
+        .map(|srs| srs.contains("CRS84"))
+        .unwrap_or(false)
+}
+
+fn coords(s: &str, lon_first: bool) -> FIRResult<Vec<Coord>> {
+    let values = s
+        .split_whitespace()
+        .map(|v| {
+            v.parse::<f64>()
+                .map_err(|_| aixm_error(format!("not a coordinate: {}", v)))
+        })
+        .collect::<FIRResult<Vec<_>>>()?;
+    if values.len() % 2 != 0 {
+        return Err(aixm_error(format!(
+            "odd number of coordinates: {}",
+            s.trim()
+        )));
+    }
+    Ok(values
+        .chunks(2)
+        .map(|c| match lon_first {
+            true => Coord {
+                lat: c[1],
+                lon: c[0],
+            },
+            false => Coord {
+                lat: c[0],
+                lon: c[1],
+            },
+        })
+        .collect())
+}
+
+/// Points of a gml:posList, gml:pos or gml:pointProperty list.
+fn positions(node: Node) -> FIRResult<Vec<Coord>> {
+    let lon_first = lon_first(node);
+    if let Some(list) = child(node, "posList") {
+        return coords(list.text().unwrap_or_default(), lon_first);
+    }
+    let mut points = Vec::new();
+    for n in node.children().filter(|n| n.is_element()) {
+        let pos = match n.tag_name().name() {
+            "pos" => Some(n),
+            "pointProperty" | "pointRep" => n.descendants().find(|d| is(d, "pos")),
+            _ => None,
+        };
+        if let Some(pos) = pos {
+            points.extend(coords(pos.text().unwrap_or_default(), lon_first)?);
+        }
+    }
+    Ok(points)
+}
+
+fn radius_metres(node: Node) -> FIRResult<f64> {
+    let radius = number(node, "radius")?;
+    let uom = child(node, "radius")
+        .and_then(|r| r.attribute("uom"))
+        .unwrap_or("NM");
+    let factor = match uom.to_uppercase().as_str() {
+        "NM" | "[NMI_I]" => METRES_PER_NM,
+        "KM" => 1000.0,
+        "M" => 1.0,
+        "FT" | "[FT_I]" => 0.3048,
+        "MI" | "[MI_I]" => 1609.344,
+        _ => return Err(aixm_error(format!("unknown unit for radius: {}", uom))),
+    };
+    Ok(radius * factor)
+}
+
+/// Points along a gml:ArcByCenterPoint or gml:CircleByCenterPoint, at most `step` degrees apart.
+///
+/// The angles are read as bearings from true north, and the arc goes from startAngle to endAngle,
+/// so clockwise when endAngle is larger. Some producers use mathematical angles from east instead,
+/// so if the other reading puts the start of the arc closer to where the ring was, that one is used.
+fn arc(node: Node, previous: Option<Coord>, step: f64) -> FIRResult<Vec<Coord>> {
+    let center = *positions(node)?
+        .first()
+        .ok_or_else(|| aixm_error("arc without a center"))?;
+    let radius = radius_metres(node)?;
+    let (start, end) = match is(&node, "CircleByCenterPoint") {
+        true => (0.0, 360.0),
+        false => (number(node, "startAngle")?, number(node, "endAngle")?),
+    };
+    let bearing_of = |angle: f64, mathematical: bool| match mathematical {
+        true => 90.0 - angle,
+        false => angle,
+    };
+    let mathematical = match previous {
+        Some(p) => {
+            let start_at = |m| geodesy::destination(center, bearing_of(start, m), radius);
+            geodesy::great_circle_distance(start_at(true), p)
+                < geodesy::great_circle_distance(start_at(false), p)
+        }
+        None => false,
+    };
+    let sweep = match mathematical {
+        true => start - end,
+        false => end - start,
+    };
+    geodesy::arc_points(center, radius, bearing_of(start, mathematical), sweep, step)
+}
+
+/// Points of a gml:Ring, gml:LinearRing or a curve, with arcs turned into points.
+fn ring(node: Node, step: f64) -> FIRResult<Vec<Coord>> {
+    let mut ring = RingBuilder::default();
+    for n in node.descendants().filter(|n| n.is_element()) {
+        match n.tag_name().name() {
+            "LinearRing" | "LineString" | "LineStringSegment" | "GeodesicString" | "Geodesic" => {
+                ring.push_points(positions(n)?)
+            }
+            "ArcByCenterPoint" | "CircleByCenterPoint" => ring.push_arc(arc(n, ring.last(), step)?),
+            name @ ("Arc" | "ArcString" | "Circle" | "CubicSpline" | "BSpline" | "Clothoid") => {
+                return Err(aixm_error(format!(
+                    "unsupported curve segment gml:{}",
+                    name
+                )))
+            }
+            _ => (),
+        }
+    }
+    Ok(ring.finish())
+}
+
+/// Reads the parts of an airspace, every polygon of every geometry component that adds to the airspace.
+fn parts(slice: Node, designator: &str, step: f64, skipped: &mut Vec<Skipped>) -> Vec<Vec<Point>> {
+    let mut skip = |reason: String| {
+        skipped.push(Skipped {
+            designator: designator.to_string(),
+            reason,
+        })
+    };
+    let mut parts = Vec::new();
+    for component in
+        children(slice, "geometryComponent").filter_map(|n| child(n, "AirspaceGeometryComponent"))
+    {
+        match text(component, "operation") {
+            None | Some("BASE") | Some("UNION") => (),
+            Some(op) => {
+                skip(format!("geometry component with operation {} ignored", op));
+                continue;
+            }
+        }
+        let projection = match component
+            .descendants()
+            .find(|n| is(n, "horizontalProjection"))
+        {
+            Some(p) => p,
+            None => {
+                skip("geometry component without a horizontal projection ignored".to_string());
+                continue;
+            }
+        };
+        for polygon in projection
+            .descendants()
+            .filter(|n| is(n, "PolygonPatch") || is(n, "Polygon"))
+        {
+            if children(polygon, "interior").next().is_some() {
+                skip("holes are not supported and have been left out".to_string());
+            }
+            let exterior = match child(polygon, "exterior") {
+                Some(e) => e,
+                None => continue,
+            };
+            match ring(exterior, step) {
+                Ok(coords) => match geodesy::boundary_corners(&coords) {
+                    c if c.len() >= 3 => parts.push(c),
+                    _ => skip("polygon with less than 3 points ignored".to_string()),
+                },
+                Err(e) => skip(format!("{}", e)),
+            }
+        }
+    }
+    parts
+}
+
+/// Reads the Airspace features of the given types from an AIXM 5.1 file.
+///
+/// Every polygon of an airspace becomes a FIRBoundary, the first one the FIR and the rest its extentions.
+/// Arcs are turned into points at most `step` degrees apart as seen from their center.
+/// Lables are put at the pole of inaccessibility of the largest part, and airspaces
+/// with "OCEANIC" in their name are marked as oceanic.
+pub(crate) fn read_file<P: AsRef<Path>>(
+    p: P,
+    types: &[String],
+    step: f64,
+) -> FIRResult<(Vec<FIRBoundary>, Vec<Skipped>)> {
+    let content = fs::read_to_string(p)?;
+    let doc = Document::parse(&content).map_err(aixm_error)?;
+    let types: Vec<String> = match types.is_empty() {
+        true => DEFAULT_TYPES.iter().map(|t| t.to_string()).collect(),
+        false => types.iter().map(|t| t.to_uppercase()).collect(),
+    };
+
+    let mut firs = Vec::new();
+    let mut skipped = Vec::new();
+    for slice in doc
+        .descendants()
+        .filter(|n| is(n, "Airspace"))
+        .filter_map(time_slice)
+    {
+        let typ = text(slice, "type").unwrap_or_default();
+        if !types
+            .iter()
+            .any(|t| t == typ || t == typ.trim_end_matches("_P"))
+        {
+            continue;
+        }
+        let name = text(slice, "name").unwrap_or_default();
+        let designator = match text(slice, "designator") {
+            Some(d) if !d.is_empty() => d,
+            _ => {
+                skipped.push(Skipped {
+                    designator: name.to_string(),
+                    reason: format!("{} without a designator ignored", typ),
+                });
+                continue;
+            }
+        };
+        let parts = parts(slice, designator, step, &mut skipped);
+        if parts.is_empty() {
+            skipped.push(Skipped {
+                designator: designator.to_string(),
+                reason: format!("{} without a usable geometry ignored", typ),
+            });
+            continue;
+        }
+        for (n, corners) in parts.into_iter().enumerate() {
+            let mut fir = FIRBoundary {
+                id: firs.len(),
+                icao: designator.to_string(),
+                is_oseanic: name.to_uppercase().contains("OCEANIC"),
+                is_extension: n != 0,
+                min_lat: Default::default(),
+                min_lon: Default::default(),
+                max_lat: Default::default(),
+                max_lon: Default::default(),
+                lable: corners[0].clone(),
+                boundary_corners: corners,
+            };
+            fir.update_bounds();
+            firs.push(fir);
+        }
+    }
+    lable::fix_lables(&mut firs, true);
+    Ok((firs, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fir_boundaries::{polygon_or_hole, Fill};
+
+    const AIXM: &str = r#"<message:AIXMBasicMessage xmlns:message="http://www.aixm.aero/schema/5.1/message" xmlns:aixm="http://www.aixm.aero/schema/5.1" xmlns:gml="http://www.opengis.net/gml/3.2">
+  <message:hasMember>
+    <aixm:Airspace gml:id="A1">
+      <aixm:timeSlice>
+        <aixm:AirspaceTimeSlice gml:id="A1T1">
+          <aixm:interpretation>BASELINE</aixm:interpretation>
+          <aixm:type>FIR</aixm:type>
+          <aixm:designator>XXXX</aixm:designator>
+          <aixm:name>TEST FIR</aixm:name>
+          <aixm:geometryComponent>
+            <aixm:AirspaceGeometryComponent>
+              <aixm:theAirspaceVolume>
+                <aixm:AirspaceVolume>
+                  <aixm:horizontalProjection>
+                    <aixm:Surface srsName="urn:ogc:def:crs:EPSG::4326">
+                      <gml:patches>
+                        <gml:PolygonPatch>
+                          <gml:exterior>
+                            <gml:Ring>
+                              <gml:curveMember>
+                                <gml:Curve>
+                                  <gml:segments>
+                                    <gml:GeodesicString>
+                                      <gml:posList>59 10 60 10</gml:posList>
+                                    </gml:GeodesicString>
+                                    <gml:ArcByCenterPoint numArc="1">
+                                      <gml:pos>59.5 10</gml:pos>
+                                      <gml:radius uom="NM">30</gml:radius>
+                                      <gml:startAngle uom="deg">0</gml:startAngle>
+                                      <gml:endAngle uom="deg">180</gml:endAngle>
+                                    </gml:ArcByCenterPoint>
+                                  </gml:segments>
+                                </gml:Curve>
+                              </gml:curveMember>
+                            </gml:Ring>
+                          </gml:exterior>
+                        </gml:PolygonPatch>
+                      </gml:patches>
+                    </aixm:Surface>
+                  </aixm:horizontalProjection>
+                </aixm:AirspaceVolume>
+              </aixm:theAirspaceVolume>
+            </aixm:AirspaceGeometryComponent>
+          </aixm:geometryComponent>
+        </aixm:AirspaceTimeSlice>
+      </aixm:timeSlice>
+    </aixm:Airspace>
+  </message:hasMember>
+</message:AIXMBasicMessage>"#;
+
+    #[test]
+    fn arc_is_densified() {
+        let doc = Document::parse(AIXM).unwrap();
+        let slice = doc
+            .descendants()
+            .find(|n| is(n, "AirspaceTimeSlice"))
+            .unwrap();
+        let mut skipped = Vec::new();
+        let parts = parts(slice, "XXXX", 10.0, &mut skipped);
+        assert!(skipped.is_empty(), "{:?}", skipped);
+        assert_eq!(parts.len(), 1);
+        // 2 points of the string and 19 along the arc, where the first is the end of the string
+        // and the last closes the ring
+        assert_eq!(parts[0].len(), 2 + 17);
+        assert_eq!(polygon_or_hole(&parts[0]), Fill::Polygon);
+        // the arc bulges east of 10° east, through 59.5° north
+        let east = parts[0]
+            .iter()
+            .map(|p| Coord::from(p).lon)
+            .fold(f64::NEG_INFINITY, f64::max);
+        assert!((east - (10.0 + 0.5 / 59.5f64.to_radians().cos())).abs() < 0.01);
+    }
+}
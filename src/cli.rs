@@ -2,14 +2,354 @@ use std::path::PathBuf;
 
 use clap::{crate_version, AppSettings, Clap};
 
+use crate::{
+    densify::Line,
+    euroscope::{ExtentionPlacement, Placement},
+    render::Projection,
+    simplify::Algorithm,
+    statistics::{OutputFormat, SortKey},
+};
+
+/// Shown after the help of every command reading or writing boundary files, so the arguments can refer to it.
+const BOUNDARY_FILES: &str = "Supported boundary files: .dat, .geojson/.json, .openair, .kml, \
+                              .shp, .wkt, .wkb and .topojson, decided by the file extention. \
+                              .fgb (FlatGeobuf) can only be written.";
+
 #[derive(Clap)]
+#[clap(after_help = BOUNDARY_FILES)]
 #[clap(version = crate_version!(), author = "meltinglava. <meltinglavaoutland@gmail.com>")]
 #[clap(setting = AppSettings::ColoredHelp)]
+#[clap(setting = AppSettings::ArgsNegateSubcommands)]
 pub(crate) struct Opts {
-    /// Input file input. This has to end with .bat or .geojson/.json.
-    pub(crate) input: PathBuf,
+    /// Input file, any supported boundary file.
+    pub(crate) input: Option<PathBuf>,
     /// If this argument is missing only validation will be done.
     /// If this file is the same type. Fixes will be applied to that file.
     /// If this file is of the other type. It will be converted and filled into the other file.
     pub(crate) output: Option<PathBuf>,
+    /// Move lables that are outside their FIR to the pole of inaccessibility of the largest part of the FIR.
+    /// Only used when fixing or converting.
+    #[clap(long)]
+    pub(crate) fix_lables: bool,
+    /// Like --fix-lables, but moves every lable.
+    #[clap(long)]
+    pub(crate) relable_all: bool,
+    /// Only read OpenAir airspaces with these classes (AC) or types (AY). Every airspace is read if none are given.
+    #[clap(long, multiple_occurrences = true)]
+    pub(crate) openair_class: Vec<String>,
+    /// Write coordinates in a .dat output file in degrees, minutes and seconds, like N051.30.00.000.
+    /// VATSpy only reads decimal degrees, so this is for comparing with sources using them.
+    #[clap(long)]
+    pub(crate) dms: bool,
+    /// Write a .wkt or .wkb output file as CSV with a header, instead of the tab separated text of PostgreSQL COPY.
+    #[clap(long)]
+    pub(crate) csv: bool,
+    /// Decimals kept in a .topojson output file, 4 is about 10 metres. Borders shared between FIRs stay shared.
+    #[clap(long, default_value = "6", possible_values = &["0", "1", "2", "3", "4", "5", "6"])]
+    pub(crate) topojson_decimals: u32,
+    /// Also write every validation error to this HTML file, with a map of every FIR with errors and dataset statistics.
+    /// The file works offline, for attaching to a pull request.
+    #[clap(long)]
+    pub(crate) html_report: Option<PathBuf>,
+    /// Keep running, and validate the input again every time it is saved. Only findings that are new or resolved
+    /// since the last save are printed. The output file and --html-report are written again on every save.
+    #[clap(long)]
+    pub(crate) watch: bool,
+    #[clap(subcommand)]
+    pub(crate) command: Option<Command>,
+}
+
+#[derive(Clap)]
+pub(crate) enum Command {
+    /// Print area, perimeter, vertex count, centroid and bounding box for every FIR.
+    Info(InfoOpts),
+    /// Reduce the number of points in the boundaries, keeping borders shared between FIRs identical.
+    Simplify(SimplifyOpts),
+    /// Add points along long edges, so they are drawn as great circles or rhumb lines.
+    Densify(DensifyOpts),
+    /// Snap points of neighbouring FIRs that are almost on top of each other, so shared borders are identical.
+    Snap(SnapOpts),
+    /// Build UIR outlines from the boundaries of their member FIRs, as listed in VATSpy.dat.
+    Uir(UirOpts),
+    /// Write every FIR with its extentions to a file of its own, with a manifest keeping the order.
+    Split(SplitOpts),
+    /// Join a directory made by split back into one file, validating every file.
+    Join(JoinOpts),
+    /// Import FIR, UIR and CTA airspaces from an AIXM 5.1 file.
+    Aixm(AixmOpts),
+    /// Write the boundaries as [ARTCC HIGH]/[ARTCC LOW] in a EuroScope SCT file and sectors in an ESE file.
+    Euroscope(EuroscopeOpts),
+    /// Import FIRs from the sector lines and sectors in the [AIRSPACE] section of a EuroScope ESE file.
+    Ese(EseOpts),
+    /// Render the boundaries into a pyramid of Mapbox Vector Tiles, in a directory or an MBTiles file.
+    Tiles(TilesOpts),
+    /// Draw the boundaries to an SVG or PNG image, with validation findings in red.
+    Render(RenderOpts),
+    /// Write a table with a row for every FIR and extention, with names and callsign prefixes from VATSpy.dat.
+    Metadata(MetadataOpts),
+    /// Validate a SimAware TRACONBoundaries.json file, and check every TRACON is inside the FIR of its airport.
+    Tracon(TraconOpts),
+    /// Check that every airport in VATSpy.dat is inside the FIR in its FIR field, and fix the field.
+    Airports(AirportsOpts),
+    /// Validate the International Date Line in the [IDL] section of VATSpy.dat, and write it as GeoJSON.
+    Idl(IdlOpts),
+}
+
+#[derive(Clap)]
+#[clap(after_help = BOUNDARY_FILES)]
+pub(crate) struct InfoOpts {
+    /// Input file, any supported boundary file.
+    pub(crate) input: PathBuf,
+    /// Only show FIRs where the ICAO starts with one of these prefixes.
+    #[clap(short, long, multiple_occurrences = true)]
+    pub(crate) filter: Vec<String>,
+    /// Sort by: icao, area, perimeter or vertices.
+    #[clap(short, long, default_value = "icao")]
+    pub(crate) sort: SortKey,
+    /// Sort in descending order.
+    #[clap(short, long)]
+    pub(crate) reverse: bool,
+    /// Output format: table, csv or json.
+    #[clap(long, default_value = "table")]
+    pub(crate) format: OutputFormat,
+    /// Write the result to this file instead of stdout.
+    #[clap(short, long)]
+    pub(crate) output: Option<PathBuf>,
+}
+
+#[derive(Clap)]
+#[clap(after_help = BOUNDARY_FILES)]
+pub(crate) struct SimplifyOpts {
+    /// Input file, any supported boundary file.
+    pub(crate) input: PathBuf,
+    /// File to write the simplified boundaries to, any supported boundary file.
+    pub(crate) output: PathBuf,
+    /// Largest distance in nautical miles a boundary may move.
+    /// For visvalingam points are removed while the triangle they make with their neighbours is smaller than tolerance² NM².
+    #[clap(short, long, default_value = "0.5")]
+    pub(crate) tolerance: f64,
+    /// Algorithm to use: douglas-peucker or visvalingam.
+    #[clap(short, long, default_value = "douglas-peucker")]
+    pub(crate) algorithm: Algorithm,
+}
+
+#[derive(Clap)]
+#[clap(after_help = BOUNDARY_FILES)]
+pub(crate) struct DensifyOpts {
+    /// Input file, any supported boundary file.
+    pub(crate) input: PathBuf,
+    /// File to write the densified boundaries to, any supported boundary file.
+    pub(crate) output: PathBuf,
+    /// Longest allowed edge in nautical miles.
+    #[clap(short, long, default_value = "50")]
+    pub(crate) max_length: f64,
+    /// What the edges are meant to be: great-circle or rhumb.
+    #[clap(short, long, default_value = "great-circle")]
+    pub(crate) line: Line,
+    /// Only densify these FIRs. All FIRs are densified if none are given.
+    #[clap(short, long, multiple_occurrences = true)]
+    pub(crate) icao: Vec<String>,
+}
+
+#[derive(Clap)]
+#[clap(after_help = BOUNDARY_FILES)]
+pub(crate) struct SnapOpts {
+    /// Input file, any supported boundary file.
+    pub(crate) input: PathBuf,
+    /// File to write the healed boundaries to, any supported boundary file.
+    pub(crate) output: PathBuf,
+    /// Largest distance in metres a point is moved.
+    #[clap(short, long, default_value = "10")]
+    pub(crate) tolerance: f64,
+}
+
+#[derive(Clap)]
+#[clap(after_help = BOUNDARY_FILES)]
+pub(crate) struct UirOpts {
+    /// VATSpy.dat with the [FIRs] and [UIRs] sections.
+    pub(crate) vatspy: PathBuf,
+    /// Any supported boundary file.
+    pub(crate) boundaries: PathBuf,
+    /// GeoJSON file to write the UIR outlines to.
+    pub(crate) output: PathBuf,
+    /// Only build these UIRs. All UIRs are built if none are given.
+    #[clap(short, long, multiple_occurrences = true)]
+    pub(crate) uir: Vec<String>,
+}
+
+#[derive(Clap)]
+#[clap(after_help = BOUNDARY_FILES)]
+pub(crate) struct SplitOpts {
+    /// Input file, any supported boundary file.
+    pub(crate) input: PathBuf,
+    /// Directory to write the files and manifest.json to.
+    pub(crate) dir: PathBuf,
+    /// File type of the files: dat, geojson, json, openair, kml, shp, wkt, wkb or topojson.
+    #[clap(
+        short,
+        long,
+        default_value = "dat",
+        possible_values = &["dat", "geojson", "json", "openair", "kml", "shp", "wkt", "wkb", "topojson"]
+    )]
+    pub(crate) format: String,
+}
+
+#[derive(Clap)]
+#[clap(after_help = BOUNDARY_FILES)]
+pub(crate) struct JoinOpts {
+    /// Directory made by split, with manifest.json.
+    pub(crate) dir: PathBuf,
+    /// File to write the joined boundaries to, any supported boundary file.
+    pub(crate) output: PathBuf,
+    /// Fix the files while reading them instead of stopping on validation errors.
+    #[clap(long)]
+    pub(crate) fix: bool,
+}
+
+#[derive(Clap)]
+#[clap(after_help = BOUNDARY_FILES)]
+pub(crate) struct AixmOpts {
+    /// AIXM 5.1 XML file.
+    pub(crate) input: PathBuf,
+    /// File to write the airspaces to, any supported boundary file.
+    /// If this argument is missing only validation will be done.
+    pub(crate) output: Option<PathBuf>,
+    /// Airspace types to import. FIR, UIR and CTA are imported if none are given.
+    #[clap(short, long = "type", multiple_occurrences = true)]
+    pub(crate) types: Vec<String>,
+    /// Largest angle in degrees between points along arcs, as seen from the center of the arc.
+    #[clap(long, default_value = "5")]
+    pub(crate) arc_step: f64,
+}
+
+#[derive(Clap)]
+#[clap(after_help = BOUNDARY_FILES)]
+pub(crate) struct EuroscopeOpts {
+    /// Input file, any supported boundary file.
+    pub(crate) input: PathBuf,
+    /// SCT file to write the [ARTCC HIGH] and [ARTCC LOW] sections to.
+    #[clap(long)]
+    pub(crate) sct: Option<PathBuf>,
+    /// ESE file to write the [AIRSPACE] section with sector lines and sectors to.
+    #[clap(long)]
+    pub(crate) ese: Option<PathBuf>,
+    /// Only write FIRs where the ICAO starts with one of these prefixes.
+    #[clap(short, long, multiple_occurrences = true)]
+    pub(crate) icao: Vec<String>,
+    /// Where FIRs that are not oceanic go: high, low or skip.
+    #[clap(long, default_value = "high")]
+    pub(crate) continental: Placement,
+    /// Where oceanic FIRs go: high, low or skip.
+    #[clap(long, default_value = "low")]
+    pub(crate) oceanic: Placement,
+    /// Where extentions go: same (as their FIR), high, low or skip.
+    #[clap(long, default_value = "same")]
+    pub(crate) extentions: ExtentionPlacement,
+    /// Altitude in feet between low and high sectors in the ESE file.
+    #[clap(long, default_value = "24500")]
+    pub(crate) split_level: u32,
+    /// Top of high sectors in the ESE file, in feet.
+    #[clap(long, default_value = "66000")]
+    pub(crate) ceiling: u32,
+}
+
+#[derive(Clap)]
+#[clap(after_help = BOUNDARY_FILES)]
+pub(crate) struct EseOpts {
+    /// EuroScope ESE file.
+    pub(crate) input: PathBuf,
+    /// File to write the FIRs to, any supported boundary file.
+    /// If this argument is missing only validation will be done.
+    pub(crate) output: Option<PathBuf>,
+    /// A FIR and the sectors making it up, like ENOR=ENOR_N,ENOR_S. A sector ending with * matches
+    /// every sector starting with the rest. If none are given the sectors in the file are listed.
+    #[clap(long, multiple_occurrences = true, number_of_values = 1)]
+    pub(crate) fir: Vec<String>,
+}
+
+#[derive(Clap)]
+#[clap(after_help = BOUNDARY_FILES)]
+pub(crate) struct TilesOpts {
+    /// Input file, any supported boundary file.
+    pub(crate) input: PathBuf,
+    /// Where to write the tiles. An MBTiles file if it ends with .mbtiles,
+    /// otherwise a directory of {z}/{x}/{y}.pbf files with a metadata.json.
+    pub(crate) output: PathBuf,
+    /// Lowest zoom level to render.
+    #[clap(long, default_value = "0")]
+    pub(crate) min_zoom: u8,
+    /// Highest zoom level to render.
+    #[clap(long, default_value = "8")]
+    pub(crate) max_zoom: u8,
+}
+
+#[derive(Clap)]
+#[clap(after_help = BOUNDARY_FILES)]
+pub(crate) struct RenderOpts {
+    /// Input file, any supported boundary file.
+    pub(crate) input: PathBuf,
+    /// Image to draw. This has to end with .svg or .png.
+    pub(crate) output: PathBuf,
+    /// Only draw FIRs where the ICAO starts with one of these prefixes. Use a whole ICAO for a single FIR.
+    #[clap(short, long, multiple_occurrences = true, number_of_values = 1)]
+    pub(crate) icao: Vec<String>,
+    /// Projection: equirectangular, mercator or lambert (azimuthal equal-area centered on the drawn FIRs).
+    #[clap(short, long, default_value = "equirectangular")]
+    pub(crate) projection: Projection,
+    /// Width of the image in pixels, from 16 to 16384. The height follows from the drawn FIRs.
+    #[clap(short, long, default_value = "1200")]
+    pub(crate) width: u32,
+}
+
+#[derive(Clap)]
+#[clap(after_help = BOUNDARY_FILES)]
+pub(crate) struct MetadataOpts {
+    /// Input file, any supported boundary file.
+    pub(crate) input: PathBuf,
+    /// File to write the table to. This has to end with .csv or .json.
+    pub(crate) output: PathBuf,
+    /// VATSpy.dat to take the name and callsign prefix of every FIR from, from the [FIRs] lines using its boundary.
+    #[clap(long)]
+    pub(crate) vatspy: Option<PathBuf>,
+}
+
+#[derive(Clap)]
+#[clap(after_help = BOUNDARY_FILES)]
+pub(crate) struct TraconOpts {
+    /// TRACONBoundaries.json with the TRACONs to validate.
+    pub(crate) input: PathBuf,
+    /// FIR boundary file to check the TRACONs against, needs --vatspy for the airports.
+    /// Any supported boundary file.
+    #[clap(long, requires = "vatspy")]
+    pub(crate) boundaries: Option<PathBuf>,
+    /// VATSpy.dat to find the airport of every TRACON in, from its id or prefixes. Needs --boundaries.
+    #[clap(long, requires = "boundaries")]
+    pub(crate) vatspy: Option<PathBuf>,
+}
+
+#[derive(Clap)]
+#[clap(after_help = BOUNDARY_FILES)]
+pub(crate) struct AirportsOpts {
+    /// VATSpy.dat with the [Airports] and [FIRs] sections.
+    pub(crate) vatspy: PathBuf,
+    /// Any supported boundary file.
+    pub(crate) boundaries: PathBuf,
+    /// If this argument is missing only validation will be done.
+    /// Otherwise VATSpy.dat is written here with the FIR field of every airport set to the FIR containing it.
+    /// Use the same file as the input to fix it in place.
+    pub(crate) output: Option<PathBuf>,
+}
+
+#[derive(Clap)]
+#[clap(after_help = BOUNDARY_FILES)]
+pub(crate) struct IdlOpts {
+    /// VATSpy.dat with the [IDL] section.
+    pub(crate) vatspy: PathBuf,
+    /// GeoJSON file to write the line to, as a LineString feature. This has to end with .geojson or .json.
+    /// If this argument is missing only validation will be done.
+    pub(crate) output: Option<PathBuf>,
+    /// Boundary file with FIRs to write before the line, like a GeoJSON output file.
+    /// Any supported boundary file.
+    #[clap(long)]
+    pub(crate) boundaries: Option<PathBuf>,
 }
@@ -0,0 +1,223 @@
+use std::str::FromStr;
+
+use crate::{
+    fir_boundaries::FIRBoundary,
+    geodesy::{self, Coord, METRES_PER_NM},
+};
+
+/// The kind of line an edge between two boundary points is meant to be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Line {
+    GreatCircle,
+    Rhumb,
+}
+
+impl FromStr for Line {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "great-circle" => Ok(Self::GreatCircle),
+            "rhumb" => Ok(Self::Rhumb),
+            s => Err(format!(
+                "unknown line type: {}, expected one of: great-circle, rhumb",
+                s
+            )),
+        }
+    }
+}
+
+fn to_vector(c: Coord) -> [f64; 3] {
+    let (lat, lon) = (c.lat.to_radians(), c.lon.to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+fn from_vector(v: [f64; 3]) -> Coord {
+    let len = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    Coord {
+        lat: (v[2] / len).asin().to_degrees(),
+        lon: v[1].atan2(v[0]).to_degrees(),
+    }
+}
+
+/// Point at `fraction` along the great circle from `a` to `b`.
+pub(crate) fn great_circle_point(a: Coord, b: Coord, fraction: f64) -> Coord {
+    let (va, vb) = (to_vector(a), to_vector(b));
+    let dot = (va[0] * vb[0] + va[1] * vb[1] + va[2] * vb[2]).clamp(-1.0, 1.0);
+    let angle = dot.acos();
+    if angle.sin().abs() < 1e-12 {
+        return a;
+    }
+    let wa = ((1.0 - fraction) * angle).sin() / angle.sin();
+    let wb = (fraction * angle).sin() / angle.sin();
+    from_vector([
+        wa * va[0] + wb * vb[0],
+        wa * va[1] + wb * vb[1],
+        wa * va[2] + wb * vb[2],
+    ])
+}
+
+fn mercator_y(lat: f64) -> f64 {
+    (std::f64::consts::FRAC_PI_4 + lat.to_radians() / 2.0)
+        .tan()
+        .ln()
+}
+
+/// Point at `fraction` along the rhumb line (constant heading) from `a` to `b`.
+pub(crate) fn rhumb_point(a: Coord, b: Coord, fraction: f64) -> Coord {
+    let mut dlon = b.lon - a.lon;
+    if dlon > 180.0 {
+        dlon -= 360.0;
+    } else if dlon < -180.0 {
+        dlon += 360.0;
+    }
+    let lat = a.lat + (b.lat - a.lat) * fraction;
+    let (ya, yb) = (mercator_y(a.lat), mercator_y(b.lat));
+    // along the line the longitude changes linearly with the mercator y, except when following a parallel
+    let lon = match (yb - ya).abs() < 1e-12 {
+        true => a.lon + dlon * fraction,
+        false => a.lon + dlon * (mercator_y(lat) - ya) / (yb - ya),
+    };
+    Coord { lat, lon }
+}
+
+/// Inserts points along every edge of `fir` longer than `max_length` nautical miles.
+/// Returns the number of points added.
+pub(crate) fn densify_fir(fir: &mut FIRBoundary, max_length: f64, line: Line) -> usize {
+    let fill = fir.polygon_or_hole();
+    let corners = &fir.boundary_corners;
+    let closed = corners.len() > 1 && corners[0] == corners[corners.len() - 1];
+    let n = match closed {
+        true => corners.len() - 1,
+        false => corners.len(),
+    };
+    let mut points = Vec::with_capacity(corners.len());
+    let mut added = 0;
+    for i in 0..n {
+        let (from, to) = (&corners[i], &corners[(i + 1) % corners.len()]);
+        points.push(from.clone());
+        let (a, b) = (Coord::from(from), Coord::from(to));
+        let length = geodesy::distance(a, b) / METRES_PER_NM;
+        let parts = (length / max_length).ceil() as usize;
+        // always interpolate in the same direction, so both neighbours along a border get the same points
+        let (start, end, reversed) = match from < to {
+            true => (a, b, false),
+            false => (b, a, true),
+        };
+        let mut extra: Vec<_> = (1..parts)
+            .map(|k| {
+                let fraction = k as f64 / parts as f64;
+                match line {
+                    Line::GreatCircle => great_circle_point(start, end, fraction),
+                    Line::Rhumb => rhumb_point(start, end, fraction),
+                }
+                .to_point()
+            })
+            .collect();
+        if reversed {
+            extra.reverse();
+        }
+        extra.dedup();
+        extra.retain(|p| p != from && p != to);
+        added += extra.len();
+        points.extend(extra);
+    }
+    if closed {
+        points.push(points[0].clone());
+    }
+    fir.boundary_corners = points;
+    if fir.polygon_or_hole() != fill {
+        fir.boundary_corners.reverse();
+    }
+    fir.update_bounds();
+    added
+}
+
+/// Densifies the FIRs with one of the given ICAO codes, or all FIRs if `icaos` is empty.
+/// Returns the ICAO and number of added points of every FIR that changed.
+pub(crate) fn densify(
+    firs: &mut [FIRBoundary],
+    max_length: f64,
+    line: Line,
+    icaos: &[String],
+) -> Vec<(String, usize)> {
+    firs.iter_mut()
+        .filter(|fir| icaos.is_empty() || icaos.contains(&fir.icao))
+        .map(|fir| (fir.icao.clone(), densify_fir(fir, max_length, line)))
+        .filter(|(_, added)| *added > 0)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fir_boundaries::{fir, Fill};
+
+    #[test]
+    fn great_circle_goes_north_of_parallel() {
+        let a = Coord {
+            lat: 60.0,
+            lon: -40.0,
+        };
+        let b = Coord {
+            lat: 60.0,
+            lon: 0.0,
+        };
+        let middle = great_circle_point(a, b, 0.5);
+        assert!((middle.lat - 61.5).abs() < 0.1, "{:?}", middle);
+        assert!((middle.lon + 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rhumb_line_along_parallel() {
+        let a = Coord {
+            lat: 60.0,
+            lon: -40.0,
+        };
+        let b = Coord {
+            lat: 60.0,
+            lon: 0.0,
+        };
+        let middle = rhumb_point(a, b, 0.5);
+        assert!((middle.lat - 60.0).abs() < 1e-9);
+        assert!((middle.lon + 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rhumb_line_across_antimeridian() {
+        let a = Coord {
+            lat: 0.0,
+            lon: 170.0,
+        };
+        let b = Coord {
+            lat: 10.0,
+            lon: -170.0,
+        };
+        let middle = rhumb_point(a, b, 0.5);
+        assert!((geodesy::normalize_lon(middle.lon) - 180.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn densify_keeps_corners_and_direction() {
+        // every edge is about 120 NM, so two points are added to each
+        let mut ring = fir("ZZZZ", false, &[(0, 0), (2, 0), (2, 2), (0, 2)]);
+        let corners = ring.boundary_corners.clone();
+        let fill = ring.polygon_or_hole();
+        assert_eq!(fill, Fill::Polygon);
+
+        let added = densify_fir(&mut ring, 50.0, Line::GreatCircle);
+        assert_eq!(added, 8);
+        assert_eq!(ring.boundary_corners.len(), corners.len() + added);
+        assert_eq!(ring.polygon_or_hole(), fill);
+        let kept: Vec<_> = ring
+            .boundary_corners
+            .iter()
+            .filter(|p| corners.contains(p))
+            .cloned()
+            .collect();
+        assert_eq!(kept, corners);
+        assert_eq!(ring.boundary_corners[0], corners[0]);
+
+        assert_eq!(densify_fir(&mut ring, 50.0, Line::GreatCircle), 0);
+    }
+}
@@ -0,0 +1,266 @@
+use std::fmt::{self, Display};
+
+/// An angle in degrees, minutes and seconds, with the sign kept apart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Dms {
+    pub(crate) negative: bool,
+    pub(crate) degrees: u64,
+    pub(crate) minutes: u64,
+    pub(crate) seconds: f64,
+}
+
+impl Dms {
+    /// Splits an angle in degrees, rounded to `decimals` decimals of a second.
+    /// Rounding carries over, so 59.9999 seconds never shows up as 60.
+    pub(crate) fn new(value: f64, decimals: u32) -> Self {
+        let scale = 10u64.pow(decimals);
+        let total = (value.abs() * 3600.0 * scale as f64).round() as u64;
+        Self {
+            negative: value < 0.0 && total != 0,
+            degrees: total / (3600 * scale),
+            minutes: total % (3600 * scale) / (60 * scale),
+            seconds: (total % (60 * scale)) as f64 / scale as f64,
+        }
+    }
+
+    pub(crate) fn hemisphere(&self, positive: char, negative: char) -> char {
+        match self.negative {
+            true => negative,
+            false => positive,
+        }
+    }
+}
+
+/// Writes a latitude the way EuroScope sector files do: `N059.30.00.000`.
+pub(crate) fn euroscope_lat(lat: f64) -> String {
+    euroscope(lat, 'N', 'S')
+}
+
+/// Writes a longitude the way EuroScope sector files do: `E010.30.00.000`.
+pub(crate) fn euroscope_lon(lon: f64) -> String {
+    euroscope(lon, 'E', 'W')
+}
+
+fn euroscope(value: f64, positive: char, negative: char) -> String {
+    let dms = Dms::new(value, 3);
+    format!(
+        "{}{:03}.{:02}.{:06.3}",
+        dms.hemisphere(positive, negative),
+        dms.degrees,
+        dms.minutes,
+        dms.seconds
+    )
+}
+
+/// Reads a latitude or longitude written the way EuroScope sector files do: `N059.30.00.000`.
+pub(crate) fn parse_euroscope(s: &str) -> Option<f64> {
+    let mut chars = s.trim().chars();
+    let hemisphere = chars.next()?.to_ascii_uppercase();
+    let parts: Vec<&str> = chars.as_str().splitn(3, '.').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    let degrees: f64 = parts[0].parse().ok()?;
+    let minutes: f64 = parts[1].parse().ok()?;
+    let seconds: f64 = parts[2].parse().ok()?;
+    if minutes >= 60.0 || seconds >= 60.0 {
+        return None;
+    }
+    let value = degrees + minutes / 60.0 + seconds / 3600.0;
+    match hemisphere {
+        'N' | 'E' => Some(value),
+        'S' | 'W' => Some(-value),
+        _ => None,
+    }
+}
+
+/// The ways a latitude or longitude can be written in FIRBoundaries.dat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Notation {
+    /// `51.5`, the only notation VATSpy reads.
+    Decimal,
+    /// `51,5`
+    DecimalComma,
+    /// `N051.30.00.000`
+    EuroScope,
+    /// `513000N` or `5130N`, degrees, minutes and maybe seconds without separators.
+    Compact,
+    /// `51°30'00"N`
+    Symbols,
+}
+
+impl Display for Notation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Notation::Decimal => write!(f, "decimal degrees"),
+            Notation::DecimalComma => write!(f, "decimal degrees with a decimal comma"),
+            Notation::EuroScope => write!(f, "EuroScope degrees, minutes and seconds"),
+            Notation::Compact => write!(f, "degrees, minutes and seconds without separators"),
+            Notation::Symbols => write!(f, "degrees, minutes and seconds with symbols"),
+        }
+    }
+}
+
+/// An angle read in degrees, minutes and seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Angle {
+    /// Degrees, negative to the south and west.
+    pub(crate) value: f64,
+    pub(crate) notation: Notation,
+    /// N, S, E or W.
+    pub(crate) hemisphere: char,
+}
+
+/// Takes the hemisphere letter off the start or end of `s`.
+fn split_hemisphere(s: &str) -> Option<(char, &str)> {
+    let is_hemisphere = |c: char| matches!(c.to_ascii_uppercase(), 'N' | 'S' | 'E' | 'W');
+    let first = s.chars().next()?;
+    let last = s.chars().last()?;
+    if is_hemisphere(first) {
+        Some((first.to_ascii_uppercase(), s[first.len_utf8()..].trim()))
+    } else if is_hemisphere(last) {
+        Some((
+            last.to_ascii_uppercase(),
+            s[..s.len() - last.len_utf8()].trim(),
+        ))
+    } else {
+        None
+    }
+}
+
+fn angle(
+    degrees: f64,
+    minutes: f64,
+    seconds: f64,
+    hemisphere: char,
+    notation: Notation,
+) -> Option<Angle> {
+    if degrees > 180.0 || minutes >= 60.0 || seconds >= 60.0 {
+        return None;
+    }
+    let value = degrees + minutes / 60.0 + seconds / 3600.0;
+    Some(Angle {
+        value: match hemisphere {
+            'S' | 'W' => -value,
+            _ => value,
+        },
+        notation,
+        hemisphere,
+    })
+}
+
+/// `513000N`, `0001000.5W` or `5130N`: the number of digits before any decimals tells
+/// whether seconds are there, with 2 digits of degrees for latitudes and 3 for longitudes.
+fn parse_compact(digits: &str, hemisphere: char) -> Option<Angle> {
+    let (whole, fraction) = match digits.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (digits, None),
+    };
+    if !whole.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let degree_digits = match hemisphere {
+        'N' | 'S' => 2,
+        _ => 3,
+    };
+    let rest = whole.len().checked_sub(degree_digits)?;
+    if rest != 2 && rest != 4 {
+        return None;
+    }
+    let last = match fraction {
+        Some(fraction) => format!("{}.{}", &whole[whole.len() - 2..], fraction),
+        None => whole[whole.len() - 2..].to_string(),
+    };
+    let degrees: f64 = whole[..degree_digits].parse().ok()?;
+    match rest {
+        2 => angle(
+            degrees,
+            last.parse().ok()?,
+            0.0,
+            hemisphere,
+            Notation::Compact,
+        ),
+        _ => angle(
+            degrees,
+            whole[degree_digits..degree_digits + 2].parse().ok()?,
+            last.parse().ok()?,
+            hemisphere,
+            Notation::Compact,
+        ),
+    }
+}
+
+/// `51°30'00"N`, `N 51° 30.5'` and the like, with the numbers separated by any symbols or spaces.
+fn parse_symbols(s: &str, hemisphere: char) -> Option<Angle> {
+    let numbers: Vec<f64> = s
+        .split(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+        .filter(|n| !n.is_empty())
+        .map(|n| n.replace(',', ".").parse())
+        .collect::<Result<_, _>>()
+        .ok()?;
+    match numbers[..] {
+        [d] => angle(d, 0.0, 0.0, hemisphere, Notation::Symbols),
+        [d, m] => angle(d, m, 0.0, hemisphere, Notation::Symbols),
+        [d, m, s] => angle(d, m, s, hemisphere, Notation::Symbols),
+        _ => None,
+    }
+}
+
+/// Reads a latitude or longitude in degrees, minutes and seconds, in any notation with a hemisphere letter.
+pub(crate) fn parse_angle(s: &str) -> Option<Angle> {
+    let s = s.trim();
+    let (hemisphere, rest) = split_hemisphere(s)?;
+    if let Some(value) = parse_euroscope(s) {
+        return Some(Angle {
+            value,
+            notation: Notation::EuroScope,
+            hemisphere,
+        });
+    }
+    if rest.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        // N51.5 is decimal degrees with a hemisphere letter
+        return parse_compact(rest, hemisphere).or_else(|| parse_symbols(rest, hemisphere));
+    }
+    parse_symbols(rest, hemisphere)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn euroscope_format() {
+        assert_eq!(euroscope_lat(59.5), "N059.30.00.000");
+        assert_eq!(euroscope_lon(-10.504167), "W010.30.15.001");
+        // rounds up into the next minute instead of writing 60 seconds
+        assert_eq!(euroscope_lat(-0.0166666), "S000.01.00.000");
+    }
+
+    #[test]
+    fn euroscope_round_trip() {
+        for value in [59.5, -10.504167, 0.0, 179.999999] {
+            let parsed = parse_euroscope(&euroscope_lon(value)).unwrap();
+            assert!((parsed - value).abs() < 1e-6, "{} {}", value, parsed);
+        }
+        assert_eq!(parse_euroscope("X059.30.00.000"), None);
+        assert_eq!(parse_euroscope("N059.30"), None);
+    }
+
+    #[test]
+    fn notations() {
+        let read = |s| parse_angle(s).map(|a| (a.value, a.notation, a.hemisphere));
+        assert_eq!(
+            read("N051.30.00.000"),
+            Some((51.5, Notation::EuroScope, 'N'))
+        );
+        assert_eq!(read("513000N"), Some((51.5, Notation::Compact, 'N')));
+        assert_eq!(read("0001000W"), Some((-1.0 / 6.0, Notation::Compact, 'W')));
+        assert_eq!(read("5130S"), Some((-51.5, Notation::Compact, 'S')));
+        assert_eq!(read("51°30'00\"N"), Some((51.5, Notation::Symbols, 'N')));
+        assert_eq!(read("E 10° 30,0'"), Some((10.5, Notation::Symbols, 'E')));
+        assert_eq!(read("N51.5"), Some((51.5, Notation::Symbols, 'N')));
+        assert_eq!(read("51.5"), None);
+        assert_eq!(read("516000N"), None);
+        assert_eq!(read("51300N"), None);
+    }
+}
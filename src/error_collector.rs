@@ -3,7 +3,6 @@ use std::{
     fmt::{self, Display},
 };
 
-use color_eyre::eyre::{self, Report};
 use itertools::Itertools;
 
 use crate::fir_boundaries::{FIRParsingError, FIRResult};
@@ -20,6 +19,7 @@ impl ErrorCollector {
         Self { errors: Vec::new() }
     }
 
+    #[allow(dead_code)]
     pub fn addresult<T, E>(&mut self, r: Result<T, E>) -> FIRResult<Option<T>>
     where
         E: Into<FIRParsingError>,
@@ -45,6 +45,10 @@ impl ErrorCollector {
         self.errors.extend(others.errors)
     }
 
+    pub fn into_errors(self) -> Vec<FIRParsingError> {
+        self.errors
+    }
+
     #[allow(clippy::wrong_self_convention)]
     pub fn to_col_result<T>(self, t: T) -> ColResult<T> {
         match self.errors.is_empty() {
@@ -0,0 +1,443 @@
+use std::{
+    fmt::{self, Display},
+    fs,
+    path::Path,
+};
+
+use indexmap::IndexMap;
+
+use crate::{
+    dms::parse_euroscope,
+    fir_boundaries::{with_extentions, FIRBoundary, FIRParsingError, FIRResult, Point},
+    geodesy::{self, Coord, METRES_PER_NM},
+    lable,
+    openair::ARC_STEP,
+    uir,
+};
+
+// format of the [AIRSPACE] section, fields separated by :
+// SECTORLINE:name
+// COORD:N059.30.00.000:E010.30.00.000          one per point of the line
+// CIRCLE_SECTORLINE:name:lat:lon:radius         a closed circle, radius in NM
+// CIRCLE_SECTORLINE:name:fix:radius             a circle around a fix or airport, which is not defined in the file
+// SECTOR:name:floor:ceiling
+// BORDER:line:line:...                          the lines making up the sector, joined end to end
+// other lines like DISPLAY, OWNER, ALTOWNER and ACTIVE are not needed here
+
+/// The sector lines and sectors of an ESE file.
+#[derive(Debug, Default)]
+pub(crate) struct Ese {
+    /// Every sector line as its points.
+    pub(crate) lines: IndexMap<String, Vec<Point>>,
+    /// Every sector with the names of the lines of its border.
+    pub(crate) sectors: IndexMap<String, Vec<String>>,
+    /// Circles around a named fix, with the line they are on. They can only be used once the fix can be looked up.
+    pub(crate) unresolved: IndexMap<String, usize>,
+}
+
+/// Problems found while building FIRs from sectors.
+#[derive(Debug, Clone)]
+pub(crate) enum Diagnostic {
+    /// No sector matches a name given for a FIR.
+    UnknownSector { icao: String, sector: String },
+    /// The border of a sector uses a line that is not defined.
+    MissingLine { sector: String, line: String },
+    /// The lines of the border of a sector do not join into a closed ring.
+    OpenBorder { sector: String },
+    /// The sectors of a FIR leave holes in it.
+    Holes { icao: String, holes: usize },
+    /// Borders between sectors that are not drawn with the same points, leaving thin slivers.
+    Slivers { icao: String, slivers: usize },
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Diagnostic::UnknownSector { icao, sector } => {
+                write!(f, "{}: no sector matches {}", icao, sector)
+            }
+            Diagnostic::MissingLine { sector, line } => {
+                write!(f, "{}: border line {} is not defined", sector, line)
+            }
+            Diagnostic::OpenBorder { sector } => write!(
+                f,
+                "{}: the border lines do not join into a closed ring, the sector is left out",
+                sector
+            ),
+            Diagnostic::Holes { icao, holes } => {
+                write!(
+                    f,
+                    "{}: the sectors leave {} hole(s), they are left out",
+                    icao, holes
+                )
+            }
+            Diagnostic::Slivers { icao, slivers } => write!(
+                f,
+                "{}: {} border(s) between sectors do not share the same points",
+                icao, slivers
+            ),
+        }
+    }
+}
+
+const CIRCLE_FIELDS: &str =
+    "CIRCLE_SECTORLINE needs a name, a latitude and longitude or a fix, and a radius";
+
+fn ese_error<S: AsRef<str>>(linenr: usize, s: S) -> FIRParsingError {
+    FIRParsingError::FIRParsing(format!("ESE line: {}, {}", linenr, s.as_ref()))
+}
+
+fn coord(lat: &str, lon: &str, linenr: usize) -> FIRResult<Coord> {
+    match (parse_euroscope(lat), parse_euroscope(lon)) {
+        (Some(lat), Some(lon)) if lat.abs() <= 90.0 && lon.abs() <= 180.0 => Ok(Coord { lat, lon }),
+        _ => Err(ese_error(
+            linenr,
+            format!(
+                "not a coordinate: {}:{}, only coordinates and no fix names are supported",
+                lat, lon
+            ),
+        )),
+    }
+}
+
+impl Ese {
+    pub(crate) fn parse(content: &str) -> FIRResult<Self> {
+        let mut ese = Self::default();
+        let mut section = String::new();
+        let mut line_name: Option<String> = None;
+        let mut sector_name: Option<String> = None;
+        for (n, line) in content.lines().enumerate() {
+            let linenr = n + 1;
+            let line = line.split(';').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                section = line[1..line.len() - 1].to_uppercase();
+                continue;
+            }
+            if section != "AIRSPACE" {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(':').map(str::trim).collect();
+            match fields[0] {
+                "SECTORLINE" => {
+                    let name = fields.get(1).unwrap_or(&"").to_string();
+                    ese.lines.insert(name.clone(), Vec::new());
+                    line_name = Some(name);
+                    sector_name = None;
+                }
+                "CIRCLE_SECTORLINE" if fields.len() == 5 => {
+                    let center = coord(fields[2], fields[3], linenr)?;
+                    let radius: f64 = fields[4]
+                        .parse()
+                        .map_err(|_| ese_error(linenr, format!("not a radius: {}", fields[4])))?;
+                    let circle =
+                        geodesy::arc_points(center, radius * METRES_PER_NM, 0.0, 360.0, ARC_STEP)?;
+                    let mut points: Vec<Point> = circle.iter().map(|c| c.to_point()).collect();
+                    points.dedup();
+                    // the last point is the first one again, apart from rounding
+                    points.pop();
+                    if points.len() < 3 {
+                        return Err(ese_error(linenr, "circle with no radius"));
+                    }
+                    points.push(points[0].clone());
+                    ese.lines.insert(fields[1].to_string(), points);
+                    line_name = None;
+                    sector_name = None;
+                }
+                "CIRCLE_SECTORLINE" if fields.len() == 4 => {
+                    ese.unresolved.insert(fields[1].to_string(), linenr);
+                    line_name = None;
+                    sector_name = None;
+                }
+                "CIRCLE_SECTORLINE" => return Err(ese_error(linenr, CIRCLE_FIELDS)),
+                "COORD" => {
+                    let name = line_name
+                        .as_ref()
+                        .ok_or_else(|| ese_error(linenr, "COORD outside of a SECTORLINE"))?;
+                    if fields.len() < 3 {
+                        return Err(ese_error(
+                            linenr,
+                            format!("COORD needs a latitude and longitude: {}", line),
+                        ));
+                    }
+                    let p = coord(fields[1], fields[2], linenr)?.to_point();
+                    ese.lines.get_mut(name).unwrap().push(p);
+                }
+                "SECTOR" => {
+                    let name = fields.get(1).unwrap_or(&"").to_string();
+                    ese.sectors.insert(name.clone(), Vec::new());
+                    sector_name = Some(name);
+                    line_name = None;
+                }
+                "BORDER" => {
+                    let name = sector_name
+                        .as_ref()
+                        .ok_or_else(|| ese_error(linenr, "BORDER outside of a SECTOR"))?;
+                    ese.sectors.get_mut(name).unwrap().extend(
+                        fields[1..]
+                            .iter()
+                            .filter(|f| !f.is_empty())
+                            .map(|f| f.to_string()),
+                    );
+                }
+                _ => (),
+            }
+        }
+        Ok(ese)
+    }
+
+    /// Joins the border lines of a sector end to end into a ring, turning lines around where needed.
+    /// A border using a circle around a fix is an error, as the circle can not be drawn.
+    pub(crate) fn sector_ring(
+        &self,
+        sector: &str,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> FIRResult<Option<Vec<Point>>> {
+        let names = match self.sectors.get(sector) {
+            Some(names) => names,
+            None => return Ok(None),
+        };
+        if let Some((name, linenr)) = names.iter().find_map(|n| self.unresolved.get_key_value(n)) {
+            return Err(ese_error(
+                *linenr,
+                format!(
+                    "sector {} uses CIRCLE_SECTORLINE {} around a fix, which is not supported",
+                    sector, name
+                ),
+            ));
+        }
+        let mut lines = Vec::new();
+        for name in names {
+            match self.lines.get(name) {
+                Some(line) if !line.is_empty() => lines.push(line.clone()),
+                _ => diagnostics.push(Diagnostic::MissingLine {
+                    sector: sector.to_string(),
+                    line: name.clone(),
+                }),
+            }
+        }
+        let open = || Diagnostic::OpenBorder {
+            sector: sector.to_string(),
+        };
+        if lines.is_empty() {
+            diagnostics.push(open());
+            return Ok(None);
+        }
+        let mut ring = lines.remove(0);
+        while !lines.is_empty() {
+            let end = ring.last().unwrap().clone();
+            let next = lines
+                .iter()
+                .position(|l| l[0] == end || l[l.len() - 1] == end);
+            let mut line = match next {
+                Some(n) => lines.remove(n),
+                None => {
+                    diagnostics.push(open());
+                    return Ok(None);
+                }
+            };
+            if line[0] != end {
+                line.reverse();
+            }
+            ring.extend(line.into_iter().skip(1));
+        }
+        ring.dedup();
+        if ring.len() < 4 || ring[0] != ring[ring.len() - 1] {
+            diagnostics.push(open());
+            return Ok(None);
+        }
+        ring.pop();
+        Ok(Some(ring))
+    }
+
+    /// Names of the sectors matching a name, or a prefix when it ends with *.
+    pub(crate) fn matching(&self, pattern: &str) -> Vec<&str> {
+        self.sectors
+            .keys()
+            .map(String::as_str)
+            .filter(|name| match pattern.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => *name == pattern,
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn read_file<P: AsRef<Path>>(p: P) -> FIRResult<Ese> {
+    Ese::parse(&fs::read_to_string(p)?)
+}
+
+/// Builds a FIR for every entry of `firs`, an ICAO with the names of the sectors making it up.
+/// The sectors are joined into their outline, where the largest area becomes the FIR
+/// and the rest its extentions. Holes are left out.
+pub(crate) fn build_firs(
+    ese: &Ese,
+    firs: &[(String, Vec<String>)],
+) -> FIRResult<(Vec<FIRBoundary>, Vec<Diagnostic>)> {
+    let mut diagnostics = Vec::new();
+    let mut result = Vec::new();
+    for (icao, patterns) in firs {
+        let mut rings = Vec::new();
+        for pattern in patterns {
+            let sectors = ese.matching(pattern);
+            if sectors.is_empty() {
+                diagnostics.push(Diagnostic::UnknownSector {
+                    icao: icao.clone(),
+                    sector: pattern.clone(),
+                });
+            }
+            for sector in sectors {
+                rings.extend(ese.sector_ring(sector, &mut diagnostics)?);
+            }
+        }
+        let outline = uir::union(&rings);
+        if !outline.holes.is_empty() {
+            diagnostics.push(Diagnostic::Holes {
+                icao: icao.clone(),
+                holes: outline.holes.len(),
+            });
+        }
+        if outline.slivers > 0 {
+            diagnostics.push(Diagnostic::Slivers {
+                icao: icao.clone(),
+                slivers: outline.slivers,
+            });
+        }
+        let mut outer = outline.outer;
+        outer.sort_by(|a, b| {
+            geodesy::area(&geodesy::ring_coords(b))
+                .partial_cmp(&geodesy::area(&geodesy::ring_coords(a)))
+                .unwrap()
+        });
+        for (n, corners) in outer.into_iter().enumerate() {
+            let mut fir = FIRBoundary {
+                id: result.len(),
+                icao: icao.clone(),
+                is_oseanic: false,
+                is_extension: n != 0,
+                min_lat: Default::default(),
+                min_lon: Default::default(),
+                max_lat: Default::default(),
+                max_lon: Default::default(),
+                lable: corners[0].clone(),
+                boundary_corners: corners,
+            };
+            fir.update_bounds();
+            result.push(fir);
+        }
+    }
+    for group in with_extentions(&result) {
+        let lable = lable::group_lable(&result, &group);
+        for n in group {
+            result[n].lable = lable.clone();
+        }
+    }
+    Ok((result, diagnostics))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // two sectors sharing the line MID, written in different directions and orders
+    const ESE: &str = "[SECTORLINE]
+COORD:X:Y
+
+[AIRSPACE]
+SECTORLINE:WEST
+COORD:N000.00.00.000:E000.00.00.000
+COORD:N000.00.00.000:E001.00.00.000
+
+SECTORLINE:MID
+COORD:N000.00.00.000:E001.00.00.000
+COORD:N001.00.00.000:E001.00.00.000
+
+SECTORLINE:NORTHWEST ; a comment
+COORD:N001.00.00.000:E001.00.00.000
+COORD:N001.00.00.000:E000.00.00.000
+COORD:N000.00.00.000:E000.00.00.000
+
+SECTORLINE:EAST
+COORD:N001.00.00.000:E001.00.00.000
+COORD:N001.00.00.000:E002.00.00.000
+COORD:N000.00.00.000:E002.00.00.000
+COORD:N000.00.00.000:E001.00.00.000
+
+SECTOR:XXXX_W:0:66000
+OWNER:XXXX
+BORDER:WEST:NORTHWEST:MID
+
+SECTOR:XXXX_E:0:66000
+BORDER:EAST:MID
+";
+
+    #[test]
+    fn sectors_joined() {
+        let ese = Ese::parse(ESE).unwrap();
+        assert_eq!(ese.matching("XXXX_*"), vec!["XXXX_W", "XXXX_E"]);
+        let mut diagnostics = Vec::new();
+        assert_eq!(
+            ese.sector_ring("XXXX_W", &mut diagnostics)
+                .unwrap()
+                .unwrap()
+                .len(),
+            4
+        );
+        assert!(diagnostics.is_empty());
+
+        let (firs, diagnostics) = build_firs(
+            &ese,
+            &[(
+                "XXXX".to_string(),
+                vec!["XXXX_*".to_string(), "YYYY".to_string()],
+            )],
+        )
+        .unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(firs.len(), 1);
+        // the shared border is gone, leaving the corners and the ends of the shared line
+        assert_eq!(firs[0].boundary_corners.len(), 6);
+        assert!(
+            crate::fir_boundaries::polygon_or_hole(&firs[0].boundary_corners)
+                == crate::fir_boundaries::Fill::Polygon
+        );
+        assert_eq!(firs[0].max_lon, 2.into());
+    }
+
+    #[test]
+    fn fix_names_are_errors() {
+        let err = Ese::parse("[AIRSPACE]\nSECTORLINE:A\nCOORD:GM:N000.00.00.000\n").unwrap_err();
+        assert!(err.to_string().contains("ESE line: 3"));
+    }
+
+    #[test]
+    fn circle_without_radius() {
+        let err = Ese::parse("[AIRSPACE]\nCIRCLE_SECTORLINE:C1:N059.00.00.000:E010.00.00.000:0\n")
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("ESE line: 2, circle with no radius"));
+        let ese = Ese::parse("[AIRSPACE]\nCIRCLE_SECTORLINE:C1:N059.00.00.000:E010.00.00.000:5\n")
+            .unwrap();
+        assert!(ese.lines["C1"].len() > 3);
+    }
+
+    #[test]
+    fn circles_around_fixes() {
+        let ese = format!(
+            "{}\nCIRCLE_SECTORLINE:ENGM_CTR:ENGM:8\n\nSECTOR:ENGM:0:2500\nBORDER:ENGM_CTR\n",
+            ESE
+        );
+        let ese = Ese::parse(&ese).unwrap();
+        assert_eq!(ese.unresolved["ENGM_CTR"], 31);
+        let (firs, _) =
+            build_firs(&ese, &[("XXXX".to_string(), vec!["XXXX_*".to_string()])]).unwrap();
+        assert_eq!(firs.len(), 1);
+        let err = build_firs(&ese, &[("ENGM".to_string(), vec!["ENGM".to_string()])]).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("ESE line: 31, sector ENGM uses CIRCLE_SECTORLINE ENGM_CTR around a fix"));
+    }
+}
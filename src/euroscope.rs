@@ -0,0 +1,265 @@
+use std::{
+    fmt::{self, Display},
+    io::{self, Write},
+    str::FromStr,
+};
+
+use crate::{
+    dms::{euroscope_lat, euroscope_lon},
+    fir_boundaries::{with_extentions, FIRBoundary, Point},
+    geodesy::Coord,
+    topology::open_ring,
+};
+
+/// Where a kind of FIR goes in the sector file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Placement {
+    /// [ARTCC HIGH], and an ESE sector from the split level up.
+    High,
+    /// [ARTCC LOW], and an ESE sector from the ground to the split level.
+    Low,
+    /// Left out.
+    Skip,
+}
+
+impl FromStr for Placement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "high" => Ok(Placement::High),
+            "low" => Ok(Placement::Low),
+            "skip" => Ok(Placement::Skip),
+            _ => Err(format!("Unknown placement: {}, use high, low or skip", s)),
+        }
+    }
+}
+
+impl Display for Placement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Placement::High => write!(f, "ARTCC HIGH"),
+            Placement::Low => write!(f, "ARTCC LOW"),
+            Placement::Skip => Ok(()),
+        }
+    }
+}
+
+/// Where extentions go in the sector file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ExtentionPlacement {
+    /// The same place as their FIR.
+    Same,
+    Other(Placement),
+}
+
+impl FromStr for ExtentionPlacement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "same" => Ok(ExtentionPlacement::Same),
+            _ => s
+                .parse()
+                .map(ExtentionPlacement::Other)
+                .map_err(|_| format!("Unknown placement: {}, use same, high, low or skip", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Options {
+    /// Only FIRs where the ICAO starts with one of these, or all if empty.
+    pub(crate) icao: Vec<String>,
+    pub(crate) continental: Placement,
+    pub(crate) oceanic: Placement,
+    pub(crate) extentions: ExtentionPlacement,
+    /// Altitude in feet between low and high sectors in the ESE file.
+    pub(crate) split_level: u32,
+    /// Top of high sectors in the ESE file.
+    pub(crate) ceiling: u32,
+}
+
+/// A FIR or extention as it ends up in the sector file.
+struct Sector<'a> {
+    /// Name of the SCT lines and owner of the ESE sector.
+    name: String,
+    /// Name of the ESE sector line and sector, unique for every part.
+    part: String,
+    placement: Placement,
+    corners: Vec<Point>,
+    fir: &'a FIRBoundary,
+}
+
+fn sectors<'a>(firs: &'a [FIRBoundary], opts: &Options) -> Vec<Sector<'a>> {
+    let mut sectors = Vec::new();
+    for group in with_extentions(firs) {
+        let fir = &firs[group[0]];
+        if !opts.icao.is_empty() && !opts.icao.iter().any(|i| fir.icao.starts_with(i.as_str())) {
+            continue;
+        }
+        let placement = match fir.is_oseanic {
+            true => opts.oceanic,
+            false => opts.continental,
+        };
+        let name = match fir.is_oseanic {
+            true => format!("{}_OCA", fir.icao),
+            false => fir.icao.clone(),
+        };
+        for (n, index) in group.iter().enumerate() {
+            let part = &firs[*index];
+            let placement = match (part.is_extension, opts.extentions) {
+                (true, ExtentionPlacement::Other(p)) => p,
+                _ => placement,
+            };
+            if placement == Placement::Skip {
+                continue;
+            }
+            sectors.push(Sector {
+                name: name.clone(),
+                part: match n {
+                    0 => name.clone(),
+                    n => format!("{}_{}", name, n),
+                },
+                placement,
+                corners: open_ring(&part.boundary_corners),
+                fir: part,
+            });
+        }
+    }
+    sectors
+}
+
+fn coordinate(p: &Point, separator: &str) -> String {
+    let c = Coord::from(p);
+    format!(
+        "{}{}{}",
+        euroscope_lat(c.lat),
+        separator,
+        euroscope_lon(c.lon)
+    )
+}
+
+/// Writes the [ARTCC HIGH] and [ARTCC LOW] sections of a SCT file, one line per edge.
+pub(crate) fn write_sct<W: Write>(
+    firs: &[FIRBoundary],
+    opts: &Options,
+    writer: &mut W,
+) -> io::Result<()> {
+    let sectors = sectors(firs, opts);
+    for placement in [Placement::High, Placement::Low] {
+        let mut in_section = sectors
+            .iter()
+            .filter(|s| s.placement == placement)
+            .peekable();
+        if in_section.peek().is_none() {
+            continue;
+        }
+        writeln!(writer, "[{}]", placement)?;
+        for sector in in_section {
+            let corners = &sector.corners;
+            for (a, b) in corners.iter().zip(corners.iter().cycle().skip(1)) {
+                writeln!(
+                    writer,
+                    "{:<16} {} {}",
+                    sector.name,
+                    coordinate(a, " "),
+                    coordinate(b, " ")
+                )?;
+            }
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Writes an [AIRSPACE] section of an ESE file with a closed SECTORLINE and a SECTOR for every part.
+/// Sectors are owned by the FIR, and reach from the ground to the split level or from there to the ceiling.
+pub(crate) fn write_ese<W: Write>(
+    firs: &[FIRBoundary],
+    opts: &Options,
+    writer: &mut W,
+) -> io::Result<()> {
+    let sectors = sectors(firs, opts);
+    writeln!(writer, "[AIRSPACE]")?;
+    for sector in &sectors {
+        writeln!(writer)?;
+        writeln!(writer, "SECTORLINE:{}", sector.part)?;
+        for p in sector.corners.iter().chain(sector.corners.first()) {
+            writeln!(writer, "COORD:{}", coordinate(p, ":"))?;
+        }
+    }
+    for sector in &sectors {
+        let (floor, ceiling) = match sector.placement {
+            Placement::Low => (0, opts.split_level),
+            _ => (opts.split_level, opts.ceiling),
+        };
+        writeln!(writer)?;
+        match sector.fir.is_extension {
+            true => writeln!(writer, "; extention of {}", sector.name)?,
+            false => writeln!(writer, "; {}", sector.name)?,
+        }
+        writeln!(writer, "SECTOR:{}:{}:{}", sector.part, floor, ceiling)?;
+        writeln!(writer, "OWNER:{}", sector.name)?;
+        writeln!(writer, "BORDER:{}", sector.part)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fir_boundaries;
+
+    fn fir(icao: &str, is_oseanic: bool, is_extension: bool) -> FIRBoundary {
+        let mut fir = fir_boundaries::fir(icao, is_extension, &[(0, 0), (1, 0), (1, 1)]);
+        fir.is_oseanic = is_oseanic;
+        fir
+    }
+
+    #[test]
+    fn placements() {
+        let firs = vec![
+            fir("ENOR", false, false),
+            fir("ENOR", false, true),
+            fir("ENOB", true, false),
+            fir("ESAA", false, false),
+        ];
+        let opts = Options {
+            icao: vec!["EN".to_string()],
+            continental: Placement::High,
+            oceanic: Placement::Low,
+            extentions: ExtentionPlacement::Other(Placement::Skip),
+            split_level: 24500,
+            ceiling: 66000,
+        };
+        let mut sct = Vec::new();
+        write_sct(&firs, &opts, &mut sct).unwrap();
+        let sct = String::from_utf8(sct).unwrap();
+        assert_eq!(
+            sct,
+            "[ARTCC HIGH]
+ENOR             N000.00.00.000 E000.00.00.000 N001.00.00.000 E000.00.00.000
+ENOR             N001.00.00.000 E000.00.00.000 N001.00.00.000 E001.00.00.000
+ENOR             N001.00.00.000 E001.00.00.000 N000.00.00.000 E000.00.00.000
+
+[ARTCC LOW]
+ENOB_OCA         N000.00.00.000 E000.00.00.000 N001.00.00.000 E000.00.00.000
+ENOB_OCA         N001.00.00.000 E000.00.00.000 N001.00.00.000 E001.00.00.000
+ENOB_OCA         N001.00.00.000 E001.00.00.000 N000.00.00.000 E000.00.00.000
+
+"
+        );
+
+        let opts = Options {
+            extentions: ExtentionPlacement::Same,
+            ..opts
+        };
+        let mut ese = Vec::new();
+        write_ese(&firs, &opts, &mut ese).unwrap();
+        let ese = String::from_utf8(ese).unwrap();
+        assert!(ese.contains("SECTOR:ENOR_1:24500:66000\nOWNER:ENOR\nBORDER:ENOR_1\n"));
+        assert!(ese.contains("SECTOR:ENOB_OCA:0:24500\n"));
+        assert_eq!(ese.matches("COORD:").count(), 3 * 4);
+    }
+}
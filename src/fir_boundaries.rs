@@ -10,14 +10,19 @@ use std::{
 
 use indexmap::{IndexMap, IndexSet};
 use itertools::Itertools;
-use rust_decimal::Decimal;
+use rust_decimal::{
+    prelude::{FromPrimitive, ToPrimitive},
+    Decimal,
+};
 use rust_decimal_macros::dec;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::{
+    dms::{self, Notation},
     error_collector::{ColResult, ErrorCollector},
-    Mode,
+    geodesy::Coord,
+    lable, Mode,
 };
 
 pub type FIRResult<T> = Result<T, FIRParsingError>;
@@ -41,6 +46,14 @@ pub enum FIRParsingError {
     MultipleFirs(IndexMap<String, usize>),
     #[error("Wrong min/max for sector: {1}: {}.", .0.iter().map(|(stated, actual, typ)| format!("stated {}: {}, actual: {}", typ, stated, actual)).join(", "))]
     WrongMinMax(Vec<(Decimal, Decimal, &'static str)>, String),
+    #[error("Coordinate notation: FIR: {owner}, has coordinates written in {notation} on lines: {}, VATSpy only reads decimal degrees. Fix the file to convert them.", .lines.iter().join(", "))]
+    CoordinateNotation {
+        owner: String,
+        notation: Notation,
+        lines: Vec<usize>,
+    },
+    #[error("Lable outside FIR: FIR: {owner}, has its lable at {lable}, which is outside the FIR and all its extentions.")]
+    LableOutsideFir { owner: String, lable: Point },
     #[error(transparent)]
     ParseDecimalError(#[from] rust_decimal::Error),
     #[error(transparent)]
@@ -69,6 +82,18 @@ impl FIRParsingError {
             }
             FIRParsingError::MultipleFirs(e) => Err(FIRParsingError::MultipleFirs(e)),
             FIRParsingError::WrongMinMax(d, f) => Ok(FIRParsingError::WrongMinMax(d, f)),
+            FIRParsingError::CoordinateNotation {
+                owner,
+                notation,
+                lines,
+            } => Ok(FIRParsingError::CoordinateNotation {
+                owner,
+                notation,
+                lines,
+            }),
+            FIRParsingError::LableOutsideFir { owner, lable } => {
+                Ok(FIRParsingError::LableOutsideFir { owner, lable })
+            }
             FIRParsingError::ParseDecimalError(e) => Err(FIRParsingError::ParseDecimalError(e)),
             FIRParsingError::ParseIntError(e) => Err(FIRParsingError::ParseIntError(e)),
             FIRParsingError::IoError(e) => Err(FIRParsingError::IoError(e)),
@@ -118,8 +143,102 @@ impl Point {
         }
     }
 
-    fn to_fir_dat_str(&self) -> String {
-        format!("{}|{}", self.lat, self.lon)
+    fn to_fir_dat_str(&self, dms: bool) -> String {
+        match dms {
+            true => {
+                let c = Coord::from(self);
+                format!(
+                    "{}|{}",
+                    dms::euroscope_lat(c.lat),
+                    dms::euroscope_lon(c.lon)
+                )
+            }
+            false => format!("{}|{}", self.lat, self.lon),
+        }
+    }
+
+    /// Reads a point in any notation, see [`Notation`], with the latitude and longitude split on `|`,
+    /// or on whitespace if there is no `|`. Points written longitude first are turned around if
+    /// the hemisphere letters tell which is which.
+    /// Returns the first notation that is not decimal degrees, or decimal degrees.
+    pub(crate) fn parse_notation(s: &str) -> FIRResult<(Self, Notation)> {
+        let fields: Vec<_> = match s.contains('|') {
+            true => s.split('|').map(str::trim).collect(),
+            false => s.split_whitespace().collect(),
+        };
+        if fields.len() != 2 {
+            return Err(FIRParsingError::FIRParsing(format!(
+                "A point expects 2 fields (lat|lon), got: {}",
+                fields.len()
+            )));
+        }
+        let (mut lat, mut lon) = (Value::parse(fields[0])?, Value::parse(fields[1])?);
+        if lat.is_lon() && !lon.is_lon() {
+            std::mem::swap(&mut lat, &mut lon);
+        }
+        if lat.is_lon() || lon.is_lat() {
+            return Err(FIRParsingError::FIRParsing(format!(
+                "A point expects a latitude and a longitude, got: {}",
+                s.trim()
+            )));
+        }
+        let notation = match lat.notation {
+            Notation::Decimal => lon.notation,
+            n => n,
+        };
+        Ok((
+            Point {
+                lat: lat.value,
+                lon: lon.value,
+            },
+            notation,
+        ))
+    }
+}
+
+/// A latitude or longitude as read, in any notation.
+struct Value {
+    value: Decimal,
+    notation: Notation,
+    hemisphere: Option<char>,
+}
+
+impl Value {
+    fn parse(s: &str) -> FIRResult<Self> {
+        let s = s.trim();
+        let decimal = |value, notation| Self {
+            value,
+            notation,
+            hemisphere: None,
+        };
+        if let Ok(value) = s.parse() {
+            return Ok(decimal(value, Notation::Decimal));
+        }
+        if let Ok(value) = s.replace(',', ".").parse() {
+            return Ok(decimal(value, Notation::DecimalComma));
+        }
+        match dms::parse_angle(s) {
+            Some(angle) => Ok(Self {
+                value: Decimal::from_f64(angle.value)
+                    .unwrap()
+                    .round_dp(6)
+                    .normalize(),
+                notation: angle.notation,
+                hemisphere: Some(angle.hemisphere),
+            }),
+            None => Err(FIRParsingError::FIRParsing(format!(
+                "Not a latitude or longitude: {}",
+                s
+            ))),
+        }
+    }
+
+    fn is_lat(&self) -> bool {
+        matches!(self.hemisphere, Some('N') | Some('S'))
+    }
+
+    fn is_lon(&self) -> bool {
+        matches!(self.hemisphere, Some('E') | Some('W'))
     }
 }
 
@@ -133,17 +252,7 @@ impl FromStr for Point {
     type Err = FIRParsingError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let fields: Vec<_> = s.split('|').map(str::trim).collect();
-        match fields.len() {
-            2 => Ok(Point {
-                lat: fields[0].parse()?,
-                lon: fields[1].parse()?,
-            }),
-            n => Err(Self::Err::FIRParsing(format!(
-                "A point expects 2 fields (lat|lon), got: {}",
-                n
-            ))),
-        }
+        Self::parse_notation(s).map(|(p, _)| p)
     }
 }
 
@@ -154,15 +263,20 @@ pub enum Fill {
 }
 
 pub(crate) fn polygon_or_hole(arr: &[Point]) -> Fill {
+    try_polygon_or_hole(arr).expect("A stait line")
+}
+
+/// Like `polygon_or_hole`, but gives None for rings without area instead of panicking.
+pub(crate) fn try_polygon_or_hole(arr: &[Point]) -> Option<Fill> {
     match arr
         .windows(2)
         .map(|v| v[0].lon * v[1].lat - v[0].lat * v[1].lon)
         .sum::<Decimal>()
         // / dec!(2.0) //not needed as we only look for zero point
     {
-        n if n == dec!(0) => panic!("A stait line"),
-        s if s.is_sign_negative() => Fill::Polygon,
-        s if s.is_sign_positive() => Fill::Hole,
+        n if n == dec!(0) => None,
+        s if s.is_sign_negative() => Some(Fill::Polygon),
+        s if s.is_sign_positive() => Some(Fill::Hole),
         n => unreachable!("Math is off (are we in imag numbers): {}", n),
     }
 }
@@ -192,7 +306,6 @@ impl FIRBoundary {
         mode: Mode,
         linenr: &mut usize,
     ) -> FIRResult<ColResult<Self>> {
-        let mut errors = ErrorCollector::new();
         let mut line = String::new();
         f.read_line(&mut line)?;
         *linenr += 1;
@@ -214,6 +327,16 @@ impl FIRBoundary {
             });
         }
         let amount: usize = fields[3].parse()?;
+        let header = fields[4..]
+            .iter()
+            .map(|s| Value::parse(s))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut notations: Vec<(Notation, usize)> = header
+            .iter()
+            .find(|v| v.notation != Notation::Decimal)
+            .map(|v| (v.notation, *linenr))
+            .into_iter()
+            .collect();
         let v = std::iter::repeat_with(|| -> FIRResult<(String, usize)> {
             let mut s = String::new();
             f.read_line(&mut s)?;
@@ -221,30 +344,56 @@ impl FIRBoundary {
             Ok((s, *linenr))
         })
         .take(amount)
-        .map(|r| r.and_then(|(s, l)| Point::from_str(&s).map(|p| (p, l))))
+        .map(|r| r.and_then(|(s, l)| Point::parse_notation(&s).map(|(p, n)| (p, n, l))))
         .collect::<Result<Vec<_>, _>>()?;
-        let mut fir = Self {
+        notations.extend(
+            v.iter()
+                .filter(|(_, n, _)| *n != Notation::Decimal)
+                .map(|(_, n, l)| (*n, *l)),
+        );
+        let fir = Self {
             id: *count,
             icao: fields[0].into(),
             is_oseanic: numstr_to_bool(fields[1]),
             is_extension: numstr_to_bool(fields[2]),
-            min_lat: fields[4].parse()?,
-            min_lon: fields[5].parse()?,
-            max_lat: fields[6].parse()?,
-            max_lon: fields[7].parse()?,
-            lable: Point::new(fields[8].parse()?, fields[9].parse()?)?,
-            boundary_corners: v.iter().map(|(p, _)| p.clone()).collect(),
+            min_lat: header[0].value,
+            min_lon: header[1].value,
+            max_lat: header[2].value,
+            max_lon: header[3].value,
+            lable: Point::new(header[4].value, header[5].value)?,
+            boundary_corners: v.iter().map(|(p, _, _)| p.clone()).collect(),
         };
         *count += 1;
+        // other notations are converted when fixing
+        let mut errors = ErrorCollector::new();
+        if mode == Mode::Strict && !notations.is_empty() {
+            errors.adderror(FIRParsingError::CoordinateNotation {
+                owner: fir.icao.clone(),
+                notation: notations[0].0,
+                lines: notations.iter().map(|(_, l)| *l).collect(),
+            })?;
+        }
+        match fir.validate(mode)? {
+            Ok(fir) => Ok(errors.to_col_result(fir)),
+            Err(e) => {
+                errors.adderrors(e);
+                Ok(Err(errors))
+            }
+        }
+    }
+
+    /// Checks a single FIR: draw direction, duplicate points and the stated min/max.
+    /// In Fix mode the problems are fixed instead of reported.
+    pub(crate) fn validate(mut self, mode: Mode) -> FIRResult<ColResult<Self>> {
+        let mut errors = ErrorCollector::new();
         match mode {
             Mode::Strict => {
-                if fir.polygon_or_hole() == Fill::Hole {
-                    fir.icao.as_str();
-                    errors.adderror(FIRParsingError::AirspaceDrawDirection(fir.icao.clone()))?
+                if self.polygon_or_hole() == Fill::Hole {
+                    errors.adderror(FIRParsingError::AirspaceDrawDirection(self.icao.clone()))?
                 }
                 let mut boundaries = IndexSet::new();
                 let mut duplicates = IndexSet::new();
-                for point in &fir.boundary_corners {
+                for point in &self.boundary_corners {
                     if !boundaries.insert(point.clone()) {
                         duplicates.insert(point.clone());
                     }
@@ -252,33 +401,55 @@ impl FIRBoundary {
                 if !duplicates.is_empty() {
                     errors.adderror(FIRParsingError::DuplicatePointError {
                         points: duplicates,
-                        owner: fir.icao.clone(),
+                        owner: self.icao.clone(),
                     })?
                 }
             }
             Mode::Fix => {
-                fir.boundary_corners = fir
+                self.boundary_corners = self
                     .boundary_corners
                     .iter()
                     .collect::<IndexSet<_>>()
                     .into_iter()
                     .cloned()
                     .collect_vec();
-                if fir.polygon_or_hole() == Fill::Hole {
-                    fir.boundary_corners.reverse();
-                    fir.icao.as_str();
-                    assert!(fir.polygon_or_hole() == Fill::Polygon);
+                if self.polygon_or_hole() == Fill::Hole {
+                    self.boundary_corners.reverse();
+                    assert!(self.polygon_or_hole() == Fill::Polygon);
+                }
+            }
+        }
+        let (min_lat, min_lon, max_lat, max_lon) = self.bounds();
+        match mode {
+            Mode::Strict => {
+                let wrong = vec![
+                    (self.min_lat, min_lat, "minimum latitude"),
+                    (self.min_lon, min_lon, "minimum longitude"),
+                    (self.max_lat, max_lat, "maximum latitude"),
+                    (self.max_lon, max_lon, "maximum longitude"),
+                ]
+                .into_iter()
+                .filter(|(f, c, _)| f != c)
+                .collect_vec();
+                if !wrong.is_empty() {
+                    errors.adderror(FIRParsingError::WrongMinMax(wrong, self.icao.clone()))?;
                 }
             }
+            Mode::Fix => self.update_bounds(),
         }
-        let (min_lat, max_lat) = fir
+        Ok(errors.to_col_result(self))
+    }
+
+    /// Calculates (min_lat, min_lon, max_lat, max_lon) from the boundary corners.
+    fn bounds(&self) -> (Decimal, Decimal, Decimal, Decimal) {
+        let (min_lat, max_lat) = self
             .boundary_corners
             .iter()
             .map(|n| n.lat)
             .minmax()
             .into_option()
             .unwrap();
-        let (mut min_lon, mut max_lon) = fir
+        let (mut min_lon, mut max_lon) = self
             .boundary_corners
             .iter()
             .map(|n| n.lon)
@@ -286,32 +457,19 @@ impl FIRBoundary {
             .into_option()
             .unwrap();
         fix_min_max_lon(&mut min_lon, &mut max_lon);
-        match mode {
-            Mode::Strict => {
-                let wrong = vec![
-                    (fir.min_lat, min_lat, "minimum latitude"),
-                    (fir.min_lon, min_lon, "minimum longitude"),
-                    (fir.max_lat, max_lat, "maximum latitude"),
-                    (fir.max_lon, max_lon, "maximum longitude"),
-                ]
-                .into_iter()
-                .filter(|(f, c, _)| f != c)
-                .collect_vec();
-                if !wrong.is_empty() {
-                    errors.adderror(FIRParsingError::WrongMinMax(wrong, fir.icao.clone()))?;
-                }
-            }
-            Mode::Fix => {
-                fir.min_lat = min_lat;
-                fir.min_lon = min_lon;
-                fir.max_lat = max_lat;
-                fir.max_lon = max_lon;
-            }
-        }
-        Ok(errors.to_col_result(fir))
+        (min_lat, min_lon, max_lat, max_lon)
+    }
+
+    /// Sets the min/max fields to match the boundary corners, used after the corners have been changed.
+    pub(crate) fn update_bounds(&mut self) {
+        let (min_lat, min_lon, max_lat, max_lon) = self.bounds();
+        self.min_lat = min_lat;
+        self.min_lon = min_lon;
+        self.max_lat = max_lat;
+        self.max_lon = max_lon;
     }
 
-    fn to_writer<W>(&self, writer: &mut BufWriter<W>) -> io::Result<()>
+    fn to_writer<W>(&self, writer: &mut BufWriter<W>, dms: bool) -> io::Result<()>
     where
         W: Write,
     {
@@ -322,15 +480,15 @@ impl FIRBoundary {
             bool_to_num(self.is_oseanic),
             bool_to_num(self.is_extension),
             self.boundary_corners.len(),
-            self.min_lat,
-            self.min_lon,
-            self.max_lat,
-            self.max_lon,
-            self.lable.to_fir_dat_str(),
+            coordinate(self.min_lat, dms, dms::euroscope_lat),
+            coordinate(self.min_lon, dms, dms::euroscope_lon),
+            coordinate(self.max_lat, dms, dms::euroscope_lat),
+            coordinate(self.max_lon, dms, dms::euroscope_lon),
+            self.lable.to_fir_dat_str(dms),
         )?;
         self.boundary_corners
             .iter()
-            .map(|c| writeln!(writer, "{}", c.to_fir_dat_str()))
+            .map(|c| writeln!(writer, "{}", c.to_fir_dat_str(dms)))
             .collect::<Result<Vec<_>, io::Error>>()
             .map(|_| ())
     }
@@ -346,6 +504,36 @@ impl FIRBoundary {
     }
 }
 
+/// A FIR with the given corners as (lat, lon), for tests.
+#[cfg(test)]
+pub(crate) fn fir(icao: &str, is_extension: bool, corners: &[(i64, i64)]) -> FIRBoundary {
+    let corners: Vec<Point> = corners
+        .iter()
+        .map(|(lat, lon)| Point::new((*lat).into(), (*lon).into()).unwrap())
+        .collect();
+    let mut fir = FIRBoundary {
+        id: 0,
+        icao: icao.to_string(),
+        is_oseanic: false,
+        is_extension,
+        min_lat: 0.into(),
+        min_lon: 0.into(),
+        max_lat: 0.into(),
+        max_lon: 0.into(),
+        lable: Point::new(1.into(), 1.into()).unwrap(),
+        boundary_corners: corners,
+    };
+    fir.update_bounds();
+    fir
+}
+
+fn coordinate(value: Decimal, dms: bool, format: fn(f64) -> String) -> String {
+    match dms {
+        true => format(value.to_f64().unwrap()),
+        false => value.to_string(),
+    }
+}
+
 fn numstr_to_bool(a: &str) -> bool {
     match a {
         "0" => false,
@@ -419,22 +607,91 @@ pub fn read_file<P: AsRef<Path>>(p: P, mode: Mode) -> FIRResult<ColResult<Vec<FI
     }
 
     if mode == Mode::Strict {
-        let wrong_orders: IndexSet<_> = all
-            .iter()
-            .enumerate()
-            .filter(|(_, fir)| fir.is_extension)
-            .filter(|(n, fir)| fir.id != *n)
-            //.inspect(|(n, fir)| {dbg!(n, fir.id);})
-            .map(|(_, fir)| fir.icao.clone())
-            .collect();
-        if !wrong_orders.is_empty() {
-            errors.adderror(FIRParsingError::ExtentionNotAfterFir(wrong_orders))?;
-        }
+        check_dataset(&all, &mut errors)?;
     }
 
     Ok(errors.to_col_result(all))
 }
 
+/// Checks that need every FIR: extentions following their FIR and lables inside their FIR.
+fn check_dataset(all: &[FIRBoundary], errors: &mut ErrorCollector) -> FIRResult<()> {
+    let wrong_orders: IndexSet<_> = all
+        .iter()
+        .enumerate()
+        .filter(|(_, fir)| fir.is_extension)
+        .filter(|(n, fir)| fir.id != *n)
+        .map(|(_, fir)| fir.icao.clone())
+        .collect();
+    if !wrong_orders.is_empty() {
+        errors.adderror(FIRParsingError::ExtentionNotAfterFir(wrong_orders))?;
+    }
+    for e in lable::check_lables(all) {
+        errors.adderror(e)?;
+    }
+    Ok(())
+}
+
+/// Runs the same checks as `read_file` on FIRs that come from another source.
+/// The FIRs have to be in file order, with `id` set to their place in it.
+pub(crate) fn validate(
+    firs: Vec<FIRBoundary>,
+    mode: Mode,
+) -> FIRResult<ColResult<Vec<FIRBoundary>>> {
+    let mut errors = ErrorCollector::new();
+    let mut all = Vec::with_capacity(firs.len());
+    for fir in firs {
+        match fir.validate(mode)? {
+            Ok(fir) => all.push(fir),
+            Err(e) => errors.adderrors(e),
+        }
+    }
+    let mut duplicate_firs = IndexMap::new();
+    for fir in all.iter().filter(|f| !f.is_extension) {
+        *duplicate_firs
+            .entry((fir.icao.clone(), fir.is_oseanic))
+            .or_insert(0usize) += 1;
+    }
+    duplicate_firs.retain(|_, n| *n > 1);
+    if !duplicate_firs.is_empty() {
+        errors.adderror(FIRParsingError::MultipleFirs(
+            duplicate_firs
+                .into_iter()
+                .map(|((s, _), v)| (s, v))
+                .collect(),
+        ))?;
+    }
+    if mode == Mode::Strict {
+        check_dataset(&all, &mut errors)?;
+    }
+    Ok(errors.to_col_result(all))
+}
+
+/// Groups every FIR with its extentions, as indexes into `firs` with the FIR first.
+/// Extentions belong to the first FIR with the same ICAO, like in `read_file`.
+/// Extentions without a FIR get a group of their own.
+pub(crate) fn with_extentions(firs: &[FIRBoundary]) -> Vec<Vec<usize>> {
+    let mut groups: IndexMap<&str, Vec<usize>> = IndexMap::new();
+    let mut rest = Vec::new();
+    for (n, fir) in firs.iter().enumerate().filter(|(_, f)| !f.is_extension) {
+        match groups.contains_key(fir.icao.as_str()) {
+            true => rest.push(vec![n]), // same ICAO as an earlier FIR, e.g. an oceanic and a land FIR
+            false => {
+                groups.insert(fir.icao.as_str(), vec![n]);
+            }
+        }
+    }
+    for (n, fir) in firs.iter().enumerate().filter(|(_, f)| f.is_extension) {
+        match groups.get_mut(fir.icao.as_str()) {
+            Some(g) => g.push(n),
+            None => rest.push(vec![n]),
+        }
+    }
+    let mut all = groups.into_iter().map(|(_, g)| g).collect_vec();
+    all.extend(rest);
+    all.sort_by_key(|g| g[0]);
+    all
+}
+
 pub(crate) fn convert_from_geojson(gj: crate::geo_json::GeoJson) -> Vec<FIRBoundary> {
     let data = gj.features;
     let number = Cell::new(0usize);
@@ -444,7 +701,7 @@ pub(crate) fn convert_from_geojson(gj: crate::geo_json::GeoJson) -> Vec<FIRBound
             fir.geometry
                 .array
                 .iter()
-                .map(|n| n.get(0).unwrap())
+                .map(|n| n.first().unwrap())
                 .enumerate()
                 .map(move |(n, points)| {
                     let mut fir = FIRBoundary {
@@ -464,8 +721,8 @@ pub(crate) fn convert_from_geojson(gj: crate::geo_json::GeoJson) -> Vec<FIRBound
                     fir
                 })
         })
-        .sorted_unstable_by(|a, b| match a.icao.as_str().cmp(b.icao.as_str()){
-            std::cmp::Ordering::Equal => match a.is_oseanic == b.is_oseanic{
+        .sorted_unstable_by(|a, b| match a.icao.as_str().cmp(b.icao.as_str()) {
+            std::cmp::Ordering::Equal => match a.is_oseanic == b.is_oseanic {
                 true => std::cmp::Ordering::Equal,
                 false if a.is_oseanic => std::cmp::Ordering::Less,
                 false => std::cmp::Ordering::Greater,
@@ -482,9 +739,19 @@ fn fix_min_max_lon(min_lon: &mut Decimal, max_lon: &mut Decimal) {
 }
 
 pub fn write_to_file<P: AsRef<Path>>(firs: &[FIRBoundary], p: P) -> io::Result<()> {
+    write_file(firs, p, false)
+}
+
+/// Writes FIRBoundaries.dat with every coordinate in degrees, minutes and seconds, like `N051.30.00.000`.
+/// VATSpy can not read this, but it is easier to compare with sources that use it.
+pub(crate) fn write_dms_file<P: AsRef<Path>>(firs: &[FIRBoundary], p: P) -> io::Result<()> {
+    write_file(firs, p, true)
+}
+
+fn write_file<P: AsRef<Path>>(firs: &[FIRBoundary], p: P, dms: bool) -> io::Result<()> {
     let mut file = BufWriter::new(File::create(p.as_ref())?);
     firs.iter()
-        .map(|fir| fir.to_writer(&mut file))
+        .map(|fir| fir.to_writer(&mut file, dms))
         .collect::<Result<Vec<_>, _>>()?;
     Ok(())
 }
@@ -501,4 +768,24 @@ mod tests {
             Err(e) => panic!("{}", e),
         }
     }
+
+    #[test]
+    fn point_notations() {
+        let expected = Point::new(dec!(51.5), dec!(-0.166667)).unwrap();
+        for s in [
+            "51.5|-0.166667",
+            "51,5|-0,166667",
+            "N051.30.00.000|W000.10.00.000",
+            "513000N 0001000W",
+            "0001000W 513000N",
+            "51°30'N|0°10'W",
+        ] {
+            assert_eq!(Point::from_str(s).unwrap(), expected, "{}", s);
+        }
+        assert_eq!(
+            Point::parse_notation("N051.30.00.000|-0.166667").unwrap().1,
+            Notation::EuroScope
+        );
+        assert!(Point::from_str("513000N 513000N").is_err());
+    }
 }
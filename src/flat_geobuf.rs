@@ -0,0 +1,556 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use rust_decimal::prelude::ToPrimitive;
+
+use crate::{
+    fir_boundaries::{with_extentions, FIRBoundary, Point},
+    topology::open_ring,
+};
+
+// a FlatGeobuf file, version 3:
+// magic bytes   fgb 3 fgb 0
+// header        size prefixed flatbuffer: name, envelope, geometry type, columns, feature count, index node size, crs
+// index         packed Hilbert R-tree, 40 bytes per node, root first and the leaves last
+// features      size prefixed flatbuffers: geometry and properties, in the order of the leaves
+// every FIR with its extentions is one MultiPolygon feature, rings closed and drawn as in FIRBoundaries.dat
+// clients read the header and the index, and fetch only the features in view with HTTP range requests
+
+const MAGIC: [u8; 8] = [0x66, 0x67, 0x62, 0x03, 0x66, 0x67, 0x62, 0x00];
+const NODE_SIZE: u16 = 16;
+const HILBERT_MAX: f64 = ((1 << 16) - 1) as f64;
+
+const GEOMETRY_POLYGON: u8 = 3;
+const GEOMETRY_MULTIPOLYGON: u8 = 6;
+const COLUMN_BOOL: u8 = 2;
+const COLUMN_DOUBLE: u8 = 10;
+const COLUMN_STRING: u8 = 11;
+
+/// Columns of every feature, the same as the properties of a GeoJSON feature with the lable split in two.
+const COLUMNS: [(&str, u8); 4] = [
+    ("ICAO", COLUMN_STRING),
+    ("IsOceanic", COLUMN_BOOL),
+    ("LableLat", COLUMN_DOUBLE),
+    ("LableLon", COLUMN_DOUBLE),
+];
+
+/// A value in a flatbuffer table. Tables are built from these and written in one go.
+enum Value {
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    I32(i32),
+    U64(u64),
+    String(String),
+    Bytes(Vec<u8>),
+    Doubles(Vec<f64>),
+    Table(Table),
+    Tables(Vec<Table>),
+}
+
+impl Value {
+    /// Size and alignment of the value inside its table, offsets being 4 bytes.
+    fn inline_size(&self) -> usize {
+        match self {
+            Value::Bool(_) | Value::U8(_) => 1,
+            Value::U16(_) => 2,
+            Value::U64(_) => 8,
+            _ => 4,
+        }
+    }
+}
+
+/// A flatbuffer table as (field number, value), fields left out get their default value.
+struct Table(Vec<(u16, Value)>);
+
+/// Writes flatbuffers front to back: a vtable, the table it belongs to and then the strings,
+/// vectors and tables it points to. Offsets to them are patched in once they are written.
+/// Alignment is counted from the start of `buf`, which starts with the size prefix.
+struct Builder {
+    buf: Vec<u8>,
+}
+
+impl Builder {
+    fn pad(&mut self, align: usize) {
+        while !self.buf.len().is_multiple_of(align) {
+            self.buf.push(0);
+        }
+    }
+
+    /// Points the offset at `at` to `target`.
+    fn patch(&mut self, at: usize, target: usize) {
+        let offset = (target - at) as u32;
+        self.buf[at..at + 4].copy_from_slice(&offset.to_le_bytes());
+    }
+
+    /// Starts a vector of `len` elements of `size` bytes, with the elements aligned to `size`.
+    fn vector_start(&mut self, len: usize, size: usize) -> usize {
+        self.pad(4);
+        while !(self.buf.len() + 4).is_multiple_of(size.max(4)) {
+            self.buf.push(0);
+        }
+        let pos = self.buf.len();
+        self.buf.extend((len as u32).to_le_bytes());
+        pos
+    }
+
+    fn table(&mut self, table: &Table) -> usize {
+        // fields by size, so every field is aligned when the table starts on 8 bytes
+        let mut fields: Vec<&(u16, Value)> = table.0.iter().collect();
+        fields.sort_by_key(|(_, v)| std::cmp::Reverse(v.inline_size()));
+        let mut offsets = Vec::new();
+        let mut size: usize = 4; // offset to the vtable
+        for (_, value) in &fields {
+            let align = value.inline_size();
+            size = size.div_ceil(align) * align;
+            offsets.push(size);
+            size += align;
+        }
+        let slots = table
+            .0
+            .iter()
+            .map(|(n, _)| *n as usize + 1)
+            .max()
+            .unwrap_or(0);
+        let mut vtable = vec![0u16; 2 + slots];
+        vtable[0] = (4 + 2 * slots) as u16;
+        vtable[1] = size as u16;
+        for ((n, _), offset) in fields.iter().zip(&offsets) {
+            vtable[2 + *n as usize] = *offset as u16;
+        }
+
+        self.pad(2);
+        let vtable_pos = self.buf.len();
+        vtable.iter().for_each(|v| self.buf.extend(v.to_le_bytes()));
+        self.pad(8);
+        let pos = self.buf.len();
+        self.buf.resize(pos + size, 0);
+        self.buf[pos..pos + 4].copy_from_slice(&((pos - vtable_pos) as i32).to_le_bytes());
+        let mut children = Vec::new();
+        for ((_, value), offset) in fields.iter().zip(&offsets) {
+            let at = pos + offset;
+            let bytes = match value {
+                Value::Bool(b) => vec![*b as u8],
+                Value::U8(v) => vec![*v],
+                Value::U16(v) => v.to_le_bytes().to_vec(),
+                Value::I32(v) => v.to_le_bytes().to_vec(),
+                Value::U64(v) => v.to_le_bytes().to_vec(),
+                _ => {
+                    children.push((at, value));
+                    continue;
+                }
+            };
+            self.buf[at..at + bytes.len()].copy_from_slice(&bytes);
+        }
+        for (at, value) in children {
+            let target = self.child(value);
+            self.patch(at, target);
+        }
+        pos
+    }
+
+    fn child(&mut self, value: &Value) -> usize {
+        match value {
+            Value::String(s) => {
+                let pos = self.vector_start(s.len(), 1);
+                self.buf.extend(s.as_bytes());
+                self.buf.push(0);
+                pos
+            }
+            Value::Bytes(bytes) => {
+                let pos = self.vector_start(bytes.len(), 1);
+                self.buf.extend(bytes);
+                pos
+            }
+            Value::Doubles(values) => {
+                let pos = self.vector_start(values.len(), 8);
+                values.iter().for_each(|v| self.buf.extend(v.to_le_bytes()));
+                pos
+            }
+            Value::Table(table) => self.table(table),
+            Value::Tables(tables) => {
+                let pos = self.vector_start(tables.len(), 4);
+                self.buf.resize(pos + 4 + 4 * tables.len(), 0);
+                for (n, table) in tables.iter().enumerate() {
+                    let target = self.table(table);
+                    self.patch(pos + 4 + 4 * n, target);
+                }
+                pos
+            }
+            _ => unreachable!("scalars are written inline"),
+        }
+    }
+
+    /// A size prefixed flatbuffer with `root` as its root table.
+    fn finish(root: &Table) -> Vec<u8> {
+        let mut builder = Builder { buf: vec![0; 8] };
+        let pos = builder.table(root);
+        builder.patch(4, pos);
+        builder.pad(8);
+        let size = (builder.buf.len() - 4) as u32;
+        builder.buf[..4].copy_from_slice(&size.to_le_bytes());
+        builder.buf
+    }
+}
+
+/// Bounding box as (min x, min y, max x, max y).
+type Envelope = [f64; 4];
+
+fn expand(a: Envelope, b: Envelope) -> Envelope {
+    [
+        a[0].min(b[0]),
+        a[1].min(b[1]),
+        a[2].max(b[2]),
+        a[3].max(b[3]),
+    ]
+}
+
+const EMPTY: Envelope = [f64::MAX, f64::MAX, f64::MIN, f64::MIN];
+
+/// Position of (x, y) along a Hilbert curve filling a 2^16 by 2^16 grid.
+/// From https://github.com/rawrunprotected/hilbert_curves, as used by FlatGeobuf.
+fn hilbert(x: u32, y: u32) -> u32 {
+    let mut a = x ^ y;
+    let mut b = 0xFFFF ^ a;
+    let mut c = 0xFFFF ^ (x | y);
+    let mut d = x & (y ^ 0xFFFF);
+
+    let mut aa = a | (b >> 1);
+    let mut bb = (a >> 1) ^ a;
+    let mut cc = ((c >> 1) ^ (b & (d >> 1))) ^ c;
+    let mut dd = ((a & (c >> 1)) ^ (d >> 1)) ^ d;
+
+    for shift in [2, 4] {
+        a = aa;
+        b = bb;
+        c = cc;
+        d = dd;
+        aa = (a & (a >> shift)) ^ (b & (b >> shift));
+        bb = (a & (b >> shift)) ^ (b & ((a ^ b) >> shift));
+        cc ^= (a & (c >> shift)) ^ (b & (d >> shift));
+        dd ^= (b & (c >> shift)) ^ ((a ^ b) & (d >> shift));
+    }
+
+    a = aa;
+    b = bb;
+    c = cc;
+    d = dd;
+    cc ^= (a & (c >> 8)) ^ (b & (d >> 8));
+    dd ^= (b & (c >> 8)) ^ ((a ^ b) & (d >> 8));
+
+    a = cc ^ (cc >> 1);
+    b = dd ^ (dd >> 1);
+
+    let mut i0 = x ^ y;
+    let mut i1 = b | (0xFFFF ^ (i0 | a));
+    for (shift, mask) in [
+        (8, 0x00FF_00FF),
+        (4, 0x0F0F_0F0F),
+        (2, 0x3333_3333),
+        (1, 0x5555_5555),
+    ] {
+        i0 = (i0 | (i0 << shift)) & mask;
+        i1 = (i1 | (i1 << shift)) & mask;
+    }
+    (i1 << 1) | i0
+}
+
+/// Hilbert value of the center of `e` within `extent`.
+fn hilbert_value(e: &Envelope, extent: &Envelope) -> u32 {
+    let (width, height) = (extent[2] - extent[0], extent[3] - extent[1]);
+    let scale = |v: f64, min: f64, size: f64| match size == 0.0 {
+        true => 0,
+        false => (HILBERT_MAX * (v - min) / size).floor() as u32,
+    };
+    hilbert(
+        scale((e[0] + e[2]) / 2.0, extent[0], width),
+        scale((e[1] + e[3]) / 2.0, extent[1], height),
+    )
+}
+
+/// The first and one past the last node of every level, from the leaves up.
+/// Nodes are stored from the root down, so the leaves come last.
+fn level_bounds(items: usize, node_size: usize) -> Vec<(usize, usize)> {
+    let mut level_nodes = vec![items];
+    let mut n = items;
+    let mut nodes = n;
+    loop {
+        n = n.div_ceil(node_size);
+        nodes += n;
+        level_nodes.push(n);
+        if n == 1 {
+            break;
+        }
+    }
+    let mut end = nodes;
+    level_nodes
+        .into_iter()
+        .map(|size| {
+            end -= size;
+            (end, end + size)
+        })
+        .collect()
+}
+
+/// A packed Hilbert R-tree over features in Hilbert order. Leaves point to the byte offset of their
+/// feature in the feature section, and the other nodes to the index of their first child.
+fn packed_rtree(leaves: &[(Envelope, u64)], node_size: usize) -> Vec<(Envelope, u64)> {
+    let bounds = level_bounds(leaves.len(), node_size);
+    let mut nodes = vec![(EMPTY, 0); bounds.iter().map(|(s, e)| e - s).sum()];
+    let (first, _) = bounds[0];
+    nodes[first..first + leaves.len()].copy_from_slice(leaves);
+    for level in bounds.windows(2) {
+        let ((mut pos, end), (mut parent, _)) = (level[0], level[1]);
+        while pos < end {
+            let mut node = (EMPTY, pos as u64);
+            for _ in 0..node_size {
+                if pos == end {
+                    break;
+                }
+                node.0 = expand(node.0, nodes[pos].0);
+                pos += 1;
+            }
+            nodes[parent] = node;
+            parent += 1;
+        }
+    }
+    nodes
+}
+
+fn header(extent: Envelope, features: usize) -> Table {
+    let columns = COLUMNS
+        .iter()
+        .map(|(name, typ)| {
+            Table(vec![
+                (0, Value::String(name.to_string())),
+                (1, Value::U8(*typ)),
+                (7, Value::Bool(false)),
+            ])
+        })
+        .collect();
+    Table(vec![
+        (0, Value::String("FIR boundaries".to_string())),
+        (1, Value::Doubles(extent.to_vec())),
+        (2, Value::U8(GEOMETRY_MULTIPOLYGON)),
+        (7, Value::Tables(columns)),
+        (8, Value::U64(features as u64)),
+        (9, Value::U16(NODE_SIZE)),
+        (
+            10,
+            Value::Table(Table(vec![
+                (0, Value::String("EPSG".to_string())),
+                (1, Value::I32(4326)),
+            ])),
+        ),
+    ])
+}
+
+/// The rings of a FIR and its extentions as x, y pairs, closed.
+fn rings(firs: &[FIRBoundary], group: &[usize]) -> Vec<Vec<f64>> {
+    group
+        .iter()
+        .map(|n| {
+            let ring = open_ring(&firs[*n].boundary_corners);
+            ring.iter()
+                .chain(ring.first())
+                .flat_map(|p: &Point| [p.lon.to_f64().unwrap(), p.lat.to_f64().unwrap()])
+                .collect()
+        })
+        .collect()
+}
+
+fn envelope(rings: &[Vec<f64>]) -> Envelope {
+    rings
+        .iter()
+        .flat_map(|ring| ring.chunks(2))
+        .fold(EMPTY, |e, xy| expand(e, [xy[0], xy[1], xy[0], xy[1]]))
+}
+
+fn feature(fir: &FIRBoundary, rings: Vec<Vec<f64>>) -> Table {
+    let mut properties = Vec::new();
+    properties.extend(0u16.to_le_bytes());
+    properties.extend((fir.icao.len() as u32).to_le_bytes());
+    properties.extend(fir.icao.as_bytes());
+    properties.extend(1u16.to_le_bytes());
+    properties.push(fir.is_oseanic as u8);
+    properties.extend(2u16.to_le_bytes());
+    properties.extend(fir.lable.lat.to_f64().unwrap().to_le_bytes());
+    properties.extend(3u16.to_le_bytes());
+    properties.extend(fir.lable.lon.to_f64().unwrap().to_le_bytes());
+    let parts = rings
+        .into_iter()
+        .map(|xy| {
+            Table(vec![
+                (1, Value::Doubles(xy)),
+                (6, Value::U8(GEOMETRY_POLYGON)),
+            ])
+        })
+        .collect();
+    Table(vec![
+        (
+            0,
+            Value::Table(Table(vec![
+                (6, Value::U8(GEOMETRY_MULTIPOLYGON)),
+                (7, Value::Tables(parts)),
+            ])),
+        ),
+        (1, Value::Bytes(properties)),
+    ])
+}
+
+/// Writes the FIRs as FlatGeobuf with a spatial index, every FIR with its extentions as one feature.
+pub(crate) fn to_writer<W: Write>(firs: &[FIRBoundary], writer: &mut W) -> io::Result<()> {
+    let groups = with_extentions(firs);
+    let mut items: Vec<(Envelope, usize, Vec<Vec<f64>>)> = groups
+        .iter()
+        .map(|group| {
+            let rings = rings(firs, group);
+            (envelope(&rings), group[0], rings)
+        })
+        .collect();
+    let extent = items.iter().fold(EMPTY, |e, (item, _, _)| expand(e, *item));
+    items.sort_by_key(|(e, _, _)| std::cmp::Reverse(hilbert_value(e, &extent)));
+
+    let mut leaves = Vec::with_capacity(items.len());
+    let mut features = Vec::with_capacity(items.len());
+    let mut offset = 0;
+    for (e, fir, rings) in items {
+        let feature = Builder::finish(&feature(&firs[fir], rings));
+        leaves.push((e, offset));
+        offset += feature.len() as u64;
+        features.push(feature);
+    }
+
+    let extent = match features.is_empty() {
+        true => [0.0; 4],
+        false => extent,
+    };
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&Builder::finish(&header(extent, features.len())))?;
+    if !leaves.is_empty() {
+        for (e, offset) in packed_rtree(&leaves, NODE_SIZE as usize) {
+            e.iter()
+                .try_for_each(|v| writer.write_all(&v.to_le_bytes()))?;
+            writer.write_all(&offset.to_le_bytes())?;
+        }
+    }
+    features.iter().try_for_each(|f| writer.write_all(f))
+}
+
+pub(crate) fn write_file<P: AsRef<Path>>(firs: &[FIRBoundary], p: P) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(p)?);
+    to_writer(firs, &mut writer)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::*;
+    use crate::fir_boundaries::fir;
+
+    const NODE_ITEM_LEN: usize = 40;
+
+    fn u32_at(buf: &[u8], at: usize) -> usize {
+        u32::from_le_bytes(buf[at..at + 4].try_into().unwrap()) as usize
+    }
+
+    /// Position of a field of the table at `table`, reading the vtable like a flatbuffer reader.
+    fn field(buf: &[u8], table: usize, n: usize) -> Option<usize> {
+        let vtable = table - i32::from_le_bytes(buf[table..table + 4].try_into().unwrap()) as usize;
+        let vtable_len = u16::from_le_bytes(buf[vtable..vtable + 2].try_into().unwrap()) as usize;
+        if 4 + 2 * n >= vtable_len {
+            return None;
+        }
+        let at = vtable + 4 + 2 * n;
+        match u16::from_le_bytes(buf[at..at + 2].try_into().unwrap()) as usize {
+            0 => None,
+            offset => Some(table + offset),
+        }
+    }
+
+    fn string(buf: &[u8], at: usize) -> &str {
+        let pos = at + u32_at(buf, at);
+        std::str::from_utf8(&buf[pos + 4..pos + 4 + u32_at(buf, pos)]).unwrap()
+    }
+
+    #[test]
+    fn header_and_index() {
+        let firs: Vec<FIRBoundary> = (0..20)
+            .map(|n| {
+                fir(
+                    &format!("X{:03}", n),
+                    false,
+                    &[(0, n), (1, n), (1, n + 1), (0, n + 1)],
+                )
+            })
+            .collect();
+        let mut out = Vec::new();
+        to_writer(&firs, &mut out).unwrap();
+        assert_eq!(out[..8], MAGIC);
+
+        let header = &out[8..8 + 4 + u32_at(&out, 8)];
+        let root = 4 + u32_at(header, 4);
+        assert_eq!(
+            string(header, field(header, root, 0).unwrap()),
+            "FIR boundaries"
+        );
+        let count = field(header, root, 8).unwrap();
+        assert_eq!(
+            u64::from_le_bytes(header[count..count + 8].try_into().unwrap()),
+            20
+        );
+        let columns = field(header, root, 7).unwrap();
+        let columns = columns + u32_at(header, columns);
+        assert_eq!(u32_at(header, columns), 4);
+        let first = columns + 4 + u32_at(header, columns + 4);
+        assert_eq!(string(header, field(header, first, 0).unwrap()), "ICAO");
+
+        // 20 leaves, 2 nodes above them and the root
+        let index = 8 + header.len();
+        let nodes = level_bounds(20, NODE_SIZE as usize);
+        assert_eq!(nodes, vec![(3, 23), (1, 3), (0, 1)]);
+        let node = |n: usize| {
+            let at = index + n * NODE_ITEM_LEN;
+            let v =
+                |i: usize| f64::from_le_bytes(out[at + 8 * i..at + 8 * i + 8].try_into().unwrap());
+            (
+                [v(0), v(1), v(2), v(3)],
+                u64::from_le_bytes(out[at + 32..at + 40].try_into().unwrap()),
+            )
+        };
+        assert_eq!(node(0), ([0.0, 0.0, 20.0, 1.0], 1));
+        assert_eq!(node(1).1, 3);
+        assert_eq!(node(2).1, 3 + NODE_SIZE as u64);
+
+        // every leaf points to a feature with the same envelope, and the features follow the index
+        let features = index + 23 * NODE_ITEM_LEN;
+        for n in 3..23 {
+            let (e, offset) = node(n);
+            let feature = &out[features + offset as usize..];
+            let feature = &feature[..4 + u32_at(feature, 0)];
+            let root = 4 + u32_at(feature, 4);
+            let properties = field(feature, root, 1).unwrap();
+            let properties = properties + u32_at(feature, properties);
+            let icao_len = u32_at(feature, properties + 6);
+            let icao =
+                std::str::from_utf8(&feature[properties + 10..properties + 10 + icao_len]).unwrap();
+            let n: i64 = icao[1..].parse().unwrap();
+            assert_eq!(e, [n as f64, 0.0, n as f64 + 1.0, 1.0]);
+        }
+    }
+
+    #[test]
+    fn hilbert_curve() {
+        assert_eq!(hilbert(0, 0), 0);
+        assert_eq!(hilbert(1, 0), 1);
+        assert_eq!(hilbert(1, 1), 2);
+        assert_eq!(hilbert(0, 1), 3);
+        assert_eq!(hilbert(12345, 54321), 1555040834);
+        assert_eq!(hilbert(0xFFFF, 0), u32::MAX);
+    }
+}
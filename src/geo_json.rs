@@ -59,7 +59,7 @@ where
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct Crs {
+pub(crate) struct Crs {
     #[serde(rename = "type")]
     typ: String,
     pub(crate) properties: IndexMap<String, String>,
@@ -153,6 +153,7 @@ impl From<&IndexSet<Point>> for Geometry {
  */
 
 impl Geometry {
+    #[allow(dead_code)]
     fn polygon_or_hole(&self) -> Vec<Fill> {
         self.array[0].iter().map(|s| polygon_or_hole(s)).collect()
     }
@@ -164,7 +165,7 @@ mod tests {
 
     fn make_test_geometry() -> Geometry {
         let a = [[1, 1], [0, 2], [1, 3], [2, 2]];
-        let arr = std::array::IntoIter::new(a)
+        let arr = IntoIterator::into_iter(a)
             .map(|v| Point::new(v[1].into(), v[0].into()))
             .collect::<Result<Vec<_>, _>>()
             .unwrap();
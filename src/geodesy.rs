@@ -0,0 +1,534 @@
+use rust_decimal::{
+    prelude::{FromPrimitive, ToPrimitive},
+    Decimal,
+};
+
+use crate::fir_boundaries::{polygon_or_hole, FIRParsingError, FIRResult, Fill, Point};
+
+/// Semi-major axis of the WGS84 ellipsoid in metres.
+pub(crate) const WGS84_A: f64 = 6_378_137.0;
+/// Flattening of the WGS84 ellipsoid.
+pub(crate) const WGS84_F: f64 = 1.0 / 298.257_223_563;
+
+pub(crate) const METRES_PER_NM: f64 = 1852.0;
+
+/// A point in plain floating point degrees, used for all geometric calculations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Coord {
+    pub(crate) lat: f64,
+    pub(crate) lon: f64,
+}
+
+impl From<&Point> for Coord {
+    fn from(p: &Point) -> Self {
+        Self {
+            lat: p.lat.to_f64().unwrap(),
+            lon: p.lon.to_f64().unwrap(),
+        }
+    }
+}
+
+impl Coord {
+    /// Converts back to a Point, rounded to 6 decimals (about 0.1 m).
+    pub(crate) fn to_point(self) -> Point {
+        let round = |v: f64| Decimal::from_f64(v).unwrap().round_dp(6).normalize();
+        Point::new(
+            round(self.lat.clamp(-90.0, 90.0)),
+            round(normalize_lon(self.lon)),
+        )
+        .unwrap()
+    }
+}
+
+/// Returns the points of a boundary as coordinates, without the closing point if the ring has one.
+/// Longitudes are unwrapped so that no edge jumps more than 180 degrees,
+/// which keeps FIRs crossing the antimeridian in one piece.
+pub(crate) fn ring_coords(points: &[Point]) -> Vec<Coord> {
+    let points = match points.len() > 1 && points[0] == points[points.len() - 1] {
+        true => &points[..points.len() - 1],
+        false => points,
+    };
+    let mut ring: Vec<Coord> = points.iter().map(Coord::from).collect();
+    for n in 1..ring.len() {
+        let prev = ring[n - 1].lon;
+        let lon = &mut ring[n].lon;
+        while *lon - prev > 180.0 {
+            *lon -= 360.0;
+        }
+        while *lon - prev < -180.0 {
+            *lon += 360.0;
+        }
+    }
+    ring
+}
+
+/// Brings a longitude back into the range -180..=180.
+pub(crate) fn normalize_lon(mut lon: f64) -> f64 {
+    while lon > 180.0 {
+        lon -= 360.0;
+    }
+    while lon < -180.0 {
+        lon += 360.0;
+    }
+    lon
+}
+
+fn eccentricity_squared() -> f64 {
+    WGS84_F * (2.0 - WGS84_F)
+}
+
+/// `q` from Snyder's "Map Projections - A Working Manual" eq. 3-12, used for the authalic latitude.
+fn authalic_q(sin_lat: f64) -> f64 {
+    let e2 = eccentricity_squared();
+    let e = e2.sqrt();
+    (1.0 - e2)
+        * (sin_lat / (1.0 - e2 * sin_lat * sin_lat)
+            - 1.0 / (2.0 * e) * ((1.0 - e * sin_lat) / (1.0 + e * sin_lat)).ln())
+}
+
+/// Radius of the sphere with the same surface area as the WGS84 ellipsoid.
+fn authalic_radius() -> f64 {
+    WGS84_A * (authalic_q(1.0) / 2.0).sqrt()
+}
+
+/// Maps a geodetic latitude (degrees) to the authalic latitude (radians).
+fn authalic_lat(lat: f64) -> f64 {
+    let ratio = authalic_q(lat.to_radians().sin()) / authalic_q(1.0);
+    ratio.clamp(-1.0, 1.0).asin()
+}
+
+/// Area of a ring on the WGS84 ellipsoid in square metres.
+///
+/// The ring is mapped onto the authalic sphere, which preserves areas,
+/// and the spherical excess is summed edge by edge.
+/// The sign follows the draw direction, positive for counterclockwise rings (seen with lon as x and lat as y).
+pub(crate) fn signed_area(ring: &[Coord]) -> f64 {
+    let r = authalic_radius();
+    let excess: f64 = ring
+        .iter()
+        .zip(ring.iter().cycle().skip(1))
+        .map(|(a, b)| {
+            let t1 = (authalic_lat(a.lat) / 2.0).tan();
+            let t2 = (authalic_lat(b.lat) / 2.0).tan();
+            let dlon = (b.lon - a.lon).to_radians();
+            2.0 * ((dlon / 2.0).tan() * (t1 + t2) / (1.0 + t1 * t2)).atan()
+        })
+        .sum();
+    -excess * r * r
+}
+
+pub(crate) fn area(ring: &[Coord]) -> f64 {
+    signed_area(ring).abs()
+}
+
+/// Distance in metres along the geodesic between two points, using Vincenty's inverse formula.
+pub(crate) fn distance(a: Coord, b: Coord) -> f64 {
+    let f = WGS84_F;
+    let b_axis = WGS84_A * (1.0 - f);
+    let l = (b.lon - a.lon).to_radians();
+    let u1 = ((1.0 - f) * a.lat.to_radians().tan()).atan();
+    let u2 = ((1.0 - f) * b.lat.to_radians().tan()).atan();
+    let (sin_u1, cos_u1) = u1.sin_cos();
+    let (sin_u2, cos_u2) = u2.sin_cos();
+
+    let mut lambda = l;
+    for _ in 0..200 {
+        let (sin_lambda, cos_lambda) = lambda.sin_cos();
+        let sin_sigma = ((cos_u2 * sin_lambda).powi(2)
+            + (cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda).powi(2))
+        .sqrt();
+        if sin_sigma == 0.0 {
+            return 0.0;
+        }
+        let cos_sigma = sin_u1 * sin_u2 + cos_u1 * cos_u2 * cos_lambda;
+        let sigma = sin_sigma.atan2(cos_sigma);
+        let sin_alpha = cos_u1 * cos_u2 * sin_lambda / sin_sigma;
+        let cos2_alpha = 1.0 - sin_alpha * sin_alpha;
+        let cos_2sigma_m = if cos2_alpha == 0.0 {
+            0.0 // both points on the equator
+        } else {
+            cos_sigma - 2.0 * sin_u1 * sin_u2 / cos2_alpha
+        };
+        let c = f / 16.0 * cos2_alpha * (4.0 + f * (4.0 - 3.0 * cos2_alpha));
+        let prev = lambda;
+        lambda = l
+            + (1.0 - c)
+                * f
+                * sin_alpha
+                * (sigma
+                    + c * sin_sigma
+                        * (cos_2sigma_m + c * cos_sigma * (-1.0 + 2.0 * cos_2sigma_m.powi(2))));
+        if (lambda - prev).abs() < 1e-12 {
+            let u_sq = cos2_alpha * (WGS84_A.powi(2) - b_axis.powi(2)) / b_axis.powi(2);
+            let big_a =
+                1.0 + u_sq / 16384.0 * (4096.0 + u_sq * (-768.0 + u_sq * (320.0 - 175.0 * u_sq)));
+            let big_b = u_sq / 1024.0 * (256.0 + u_sq * (-128.0 + u_sq * (74.0 - 47.0 * u_sq)));
+            let delta_sigma = big_b
+                * sin_sigma
+                * (cos_2sigma_m
+                    + big_b / 4.0
+                        * (cos_sigma * (-1.0 + 2.0 * cos_2sigma_m.powi(2))
+                            - big_b / 6.0
+                                * cos_2sigma_m
+                                * (-3.0 + 4.0 * sin_sigma.powi(2))
+                                * (-3.0 + 4.0 * cos_2sigma_m.powi(2))));
+            return b_axis * big_a * (sigma - delta_sigma);
+        }
+    }
+    // Vincenty does not converge for nearly antipodal points, fall back to the great circle.
+    great_circle_distance(a, b)
+}
+
+/// Distance in metres along the great circle on the authalic sphere.
+pub(crate) fn great_circle_distance(a: Coord, b: Coord) -> f64 {
+    let (lat1, lat2) = (a.lat.to_radians(), b.lat.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = (b.lon - a.lon).to_radians();
+    let h = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * authalic_radius() * h.sqrt().min(1.0).asin()
+}
+
+/// The point `distance` metres from `origin` along the great circle starting out at `bearing` degrees from true north.
+pub(crate) fn destination(origin: Coord, bearing: f64, distance: f64) -> Coord {
+    let (lat1, lon1) = (origin.lat.to_radians(), origin.lon.to_radians());
+    let (bearing, d) = (bearing.to_radians(), distance / authalic_radius());
+    let lat2 = (lat1.sin() * d.cos() + lat1.cos() * d.sin() * bearing.cos()).asin();
+    let lon2 =
+        lon1 + (bearing.sin() * d.sin() * lat1.cos()).atan2(d.cos() - lat1.sin() * lat2.sin());
+    Coord {
+        lat: lat2.to_degrees(),
+        lon: normalize_lon(lon2.to_degrees()),
+    }
+}
+
+/// Points along an arc around `center` with a radius of `radius` metres, at most `step` degrees apart.
+/// The arc starts at the bearing `start` and turns `sweep` degrees, clockwise when `sweep` is positive.
+/// A `step` that is not above 0 is an error, as the arc would never end.
+pub(crate) fn arc_points(
+    center: Coord,
+    radius: f64,
+    start: f64,
+    sweep: f64,
+    step: f64,
+) -> FIRResult<Vec<Coord>> {
+    if step.is_nan() || step <= 0.0 {
+        return Err(FIRParsingError::FIRParsing(format!(
+            "arc step must be above 0, found: {}",
+            step
+        )));
+    }
+    let steps = (sweep.abs() / step).ceil().max(1.0) as usize;
+    Ok((0..=steps)
+        .map(|n| destination(center, start + sweep * n as f64 / steps as f64, radius))
+        .collect())
+}
+
+/// Where a straight segment and an arc meet, points closer than this many metres are taken to be the same point.
+const JOIN_TOLERANCE: f64 = 100.0;
+
+/// Builds a ring out of given points and points calculated along arcs.
+/// Calculated points seldom hit the given points exactly, so where they meet the given point is kept.
+#[derive(Debug, Default)]
+pub(crate) struct RingBuilder {
+    points: Vec<Coord>,
+    last_from_arc: bool,
+}
+
+impl RingBuilder {
+    pub(crate) fn last(&self) -> Option<Coord> {
+        self.points.last().copied()
+    }
+
+    fn push(&mut self, points: Vec<Coord>, from_arc: bool) {
+        let mut points = points.into_iter().peekable();
+        if let (Some(last), Some(first)) = (self.points.last(), points.peek()) {
+            if great_circle_distance(*last, *first) < JOIN_TOLERANCE {
+                match from_arc {
+                    true => drop(points.next()),
+                    false if self.last_from_arc => drop(self.points.pop()),
+                    false => (),
+                }
+            }
+        }
+        self.points.extend(points);
+        self.last_from_arc = from_arc;
+    }
+
+    pub(crate) fn push_points(&mut self, points: Vec<Coord>) {
+        self.push(points, false)
+    }
+
+    pub(crate) fn push_arc(&mut self, points: Vec<Coord>) {
+        self.push(points, true)
+    }
+
+    /// The points of the ring, without a closing point.
+    pub(crate) fn finish(mut self) -> Vec<Coord> {
+        let n = self.points.len();
+        if n > 1 && great_circle_distance(self.points[0], self.points[n - 1]) < JOIN_TOLERANCE {
+            self.points.pop();
+        }
+        self.points
+    }
+}
+
+/// Turns a ring into boundary corners: rounded, without repeated or closing points, drawn as a FIR.
+pub(crate) fn boundary_corners(ring: &[Coord]) -> Vec<Point> {
+    let mut corners: Vec<Point> = ring.iter().map(|c| c.to_point()).collect();
+    corners.dedup();
+    while corners.len() > 1 && corners.first() == corners.last() {
+        corners.pop();
+    }
+    if corners.len() >= 3 && polygon_or_hole(&corners) == Fill::Hole {
+        corners.reverse();
+    }
+    corners
+}
+
+/// Initial bearing in degrees from true north, 0..360, of the great circle from `a` to `b`.
+pub(crate) fn bearing(a: Coord, b: Coord) -> f64 {
+    let (lat1, lat2) = (a.lat.to_radians(), b.lat.to_radians());
+    let dlon = (b.lon - a.lon).to_radians();
+    let y = dlon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+    y.atan2(x).to_degrees().rem_euclid(360.0)
+}
+
+/// Length of the closed ring in metres.
+pub(crate) fn perimeter(ring: &[Coord]) -> f64 {
+    ring.iter()
+        .zip(ring.iter().cycle().skip(1))
+        .map(|(a, b)| distance(*a, *b))
+        .sum()
+}
+
+/// Area weighted centroid of the ring, calculated in the plane of latitude and longitude.
+pub(crate) fn centroid(ring: &[Coord]) -> Coord {
+    let (mut a, mut cx, mut cy) = (0.0, 0.0, 0.0);
+    for (p, q) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+        let cross = p.lon * q.lat - q.lon * p.lat;
+        a += cross;
+        cx += (p.lon + q.lon) * cross;
+        cy += (p.lat + q.lat) * cross;
+    }
+    if a == 0.0 {
+        let n = ring.len() as f64;
+        return Coord {
+            lat: ring.iter().map(|c| c.lat).sum::<f64>() / n,
+            lon: normalize_lon(ring.iter().map(|c| c.lon).sum::<f64>() / n),
+        };
+    }
+    Coord {
+        lat: cy / (3.0 * a),
+        lon: normalize_lon(cx / (3.0 * a)),
+    }
+}
+
+/// Projects `p` onto a plane in nautical miles around `origin`. Only accurate close to `origin`.
+pub(crate) fn project_nm(origin: Coord, p: Coord) -> (f64, f64) {
+    let mut dlon = p.lon - origin.lon;
+    if dlon > 180.0 {
+        dlon -= 360.0;
+    } else if dlon < -180.0 {
+        dlon += 360.0;
+    }
+    (
+        dlon * 60.0 * origin.lat.to_radians().cos(),
+        (p.lat - origin.lat) * 60.0,
+    )
+}
+
+/// Finds the point on the segment between `a` and `b` closest to `p`,
+/// and the distance to it in nautical miles.
+pub(crate) fn closest_on_segment(p: Coord, a: Coord, b: Coord) -> (Coord, f64) {
+    let origin = Coord {
+        lat: (a.lat + b.lat) / 2.0,
+        lon: a.lon,
+    };
+    let (px, py) = project_nm(origin, p);
+    let (ax, ay) = project_nm(origin, a);
+    let (bx, by) = project_nm(origin, b);
+    let (dx, dy) = (bx - ax, by - ay);
+    let len2 = dx * dx + dy * dy;
+    let t = match len2 == 0.0 {
+        true => 0.0,
+        false => (((px - ax) * dx + (py - ay) * dy) / len2).clamp(0.0, 1.0),
+    };
+    let (cx, cy) = (ax + t * dx, ay + t * dy);
+    let closest = Coord {
+        lat: origin.lat + cy / 60.0,
+        lon: normalize_lon(origin.lon + cx / (60.0 * origin.lat.to_radians().cos())),
+    };
+    (closest, ((px - cx).powi(2) + (py - cy).powi(2)).sqrt())
+}
+
+/// Distance in nautical miles from `p` to the segment between `a` and `b`.
+pub(crate) fn segment_distance_nm(p: Coord, a: Coord, b: Coord) -> f64 {
+    closest_on_segment(p, a, b).1
+}
+
+/// Tells if `p` is inside the ring, treating latitude and longitude as a plane.
+/// The longitude of `p` is moved by 360 degrees if needed to be next to the unwrapped ring.
+pub(crate) fn contains(ring: &[Coord], p: Coord) -> bool {
+    let (min_lon, max_lon) = ring
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), c| {
+            (min.min(c.lon), max.max(c.lon))
+        });
+    let mut lon = p.lon;
+    if lon < min_lon {
+        lon += 360.0;
+    } else if lon > max_lon {
+        lon -= 360.0;
+    }
+    let mut inside = false;
+    for (a, b) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+        if (a.lat > p.lat) != (b.lat > p.lat)
+            && lon < (b.lon - a.lon) * (p.lat - a.lat) / (b.lat - a.lat) + a.lon
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Points where edges of a ring cross or touch each other, treating latitude and longitude as a plane.
+/// Neighbouring edges meeting at their shared corner are not counted. Use an unwrapped ring from `ring_coords`.
+pub(crate) fn self_intersections(ring: &[Coord]) -> Vec<Coord> {
+    crossings(ring, true)
+}
+
+/// Like `self_intersections`, for a line that does not go back to its first point.
+pub(crate) fn line_self_intersections(line: &[Coord]) -> Vec<Coord> {
+    crossings(line, false)
+}
+
+fn crossings(points: &[Coord], closed: bool) -> Vec<Coord> {
+    let n = points.len();
+    let edges = match closed {
+        true => n,
+        false => n.saturating_sub(1),
+    };
+    let edge = |i: usize| (points[i], points[(i + 1) % n]);
+    let mut found = Vec::new();
+    for i in 0..edges {
+        let (a, b) = edge(i);
+        for j in i + 1..edges {
+            if j == i + 1 || (closed && i == 0 && j == n - 1) {
+                continue;
+            }
+            let (c, d) = edge(j);
+            if a.lon.max(b.lon) < c.lon.min(d.lon)
+                || c.lon.max(d.lon) < a.lon.min(b.lon)
+                || a.lat.max(b.lat) < c.lat.min(d.lat)
+                || c.lat.max(d.lat) < a.lat.min(b.lat)
+            {
+                continue;
+            }
+            let (rx, ry) = (b.lon - a.lon, b.lat - a.lat);
+            let (sx, sy) = (d.lon - c.lon, d.lat - c.lat);
+            let (qx, qy) = (c.lon - a.lon, c.lat - a.lat);
+            let denominator = rx * sy - ry * sx;
+            if denominator == 0.0 {
+                // parallel, overlapping if they are on the same line
+                if qx * ry - qy * rx == 0.0 {
+                    found.push(c);
+                }
+                continue;
+            }
+            let t = (qx * sy - qy * sx) / denominator;
+            let u = (qx * ry - qy * rx) / denominator;
+            if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u) {
+                found.push(Coord {
+                    lat: a.lat + t * ry,
+                    lon: a.lon + t * rx,
+                });
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(lat: f64, lon: f64, size: f64) -> Vec<Coord> {
+        vec![
+            Coord { lat, lon },
+            Coord {
+                lat,
+                lon: lon + size,
+            },
+            Coord {
+                lat: lat + size,
+                lon: lon + size,
+            },
+            Coord {
+                lat: lat + size,
+                lon,
+            },
+        ]
+    }
+
+    #[test]
+    fn one_degree_on_equator() {
+        let d = distance(Coord { lat: 0.0, lon: 0.0 }, Coord { lat: 0.0, lon: 1.0 });
+        assert!((d - 111_319.49).abs() < 0.1, "{}", d);
+    }
+
+    #[test]
+    fn one_degree_of_latitude() {
+        let d = distance(Coord { lat: 0.0, lon: 0.0 }, Coord { lat: 1.0, lon: 0.0 });
+        assert!((d - 110_574.39).abs() < 0.1, "{}", d);
+    }
+
+    #[test]
+    fn area_of_equator_cell() {
+        // reference value from GeographicLib Planimeter
+        let a = area(&square(0.0, 0.0, 1.0)) / 1e6;
+        assert!((a - 12_308.78).abs() < 1.0, "{}", a);
+    }
+
+    #[test]
+    fn area_sign_follows_direction() {
+        let mut ring = square(60.0, 10.0, 1.0);
+        assert!(signed_area(&ring) > 0.0);
+        ring.reverse();
+        assert!(signed_area(&ring) < 0.0);
+    }
+
+    #[test]
+    fn antimeridian_ring_is_unwrapped() {
+        let points = [(0, 179), (0, -179), (1, -179), (1, 179)]
+            .iter()
+            .map(|(lat, lon)| Point::new((*lat).into(), (*lon).into()).unwrap())
+            .collect::<Vec<_>>();
+        let ring = ring_coords(&points);
+        let a = area(&ring) / 1e6;
+        assert!((a - 2.0 * 12_308.78).abs() < 2.0, "{}", a);
+        assert!((centroid(&ring).lon.abs() - 180.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn arc_step_must_be_positive() {
+        let center = Coord { lat: 0.0, lon: 0.0 };
+        assert_eq!(
+            arc_points(center, 1000.0, 0.0, 90.0, 10.0).unwrap().len(),
+            10
+        );
+        assert!(arc_points(center, 1000.0, 0.0, 90.0, 0.0).is_err());
+        assert!(arc_points(center, 1000.0, 0.0, 90.0, -5.0).is_err());
+        assert!(arc_points(center, 1000.0, 0.0, 90.0, f64::NAN).is_err());
+    }
+
+    #[test]
+    fn bow_tie_crosses_itself() {
+        let mut ring = square(0.0, 0.0, 2.0);
+        assert!(self_intersections(&ring).is_empty());
+        ring.swap(2, 3);
+        let crossings = self_intersections(&ring);
+        assert_eq!(crossings.len(), 1);
+        assert!((crossings[0].lat - 1.0).abs() < 1e-9 && (crossings[0].lon - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,204 @@
+use std::{
+    fmt::{self, Display},
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use itertools::Itertools;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal_macros::dec;
+use serde_json::{json, Value};
+
+use crate::{
+    fir_boundaries::{FIRBoundary, Point},
+    geo_json::GeoJson,
+    geodesy::{self, Coord},
+    vatspy_dat::IdlSegment,
+};
+
+/// Problems found in the [IDL] section.
+#[derive(Debug, Clone)]
+pub(crate) enum Diagnostic {
+    /// There is no [IDL] section, or it is empty.
+    Missing,
+    /// A segment does not start where the one before it ends.
+    Gap {
+        line: usize,
+        end: Point,
+        start: Point,
+    },
+    /// The line does not go from one pole to the other.
+    NotPoleToPole {
+        start: Point,
+        end: Point,
+    },
+    Crossing(Vec<Point>),
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Diagnostic::Missing => write!(f, "IDL: there is no [IDL] section"),
+            Diagnostic::Gap { line, end, start } => write!(
+                f,
+                "IDL: the segment on line {} starts at {}, but the one before it ends at {}",
+                line, start, end
+            ),
+            Diagnostic::NotPoleToPole { start, end } => write!(
+                f,
+                "IDL: the line runs from {} to {}, it needs to run from one pole to the other",
+                start, end
+            ),
+            Diagnostic::Crossing(points) => write!(
+                f,
+                "IDL: the line crosses itself at: {}",
+                points.iter().join(", ")
+            ),
+        }
+    }
+}
+
+/// The points of the line in order. Segments are joined where they meet, and a gap is bridged by a straight line.
+pub(crate) fn polyline(segments: &[IdlSegment]) -> Vec<Point> {
+    segments
+        .iter()
+        .flat_map(|s| vec![s.start.clone(), s.end.clone()])
+        .dedup()
+        .collect()
+}
+
+/// Moves longitudes by 360 degrees where needed, so no step between two points is more than 180 degrees.
+/// The line crosses the antimeridian back and forth, and is drawn across the whole map without this.
+pub(crate) fn unwrap(points: &[Point]) -> Vec<Point> {
+    let mut unwrapped: Vec<Point> = Vec::with_capacity(points.len());
+    for p in points {
+        let mut lon = p.lon;
+        if let Some(prev) = unwrapped.last() {
+            while lon - prev.lon > dec!(180) {
+                lon -= dec!(360);
+            }
+            while lon - prev.lon < dec!(-180) {
+                lon += dec!(360);
+            }
+        }
+        unwrapped.push(Point { lat: p.lat, lon });
+    }
+    unwrapped
+}
+
+/// Checks that the segments join up, and that the line runs from pole to pole without crossing itself.
+pub(crate) fn check(segments: &[IdlSegment]) -> Vec<Diagnostic> {
+    if segments.is_empty() {
+        return vec![Diagnostic::Missing];
+    }
+    let mut diagnostics: Vec<Diagnostic> = segments
+        .windows(2)
+        .filter(|w| w[0].end != w[1].start)
+        .map(|w| Diagnostic::Gap {
+            line: w[1].line,
+            end: w[0].end.clone(),
+            start: w[1].start.clone(),
+        })
+        .collect();
+    let points = polyline(segments);
+    let (start, end) = (&points[0], &points[points.len() - 1]);
+    if start.lat.abs() != dec!(90) || end.lat != -start.lat {
+        diagnostics.push(Diagnostic::NotPoleToPole {
+            start: start.clone(),
+            end: end.clone(),
+        });
+    }
+    let coords: Vec<Coord> = unwrap(&points).iter().map(Coord::from).collect();
+    let crossings = geodesy::line_self_intersections(&coords);
+    if !crossings.is_empty() {
+        diagnostics.push(Diagnostic::Crossing(
+            crossings
+                .into_iter()
+                .map(|c| {
+                    Coord {
+                        lat: c.lat,
+                        lon: geodesy::normalize_lon(c.lon),
+                    }
+                    .to_point()
+                })
+                .collect(),
+        ));
+    }
+    diagnostics
+}
+
+/// The line as a GeoJSON LineString feature, with unwrapped longitudes so it is drawn in one piece.
+/// Coordinates are written as numbers, so map tools can draw it.
+pub(crate) fn feature(points: &[Point]) -> Value {
+    let coordinates: Vec<[f64; 2]> = unwrap(points)
+        .iter()
+        .map(|p| [p.lon.to_f64().unwrap(), p.lat.to_f64().unwrap()])
+        .collect();
+    json!({
+        "type": "Feature",
+        "properties": {
+            "Name": "International Date Line",
+        },
+        "geometry": {
+            "type": "LineString",
+            "coordinates": coordinates,
+        },
+    })
+}
+
+/// Writes a GeoJSON FeatureCollection with the FIRs, like a GeoJSON output file, and the line as the last feature.
+pub(crate) fn write_file<P: AsRef<Path>>(
+    p: P,
+    firs: &[FIRBoundary],
+    points: &[Point],
+) -> io::Result<()> {
+    let mut collection = serde_json::to_value(GeoJson::from(firs))?;
+    collection["features"]
+        .as_array_mut()
+        .unwrap()
+        .push(feature(points));
+    let mut writer = BufWriter::new(File::create(p)?);
+    serde_json::to_writer_pretty(&mut writer, &collection)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::vatspy_dat::VatSpyDat;
+
+    #[test]
+    fn pole_to_pole() {
+        let dat = VatSpyDat::parse(
+            "[IDL]\n90|180|65|-169\n65|-169|0|180\n0|180|-50|172\n-50|172|-90|180\n",
+        )
+        .unwrap();
+        assert!(check(&dat.idl).is_empty());
+        let points = polyline(&dat.idl);
+        assert_eq!(points.len(), 5);
+        let lons: Vec<String> = unwrap(&points).iter().map(|p| p.lon.to_string()).collect();
+        assert_eq!(lons, vec!["180", "191", "180", "172", "180"]);
+
+        let feature = feature(&points);
+        assert_eq!(feature["geometry"]["type"], "LineString");
+        assert_eq!(feature["geometry"]["coordinates"][1], json!([191.0, 65.0]));
+    }
+
+    #[test]
+    fn broken_line() {
+        let dat =
+            VatSpyDat::parse("[IDL]\n90|180|0|-170\n0|-175|10|175\n10|175|-10|-160\n").unwrap();
+        let diagnostics: Vec<String> = check(&dat.idl).iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                "IDL: the segment on line 3 starts at 0|-175, but the one before it ends at 0|-170",
+                "IDL: the line runs from 90|180 to -10|-160, it needs to run from one pole to the other",
+                "IDL: the line crosses itself at: 0|-172.5",
+            ]
+        );
+        assert_eq!(check(&[]).len(), 1);
+    }
+}
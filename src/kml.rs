@@ -0,0 +1,298 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use indexmap::IndexMap;
+use roxmltree::{Document, Node};
+
+use crate::{
+    fir_boundaries::{
+        try_polygon_or_hole, with_extentions, FIRBoundary, FIRParsingError, FIRResult, Fill, Point,
+    },
+    lable,
+    topology::open_ring,
+};
+
+/// Name of the region a FIR belongs to, from the first letter of the ICAO.
+fn region(icao: &str) -> &'static str {
+    match icao.chars().next().map(|c| c.to_ascii_uppercase()) {
+        Some('A') => "A - South West Pacific",
+        Some('B') => "B - Greenland and Iceland",
+        Some('C') => "C - Canada",
+        Some('D') => "D - West Africa",
+        Some('E') => "E - Northern Europe",
+        Some('F') => "F - Southern and Central Africa",
+        Some('G') => "G - North West Africa",
+        Some('H') => "H - East Africa",
+        Some('K') => "K - United States",
+        Some('L') => "L - Southern Europe",
+        Some('M') => "M - Central America",
+        Some('N') => "N - South Pacific",
+        Some('O') => "O - Middle East",
+        Some('P') => "P - North Pacific",
+        Some('R') => "R - East Asia",
+        Some('S') => "S - South America",
+        Some('T') => "T - Caribbean",
+        Some('U') => "U - Russia and Central Asia",
+        Some('V') => "V - South Asia",
+        Some('W') => "W - South East Asia",
+        Some('Y') => "Y - Australia",
+        Some('Z') => "Z - China",
+        _ => "Other",
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// KML coordinates, longitude first, with the ring closed.
+fn coordinates(points: &[Point]) -> String {
+    points
+        .iter()
+        .chain(points.first())
+        .map(|p| format!("{},{}", p.lon, p.lat))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+const STYLES: &str = r#"    <Style id="fir">
+      <LineStyle><color>ff3c14dc</color><width>1.5</width></LineStyle>
+      <PolyStyle><color>333c14dc</color></PolyStyle>
+    </Style>
+    <Style id="oceanic">
+      <LineStyle><color>ffe16941</color><width>1.5</width></LineStyle>
+      <PolyStyle><color>33e16941</color></PolyStyle>
+    </Style>
+    <Style id="lable">
+      <IconStyle><scale>0</scale></IconStyle>
+      <LabelStyle><scale>0.8</scale></LabelStyle>
+    </Style>
+"#;
+
+/// Writes the FIRs as KML, in one folder per region. Every FIR is a placemark with the FIR and its
+/// extentions as polygons, and has a second placemark with only the name at the lable.
+/// Oceanic FIRs are drawn in blue and the rest in red.
+pub(crate) fn to_writer<W: Write>(firs: &[FIRBoundary], writer: &mut W) -> io::Result<()> {
+    let mut regions: IndexMap<&str, Vec<Vec<usize>>> = IndexMap::new();
+    for group in with_extentions(firs) {
+        regions
+            .entry(region(&firs[group[0]].icao))
+            .or_default()
+            .push(group);
+    }
+    regions.sort_keys();
+
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(writer, r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#)?;
+    writeln!(writer, "  <Document>")?;
+    writeln!(writer, "    <name>FIR boundaries</name>")?;
+    write!(writer, "{}", STYLES)?;
+    for (region, groups) in regions {
+        writeln!(writer, "    <Folder>")?;
+        writeln!(writer, "      <name>{}</name>", escape(region))?;
+        for group in groups {
+            let fir = &firs[group[0]];
+            let icao = escape(&fir.icao);
+            let style = match fir.is_oseanic {
+                true => "oceanic",
+                false => "fir",
+            };
+            let data = format!(
+                r#"<ExtendedData><Data name="IsOceanic"><value>{}</value></Data></ExtendedData>"#,
+                fir.is_oseanic
+            );
+            writeln!(writer, "      <Placemark>")?;
+            writeln!(writer, "        <name>{}</name>", icao)?;
+            writeln!(writer, "        <styleUrl>#{}</styleUrl>", style)?;
+            writeln!(writer, "        {}", data)?;
+            writeln!(writer, "        <MultiGeometry>")?;
+            for n in &group {
+                // KML wants outer rings counterclockwise, the other way around from FIRBoundaries.dat
+                let mut ring = open_ring(&firs[*n].boundary_corners);
+                ring.reverse();
+                writeln!(
+                    writer,
+                    "          <Polygon><outerBoundaryIs><LinearRing><coordinates>{}</coordinates></LinearRing></outerBoundaryIs></Polygon>",
+                    coordinates(&ring)
+                )?;
+            }
+            writeln!(writer, "        </MultiGeometry>")?;
+            writeln!(writer, "      </Placemark>")?;
+            writeln!(writer, "      <Placemark>")?;
+            writeln!(writer, "        <name>{}</name>", icao)?;
+            writeln!(writer, "        <styleUrl>#lable</styleUrl>")?;
+            writeln!(writer, "        {}", data)?;
+            writeln!(
+                writer,
+                "        <Point><coordinates>{},{}</coordinates></Point>",
+                fir.lable.lon, fir.lable.lat
+            )?;
+            writeln!(writer, "      </Placemark>")?;
+        }
+        writeln!(writer, "    </Folder>")?;
+    }
+    writeln!(writer, "  </Document>")?;
+    writeln!(writer, "</kml>")?;
+    Ok(())
+}
+
+pub(crate) fn write_file<P: AsRef<Path>>(firs: &[FIRBoundary], p: P) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(p)?);
+    to_writer(firs, &mut writer)?;
+    writer.flush()
+}
+
+fn kml_error<S: AsRef<str>>(s: S) -> FIRParsingError {
+    FIRParsingError::FIRParsing(format!("KML: {}", s.as_ref()))
+}
+
+fn is(node: &Node, name: &str) -> bool {
+    node.is_element() && node.tag_name().name() == name
+}
+
+fn text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.children()
+        .find(|n| is(n, name))
+        .and_then(|n| n.text())
+        .map(str::trim)
+}
+
+fn points(coordinates: &str) -> FIRResult<Vec<Point>> {
+    coordinates
+        .split_whitespace()
+        .map(|tuple| {
+            let mut values = tuple.split(',');
+            match (values.next(), values.next()) {
+                (Some(lon), Some(lat)) => Point::new(lat.parse()?, lon.parse()?),
+                _ => Err(kml_error(format!("not a coordinate: {}", tuple))),
+            }
+        })
+        .collect()
+}
+
+fn is_oceanic(placemark: Node) -> bool {
+    placemark
+        .descendants()
+        .filter(|n| is(n, "Data") && n.attribute("name") == Some("IsOceanic"))
+        .any(|n| text(n, "value") == Some("true"))
+}
+
+/// Reads placemarks with polygons as FIRs, the first polygon as the FIR and the rest as extentions.
+/// Placemarks with a point and the same name are used as lables, and FIRs without one
+/// get their lable at the pole of inaccessibility of the largest part. Holes are left out.
+pub(crate) fn parse(content: &str) -> FIRResult<Vec<FIRBoundary>> {
+    let doc = Document::parse(content).map_err(|e| kml_error(e.to_string()))?;
+    let mut firs = Vec::new();
+    let mut lables = IndexMap::new();
+    for placemark in doc.descendants().filter(|n| is(n, "Placemark")) {
+        let icao = text(placemark, "name").unwrap_or_default().to_string();
+        let is_oseanic = is_oceanic(placemark);
+        let polygons: Vec<Node> = placemark
+            .descendants()
+            .filter(|n| is(n, "Polygon"))
+            .collect();
+        if polygons.is_empty() {
+            if let Some(point) = placemark.descendants().find(|n| is(n, "Point")) {
+                let point = points(text(point, "coordinates").unwrap_or_default())?;
+                if let Some(p) = point.into_iter().next() {
+                    lables.entry((icao, is_oseanic)).or_insert(p);
+                }
+            }
+            continue;
+        }
+        if icao.is_empty() {
+            return Err(kml_error("placemark with polygons but without a name"));
+        }
+        for (n, polygon) in polygons.into_iter().enumerate() {
+            let ring = polygon
+                .descendants()
+                .find(|n| is(n, "outerBoundaryIs"))
+                .and_then(|n| n.descendants().find(|n| is(n, "coordinates")))
+                .and_then(|n| n.text())
+                .ok_or_else(|| kml_error(format!("{}: polygon without an outer boundary", icao)))?;
+            let mut corners = open_ring(&points(ring)?);
+            corners.dedup();
+            if corners.len() < 3 {
+                return Err(kml_error(format!(
+                    "{}: polygon with less than 3 points",
+                    icao
+                )));
+            }
+            match try_polygon_or_hole(&corners) {
+                None => return Err(kml_error(format!("{}: polygon without area", icao))),
+                Some(Fill::Hole) => corners.reverse(),
+                Some(Fill::Polygon) => {}
+            }
+            let mut fir = FIRBoundary {
+                id: firs.len(),
+                icao: icao.clone(),
+                is_oseanic,
+                is_extension: n != 0,
+                min_lat: Default::default(),
+                min_lon: Default::default(),
+                max_lat: Default::default(),
+                max_lon: Default::default(),
+                lable: corners[0].clone(),
+                boundary_corners: corners,
+            };
+            fir.update_bounds();
+            firs.push(fir);
+        }
+    }
+
+    for group in with_extentions(&firs) {
+        let first = &firs[group[0]];
+        let lable = match lables.get(&(first.icao.clone(), first.is_oseanic)) {
+            Some(p) => p.clone(),
+            None => lable::group_lable(&firs, &group),
+        };
+        for n in group {
+            firs[n].lable = lable.clone();
+        }
+    }
+    Ok(firs)
+}
+
+pub(crate) fn read_file<P: AsRef<Path>>(p: P) -> FIRResult<Vec<FIRBoundary>> {
+    parse(&fs::read_to_string(p)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fir_boundaries::fir;
+
+    #[test]
+    fn round_trip() {
+        let firs = vec![
+            fir("LZZZ", false, &[(0, 0), (2, 0), (2, 2), (0, 2)]),
+            fir("EZZZ", false, &[(0, 2), (2, 2), (2, 4), (0, 4)]),
+            fir("EZZZ", true, &[(2, 2), (4, 2), (4, 4), (2, 4)]),
+        ];
+        let mut kml = Vec::new();
+        to_writer(&firs, &mut kml).unwrap();
+        let read = parse(&String::from_utf8(kml).unwrap()).unwrap();
+        // the E region is written before the L region
+        assert_eq!(read[0].icao, "EZZZ");
+        assert_eq!(read[0].boundary_corners, firs[1].boundary_corners);
+        assert_eq!(read[0].lable, firs[1].lable);
+        assert_eq!(read[2].boundary_corners, firs[0].boundary_corners);
+        assert!(read[1].is_extension);
+        assert_eq!(read[1].boundary_corners, firs[2].boundary_corners);
+    }
+
+    #[test]
+    fn polygon_without_area() {
+        let kml = "<kml><Placemark><name>EZZZ</name><Polygon><outerBoundaryIs><LinearRing>\
+                   <coordinates>0,0 1,1 2,2 0,0</coordinates>\
+                   </LinearRing></outerBoundaryIs></Polygon></Placemark></kml>";
+        let err = parse(kml).unwrap_err().to_string();
+        assert!(err.contains("KML: EZZZ: polygon without area"));
+    }
+}
@@ -0,0 +1,272 @@
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use crate::{
+    fir_boundaries::{with_extentions, FIRBoundary, FIRParsingError, Point},
+    geodesy::{self, Coord},
+};
+
+/// Tells if the lable of the FIR is inside the FIR or one of its extentions.
+fn lable_inside(firs: &[FIRBoundary], group: &[usize]) -> bool {
+    let lable = Coord::from(&firs[group[0]].lable);
+    group
+        .iter()
+        .any(|n| geodesy::contains(&geodesy::ring_coords(&firs[*n].boundary_corners), lable))
+}
+
+/// Finds every FIR where the lable is outside the FIR and all of its extentions.
+pub(crate) fn check_lables(firs: &[FIRBoundary]) -> Vec<FIRParsingError> {
+    with_extentions(firs)
+        .into_iter()
+        .filter(|g| !firs[g[0]].is_extension)
+        .filter(|g| !lable_inside(firs, g))
+        .map(|g| FIRParsingError::LableOutsideFir {
+            owner: firs[g[0]].icao.clone(),
+            lable: firs[g[0]].lable.clone(),
+        })
+        .collect()
+}
+
+/// Moves lables to the pole of inaccessibility of the largest part of the FIR.
+/// If `all` is false only lables outside of their FIR are moved.
+/// Extentions get the same lable as their FIR. Returns the ICAO of every FIR that got a new lable.
+pub(crate) fn fix_lables(firs: &mut [FIRBoundary], all: bool) -> Vec<String> {
+    let mut moved = Vec::new();
+    for group in with_extentions(firs) {
+        if firs[group[0]].is_extension || (!all && lable_inside(firs, &group)) {
+            continue;
+        }
+        let lable = group_lable(firs, &group);
+        for n in &group {
+            firs[*n].lable = lable.clone();
+        }
+        moved.push(firs[group[0]].icao.clone());
+    }
+    moved
+}
+
+/// The pole of inaccessibility of the largest FIR in the group.
+pub(crate) fn group_lable(firs: &[FIRBoundary], group: &[usize]) -> Point {
+    let largest = group
+        .iter()
+        .map(|n| geodesy::ring_coords(&firs[*n].boundary_corners))
+        .max_by(|a, b| geodesy::area(a).partial_cmp(&geodesy::area(b)).unwrap())
+        .unwrap();
+    polylabel(&largest, 0.01).to_point()
+}
+
+/// A square of the search grid in `polylabel`, in a plane where x is scaled longitude and y latitude.
+struct Cell {
+    x: f64,
+    y: f64,
+    half: f64,
+    /// Distance from the center of the cell to the ring, negative outside the ring.
+    distance: f64,
+    /// Largest distance any point in the cell can have to the ring.
+    max: f64,
+}
+
+impl Cell {
+    fn new(x: f64, y: f64, half: f64, ring: &[(f64, f64)]) -> Self {
+        let distance = signed_distance(x, y, ring);
+        Self {
+            x,
+            y,
+            half,
+            distance,
+            max: distance + half * std::f64::consts::SQRT_2,
+        }
+    }
+}
+
+impl PartialEq for Cell {
+    fn eq(&self, other: &Self) -> bool {
+        self.max == other.max
+    }
+}
+
+impl Eq for Cell {}
+
+impl PartialOrd for Cell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Cell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.max.partial_cmp(&other.max).unwrap()
+    }
+}
+
+fn signed_distance(x: f64, y: f64, ring: &[(f64, f64)]) -> f64 {
+    let mut inside = false;
+    let mut min = f64::INFINITY;
+    for (a, b) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+        if (a.1 > y) != (b.1 > y) && x < (b.0 - a.0) * (y - a.1) / (b.1 - a.1) + a.0 {
+            inside = !inside;
+        }
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let len2 = dx * dx + dy * dy;
+        let t = match len2 == 0.0 {
+            true => 0.0,
+            false => (((x - a.0) * dx + (y - a.1) * dy) / len2).clamp(0.0, 1.0),
+        };
+        min = min.min(((x - a.0 - t * dx).powi(2) + (y - a.1 - t * dy).powi(2)).sqrt());
+    }
+    match inside {
+        true => min,
+        false => -min,
+    }
+}
+
+/// Finds the pole of inaccessibility of a ring: the point inside it farthest away from the boundary,
+/// with the search grid algorithm from mapbox/polylabel. `precision` is in degrees of latitude.
+pub(crate) fn polylabel(ring: &[Coord], precision: f64) -> Coord {
+    // scale longitudes so distances are roughly the same in both directions
+    let mid_lat = ring.iter().map(|c| c.lat).sum::<f64>() / ring.len() as f64;
+    let scale = mid_lat.to_radians().cos().max(0.01);
+    let plane: Vec<(f64, f64)> = ring.iter().map(|c| (c.lon * scale, c.lat)).collect();
+    let (min_x, min_y, max_x, max_y) = plane.iter().fold(
+        (
+            f64::INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NEG_INFINITY,
+        ),
+        |(a, b, c, d), p| (a.min(p.0), b.min(p.1), c.max(p.0), d.max(p.1)),
+    );
+    let size = (max_x - min_x).min(max_y - min_y);
+    let to_coord = |c: &Cell| Coord {
+        lat: c.y,
+        lon: geodesy::normalize_lon(c.x / scale),
+    };
+    if size <= 0.0 {
+        return ring[0];
+    }
+
+    let mut queue = BinaryHeap::new();
+    let half = size / 2.0;
+    let mut x = min_x;
+    while x < max_x {
+        let mut y = min_y;
+        while y < max_y {
+            queue.push(Cell::new(x + half, y + half, half, &plane));
+            y += size;
+        }
+        x += size;
+    }
+
+    // start with the centroid, which is a good guess for most FIRs
+    let centroid = geodesy::centroid(ring);
+    let mut best = Cell::new(
+        (centroid.lon + 360.0 * ((ring[0].lon - centroid.lon) / 360.0).round()) * scale,
+        centroid.lat,
+        0.0,
+        &plane,
+    );
+    let bbox_center = Cell::new((min_x + max_x) / 2.0, (min_y + max_y) / 2.0, 0.0, &plane);
+    if bbox_center.distance > best.distance {
+        best = bbox_center;
+    }
+
+    while let Some(cell) = queue.pop() {
+        if cell.distance > best.distance {
+            best = Cell::new(cell.x, cell.y, 0.0, &plane);
+        }
+        if cell.max - best.distance <= precision {
+            continue;
+        }
+        let half = cell.half / 2.0;
+        for (dx, dy) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+            queue.push(Cell::new(
+                cell.x + dx * half,
+                cell.y + dy * half,
+                half,
+                &plane,
+            ));
+        }
+    }
+    to_coord(&best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fir_boundaries::fir;
+
+    /// ZZZZ has its lable inside, XXXX only inside its extention and YYYY outside.
+    fn firs() -> Vec<FIRBoundary> {
+        let mut firs = vec![
+            fir("ZZZZ", false, &[(0, 0), (2, 0), (2, 2), (0, 2)]),
+            fir("XXXX", false, &[(20, 0), (24, 0), (24, 4), (20, 4)]),
+            fir("XXXX", true, &[(0, 2), (2, 2), (2, 4), (0, 4)]),
+            fir("YYYY", false, &[(10, 10), (12, 10), (12, 12), (10, 12)]),
+        ];
+        firs[1].lable = Point::new(1.into(), 3.into()).unwrap();
+        firs[2].lable = firs[1].lable.clone();
+        firs
+    }
+
+    fn inside(fir: &FIRBoundary) -> bool {
+        geodesy::contains(
+            &geodesy::ring_coords(&fir.boundary_corners),
+            Coord::from(&fir.lable),
+        )
+    }
+
+    #[test]
+    fn lable_in_concave_ring() {
+        // an L shape, where the centroid is outside the ring
+        let ring: Vec<Coord> = [
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 10.0),
+            (0.0, 10.0),
+        ]
+        .iter()
+        .map(|(lon, lat)| Coord {
+            lat: *lat,
+            lon: *lon,
+        })
+        .collect();
+        assert!(!geodesy::contains(&ring, geodesy::centroid(&ring)));
+        let lable = polylabel(&ring, 0.001);
+        assert!(geodesy::contains(&ring, lable), "{:?}", lable);
+    }
+
+    #[test]
+    fn lable_outside_fir() {
+        let firs = firs();
+        let errors = check_lables(&firs);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            FIRParsingError::LableOutsideFir { owner, .. } if owner == "YYYY"
+        ));
+    }
+
+    #[test]
+    fn fix_only_lables_outside() {
+        let mut firs = firs();
+        assert_eq!(fix_lables(&mut firs, false), vec!["YYYY"]);
+        assert!(inside(&firs[3]));
+        assert!(check_lables(&firs).is_empty());
+        assert_eq!(firs[0].lable, Point::new(1.into(), 1.into()).unwrap());
+        assert_eq!(firs[1].lable, Point::new(1.into(), 3.into()).unwrap());
+        assert_eq!(firs[2].lable, firs[1].lable);
+    }
+
+    #[test]
+    fn relable_all() {
+        let mut firs = firs();
+        assert_eq!(fix_lables(&mut firs, true), vec!["ZZZZ", "XXXX", "YYYY"]);
+        assert!(inside(&firs[0]));
+        assert!(inside(&firs[1]));
+        assert!(inside(&firs[3]));
+        // the lable goes to the largest part, and the extention gets the same lable
+        assert_eq!(firs[2].lable, firs[1].lable);
+        assert!(!inside(&firs[2]));
+    }
+}
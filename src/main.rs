@@ -1,20 +1,84 @@
-use std::{error::Error, fs::File};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
 
 use color_eyre::eyre::{self, eyre};
+use error_collector::ErrorCollector;
+use fir_boundaries::{FIRBoundary, FIRParsingError};
 use geo_json::GeoJson;
 
 use clap::Clap;
 use either::Either::{Left, Right};
 
+pub(crate) mod airports;
+pub(crate) mod aixm;
+pub(crate) mod csv;
+pub(crate) mod densify;
+pub(crate) mod dms;
 pub(crate) mod error_collector;
+pub(crate) mod ese;
+pub(crate) mod euroscope;
 pub(crate) mod fir_boundaries;
+pub(crate) mod flat_geobuf;
 pub(crate) mod geo_json;
+pub(crate) mod geodesy;
+pub(crate) mod idl;
+pub(crate) mod kml;
+pub(crate) mod lable;
+pub(crate) mod mbtiles;
+pub(crate) mod metadata;
+pub(crate) mod openair;
+pub(crate) mod render;
+pub(crate) mod report;
+pub(crate) mod shapefile;
+pub(crate) mod simplify;
+pub(crate) mod snap;
+pub(crate) mod split;
+pub(crate) mod statistics;
+pub(crate) mod tiles;
+pub(crate) mod topo_json;
+pub(crate) mod topology;
+pub(crate) mod tracon;
+pub(crate) mod uir;
+pub(crate) mod vatspy_dat;
+pub(crate) mod watch;
+pub(crate) mod wkt;
 
 mod cli;
 
 enum Filetype {
     Dat,
     GeoJson,
+    OpenAir,
+    Kml,
+    Shapefile,
+    Wkt,
+    Wkb,
+    TopoJson,
+    FlatGeobuf,
+}
+
+impl Filetype {
+    fn from_path(p: &Path) -> eyre::Result<Self> {
+        match p.extension().map(|os| os.to_str().unwrap()) {
+            Some("json") | Some("geojson") => Ok(Filetype::GeoJson),
+            Some("dat") => Ok(Filetype::Dat),
+            Some("openair") => Ok(Filetype::OpenAir),
+            Some("kml") => Ok(Filetype::Kml),
+            Some("shp") => Ok(Filetype::Shapefile),
+            Some("wkt") => Ok(Filetype::Wkt),
+            Some("wkb") => Ok(Filetype::Wkb),
+            Some("topojson") => Ok(Filetype::TopoJson),
+            Some("fgb") => Ok(Filetype::FlatGeobuf),
+            Some(e) => Err(eyre!(
+                "Unrecognized file extention: .{}. run --help for more info",
+                e
+            )),
+            None => Err(eyre!("No file extention found. run --help for more info")),
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -23,50 +87,170 @@ pub enum Mode {
     Fix,
 }
 
+/// Reads a file of any supported type into FIRBoundaries.
+fn read_dataset(p: &Path, mode: Mode) -> eyre::Result<Vec<FIRBoundary>> {
+    Ok(match Filetype::from_path(p)? {
+        Filetype::GeoJson => {
+            fir_boundaries::convert_from_geojson(serde_json::from_reader(File::open(p)?)?)
+        }
+        Filetype::Dat => fir_boundaries::read_file(p, mode)??,
+        Filetype::OpenAir => {
+            fir_boundaries::validate(openair::read_file(p, &[], openair::ARC_STEP)?, mode)??
+        }
+        Filetype::Kml => fir_boundaries::validate(kml::read_file(p)?, mode)??,
+        Filetype::Shapefile => fir_boundaries::validate(shapefile::read_file(p)?, mode)??,
+        Filetype::Wkt | Filetype::Wkb => fir_boundaries::validate(wkt::read_file(p)?, mode)??,
+        Filetype::TopoJson => fir_boundaries::validate(topo_json::read_file(p)?, mode)??,
+        Filetype::FlatGeobuf => return Err(eyre!("FlatGeobuf files can only be written")),
+    })
+}
+
+/// Reads a file like `read_dataset` in Fix mode, together with what Strict mode finds wrong in it.
+fn read_with_findings(p: &Path) -> eyre::Result<(Vec<FIRBoundary>, Vec<FIRParsingError>)> {
+    let findings = match read_dataset(p, Mode::Strict) {
+        Ok(firs) => return Ok((firs, Vec::new())),
+        Err(e) => match e.downcast::<ErrorCollector>() {
+            Ok(errors) => errors.into_errors(),
+            Err(e) => vec![e.downcast::<FIRParsingError>()?],
+        },
+    };
+    Ok((read_dataset(p, Mode::Fix)?, findings))
+}
+
+/// Writes FIRBoundaries to a file, the type is decided by the file extention.
+fn write_dataset(firs: &[FIRBoundary], p: &Path) -> eyre::Result<()> {
+    match Filetype::from_path(p)? {
+        Filetype::GeoJson => serde_json::to_writer_pretty(File::create(p)?, &GeoJson::from(firs))?,
+        Filetype::Dat => fir_boundaries::write_to_file(firs, p)?,
+        Filetype::OpenAir => openair::write_file(firs, p)?,
+        Filetype::Kml => kml::write_file(firs, p)?,
+        Filetype::Shapefile => shapefile::write_file(firs, p)?,
+        Filetype::Wkt => wkt::write_file(firs, p, wkt::Geometry::Wkt, false)?,
+        Filetype::Wkb => wkt::write_file(firs, p, wkt::Geometry::Wkb, false)?,
+        Filetype::TopoJson => topo_json::write_file(firs, p, topo_json::DECIMALS)?,
+        Filetype::FlatGeobuf => flat_geobuf::write_file(firs, p)?,
+    }
+    Ok(())
+}
+
 fn main() -> eyre::Result<()> {
     color_eyre::install()?;
     let opts = cli::Opts::parse();
-    let mode = match &opts.output {
+    match opts.command {
+        Some(cli::Command::Info(info)) => info_command(info),
+        Some(cli::Command::Simplify(s)) => simplify_command(s),
+        Some(cli::Command::Densify(d)) => densify_command(d),
+        Some(cli::Command::Snap(s)) => snap_command(s),
+        Some(cli::Command::Uir(u)) => uir_command(u),
+        Some(cli::Command::Split(s)) => split_command(s),
+        Some(cli::Command::Join(j)) => join_command(j),
+        Some(cli::Command::Aixm(a)) => aixm_command(a),
+        Some(cli::Command::Euroscope(e)) => euroscope_command(e),
+        Some(cli::Command::Ese(e)) => ese_command(e),
+        Some(cli::Command::Tiles(t)) => tiles_command(t),
+        Some(cli::Command::Render(r)) => render_command(r),
+        Some(cli::Command::Metadata(m)) => metadata_command(m),
+        Some(cli::Command::Tracon(t)) => tracon_command(t),
+        Some(cli::Command::Airports(a)) => airports_command(a),
+        Some(cli::Command::Idl(i)) => idl_command(i),
+        None if opts.watch => watch_command(opts),
+        None => convert(&opts),
+    }
+}
+
+fn convert(opts: &cli::Opts) -> eyre::Result<()> {
+    let input = match &opts.input {
+        Some(input) => input,
+        None => return Err(eyre!("No input file given. run --help for more info")),
+    };
+    if let Some(report) = &opts.html_report {
+        html_report(input, report)?;
+    }
+    let output = &opts.output;
+    let write_dat = match opts.dms {
+        true => fir_boundaries::write_dms_file::<&Path>,
+        false => fir_boundaries::write_to_file::<&Path>,
+    };
+    let mode = match &output {
         Some(_) => Mode::Fix,
         None => Mode::Strict,
     };
 
-    let data = match opts.input.extension().map(|os| os.to_str().unwrap()) {
-        Some("json") | Some("geojson") => Left(serde_json::from_reader::<_, GeoJson>(File::open(
-            opts.input,
-        )?)?),
-        Some("dat") => Right(fir_boundaries::read_file(opts.input, mode)??),
-        Some(e) => {
+    let mut data = match Filetype::from_path(input)? {
+        Filetype::GeoJson => Left(serde_json::from_reader::<_, GeoJson>(File::open(input)?)?),
+        Filetype::Dat => Right(fir_boundaries::read_file(input, mode)??),
+        Filetype::OpenAir => Right(fir_boundaries::validate(
+            openair::read_file(input, &opts.openair_class, openair::ARC_STEP)?,
+            mode,
+        )??),
+        Filetype::Kml
+        | Filetype::Shapefile
+        | Filetype::Wkt
+        | Filetype::Wkb
+        | Filetype::TopoJson
+        | Filetype::FlatGeobuf => Right(read_dataset(input, mode)?),
+    };
+
+    if opts.fix_lables || opts.relable_all {
+        if mode == Mode::Strict {
             return Err(eyre!(
-                "Unrecognized file extention: .{}. run --help for more info",
-                e
-            ))
+                "Lables can only be fixed when an output file is given"
+            ));
         }
-        None => return Err(eyre!("No file extention found. run --help for more info")),
-    };
+        let mut firs = data.either(fir_boundaries::convert_from_geojson, |d| d);
+        for icao in lable::fix_lables(&mut firs, opts.relable_all) {
+            println!("{}: moved lable", icao);
+        }
+        data = Right(firs);
+    }
 
-    if let Some(f) = opts.output {
-        let ft = match f.extension().map(|os| os.to_str().unwrap()) {
-            Some("json") | Some("geojson") => Filetype::GeoJson,
-            Some("dat") => Filetype::Dat,
-            Some(e) => {
-                return Err(eyre!(
-                    "Unrecognized file extention: .{}. run --help for more info",
-                    e
-                ))
-            }
-            None => return Err(eyre!("No file extention found. run --help for more info")),
-        };
+    if let Some(f) = output {
+        let ft = Filetype::from_path(f)?;
         match data {
             Left(geojson_data) => match ft {
                 Filetype::GeoJson => serde_json::to_writer_pretty(File::create(f)?, &geojson_data)?,
                 Filetype::Dat => {
                     let fir_data = fir_boundaries::convert_from_geojson(geojson_data);
-                    fir_boundaries::write_to_file(&fir_data, f)?;
+                    write_dat(&fir_data, f)?;
+                }
+                Filetype::OpenAir => {
+                    let fir_data = fir_boundaries::convert_from_geojson(geojson_data);
+                    openair::write_file(&fir_data, f)?;
+                }
+                Filetype::Kml => {
+                    let fir_data = fir_boundaries::convert_from_geojson(geojson_data);
+                    kml::write_file(&fir_data, f)?;
+                }
+                Filetype::Shapefile => {
+                    let fir_data = fir_boundaries::convert_from_geojson(geojson_data);
+                    shapefile::write_file(&fir_data, f)?;
+                }
+                Filetype::Wkt => {
+                    let fir_data = fir_boundaries::convert_from_geojson(geojson_data);
+                    wkt::write_file(&fir_data, f, wkt::Geometry::Wkt, opts.csv)?;
+                }
+                Filetype::Wkb => {
+                    let fir_data = fir_boundaries::convert_from_geojson(geojson_data);
+                    wkt::write_file(&fir_data, f, wkt::Geometry::Wkb, opts.csv)?;
+                }
+                Filetype::TopoJson => {
+                    let fir_data = fir_boundaries::convert_from_geojson(geojson_data);
+                    topo_json::write_file(&fir_data, f, opts.topojson_decimals)?;
+                }
+                Filetype::FlatGeobuf => {
+                    let fir_data = fir_boundaries::convert_from_geojson(geojson_data);
+                    flat_geobuf::write_file(&fir_data, f)?;
                 }
             },
             Right(fir_data) => match ft {
-                Filetype::Dat => fir_boundaries::write_to_file(&fir_data, f)?,
+                Filetype::Dat => write_dat(&fir_data, f)?,
+                Filetype::OpenAir => openair::write_file(&fir_data, f)?,
+                Filetype::Kml => kml::write_file(&fir_data, f)?,
+                Filetype::Shapefile => shapefile::write_file(&fir_data, f)?,
+                Filetype::Wkt => wkt::write_file(&fir_data, f, wkt::Geometry::Wkt, opts.csv)?,
+                Filetype::Wkb => wkt::write_file(&fir_data, f, wkt::Geometry::Wkb, opts.csv)?,
+                Filetype::TopoJson => topo_json::write_file(&fir_data, f, opts.topojson_decimals)?,
+                Filetype::FlatGeobuf => flat_geobuf::write_file(&fir_data, f)?,
                 Filetype::GeoJson => {
                     let gj: GeoJson = fir_data.into();
                     serde_json::to_writer_pretty(File::create(f)?, &gj)?;
@@ -76,3 +260,376 @@ fn main() -> eyre::Result<()> {
     }
     Ok(())
 }
+
+/// Writes what Strict mode finds wrong in a file to an HTML report, before it is validated or converted.
+fn html_report(input: &Path, report: &Path) -> eyre::Result<()> {
+    let (firs, errors) = read_with_findings(input)?;
+    let mut findings: Vec<render::Finding> = errors.iter().map(render::Finding::from).collect();
+    findings.extend(render::self_intersections(&firs));
+    let title = input
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    report::write_file(report, &title, &firs, &findings)?;
+    println!(
+        "Report with {} findings written to {}",
+        findings.len(),
+        report.display()
+    );
+    Ok(())
+}
+
+/// Validates the input on every save, and converts it again when an output file is given.
+/// Errors while converting are printed, so a half edited file does not stop the watching.
+fn watch_command(opts: cli::Opts) -> eyre::Result<()> {
+    let input = match &opts.input {
+        Some(input) => input,
+        None => return Err(eyre!("No input file given. run --help for more info")),
+    };
+    println!("Watching {}, stop with Ctrl+C", input.display());
+    watch::watch(input, || {
+        let findings = match read_with_findings(input) {
+            Ok((_, errors)) => errors.iter().map(|e| e.to_string()).collect(),
+            Err(e) => std::iter::once(e.to_string()).collect(),
+        };
+        let written = match &opts.output {
+            Some(_) => convert(&opts),
+            None => match &opts.html_report {
+                Some(report) => html_report(input, report),
+                None => Ok(()),
+            },
+        };
+        if let Err(e) = written {
+            println!("Not written: {}", e);
+        }
+        findings
+    })
+}
+
+fn info_command(opts: cli::InfoOpts) -> eyre::Result<()> {
+    let firs = read_dataset(&opts.input, Mode::Fix)?;
+    let mut stats = statistics::filter(statistics::fir_stats(&firs), &opts.filter);
+    statistics::sort(&mut stats, opts.sort, opts.reverse);
+    match opts.output {
+        Some(p) => {
+            statistics::write_report(&mut BufWriter::new(File::create(p)?), &stats, opts.format)?
+        }
+        None => statistics::write_report(&mut io::stdout().lock(), &stats, opts.format)?,
+    }
+    Ok(())
+}
+
+fn simplify_command(opts: cli::SimplifyOpts) -> eyre::Result<()> {
+    let mut firs = read_dataset(&opts.input, Mode::Fix)?;
+    let report = simplify::simplify(&mut firs, opts.tolerance, opts.algorithm);
+    report.iter().for_each(|r| println!("{}", r));
+    let before: usize = report.iter().map(|r| r.vertices_before).sum();
+    let after: usize = report.iter().map(|r| r.vertices_after).sum();
+    println!("Total: {} -> {} points", before, after);
+    write_dataset(&firs, &opts.output)
+}
+
+fn densify_command(opts: cli::DensifyOpts) -> eyre::Result<()> {
+    if opts.max_length.is_nan() || opts.max_length <= 0.0 {
+        return Err(eyre!(
+            "--max-length must be above 0, found: {}",
+            opts.max_length
+        ));
+    }
+    let mut firs = read_dataset(&opts.input, Mode::Fix)?;
+    for (icao, added) in densify::densify(&mut firs, opts.max_length, opts.line, &opts.icao) {
+        println!("{}: added {} points", icao, added);
+    }
+    write_dataset(&firs, &opts.output)
+}
+
+fn snap_command(opts: cli::SnapOpts) -> eyre::Result<()> {
+    if opts.tolerance.is_nan() || opts.tolerance <= 0.0 {
+        return Err(eyre!(
+            "--tolerance must be above 0, found: {}",
+            opts.tolerance
+        ));
+    }
+    let mut firs = read_dataset(&opts.input, Mode::Fix)?;
+    let report = snap::snap(&mut firs, opts.tolerance);
+    report.iter().for_each(|s| println!("{}", s));
+    println!("{} points snapped", report.len());
+    write_dataset(&firs, &opts.output)
+}
+
+fn uir_command(opts: cli::UirOpts) -> eyre::Result<()> {
+    let dat = vatspy_dat::read_file(&opts.vatspy)?;
+    let firs = read_dataset(&opts.boundaries, Mode::Fix)?;
+    let (uirs, diagnostics) = uir::build_uirs(&dat, &firs, &opts.uir);
+    diagnostics.iter().for_each(|d| println!("{}", d));
+    println!("{} UIR outlines built", uirs.features.len());
+    serde_json::to_writer_pretty(File::create(opts.output)?, &uirs)?;
+    Ok(())
+}
+
+fn split_command(opts: cli::SplitOpts) -> eyre::Result<()> {
+    let firs = read_dataset(&opts.input, Mode::Fix)?;
+    let manifest = split::split(&firs, &opts.dir, &opts.format)?;
+    println!(
+        "{} FIRs written to {} files in {}",
+        firs.len(),
+        manifest.files.len(),
+        opts.dir.display()
+    );
+    Ok(())
+}
+
+fn join_command(opts: cli::JoinOpts) -> eyre::Result<()> {
+    let mode = match opts.fix {
+        true => Mode::Fix,
+        false => Mode::Strict,
+    };
+    let firs = split::join(&opts.dir, mode)?;
+    println!("{} FIRs joined", firs.len());
+    write_dataset(&firs, &opts.output)
+}
+
+fn aixm_command(opts: cli::AixmOpts) -> eyre::Result<()> {
+    if opts.arc_step.is_nan() || opts.arc_step <= 0.0 {
+        return Err(eyre!(
+            "--arc-step must be above 0, found: {}",
+            opts.arc_step
+        ));
+    }
+    let mode = match &opts.output {
+        Some(_) => Mode::Fix,
+        None => Mode::Strict,
+    };
+    let (firs, skipped) = aixm::read_file(&opts.input, &opts.types, opts.arc_step)?;
+    skipped.iter().for_each(|s| println!("{}", s));
+    let firs = fir_boundaries::validate(firs, mode)??;
+    println!("{} airspaces imported", firs.len());
+    match opts.output {
+        Some(p) => write_dataset(&firs, &p),
+        None => Ok(()),
+    }
+}
+
+fn euroscope_command(opts: cli::EuroscopeOpts) -> eyre::Result<()> {
+    if opts.sct.is_none() && opts.ese.is_none() {
+        return Err(eyre!(
+            "Give --sct and/or --ese to write. run --help for more info"
+        ));
+    }
+    if opts.split_level > opts.ceiling {
+        return Err(eyre!(
+            "--split-level must not be above --ceiling, found: {} above {}",
+            opts.split_level,
+            opts.ceiling
+        ));
+    }
+    let firs = read_dataset(&opts.input, Mode::Fix)?;
+    let options = euroscope::Options {
+        icao: opts.icao,
+        continental: opts.continental,
+        oceanic: opts.oceanic,
+        extentions: opts.extentions,
+        split_level: opts.split_level,
+        ceiling: opts.ceiling,
+    };
+    if let Some(p) = opts.sct {
+        let mut writer = BufWriter::new(File::create(p)?);
+        euroscope::write_sct(&firs, &options, &mut writer)?;
+        writer.flush()?;
+    }
+    if let Some(p) = opts.ese {
+        let mut writer = BufWriter::new(File::create(p)?);
+        euroscope::write_ese(&firs, &options, &mut writer)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+fn ese_command(opts: cli::EseOpts) -> eyre::Result<()> {
+    let ese = ese::read_file(&opts.input)?;
+    if opts.fir.is_empty() {
+        ese.sectors.keys().for_each(|s| println!("{}", s));
+        return Ok(());
+    }
+    let mut firs = Vec::new();
+    for fir in &opts.fir {
+        match fir.split_once('=') {
+            Some((icao, sectors)) if !icao.is_empty() => firs.push((
+                icao.to_string(),
+                sectors.split(',').map(|s| s.trim().to_string()).collect(),
+            )),
+            _ => {
+                return Err(eyre!(
+                    "Not a FIR with its sectors: {}, use ICAO=SECTOR,SECTOR",
+                    fir
+                ))
+            }
+        }
+    }
+    let mode = match &opts.output {
+        Some(_) => Mode::Fix,
+        None => Mode::Strict,
+    };
+    let (firs, diagnostics) = ese::build_firs(&ese, &firs)?;
+    diagnostics.iter().for_each(|d| println!("{}", d));
+    let firs = fir_boundaries::validate(firs, mode)??;
+    println!("{} FIRs imported", firs.len());
+    match opts.output {
+        Some(p) => write_dataset(&firs, &p),
+        None => Ok(()),
+    }
+}
+
+fn tiles_command(opts: cli::TilesOpts) -> eyre::Result<()> {
+    if opts.min_zoom > opts.max_zoom || opts.max_zoom > tiles::MAX_ZOOM_LIMIT {
+        return Err(eyre!(
+            "Zoom levels must be between 0 and {}, with --min-zoom not above --max-zoom",
+            tiles::MAX_ZOOM_LIMIT
+        ));
+    }
+    let firs = read_dataset(&opts.input, Mode::Fix)?;
+    let count = tiles::write_tiles(&firs, &opts.output, opts.min_zoom..=opts.max_zoom)?;
+    println!("{} tiles written to {}", count, opts.output.display());
+    Ok(())
+}
+
+fn render_command(opts: cli::RenderOpts) -> eyre::Result<()> {
+    match opts.output.extension().and_then(|e| e.to_str()) {
+        Some("svg") | Some("png") => (),
+        _ => return Err(eyre!("The image has to end with .svg or .png")),
+    }
+    if !(render::MIN_WIDTH..=render::MAX_WIDTH).contains(&opts.width) {
+        return Err(eyre!(
+            "--width must be between {} and {} pixels, found: {}",
+            render::MIN_WIDTH,
+            render::MAX_WIDTH,
+            opts.width
+        ));
+    }
+    let (firs, errors) = read_with_findings(&opts.input)?;
+    let firs: Vec<FIRBoundary> = firs
+        .into_iter()
+        .filter(|fir| {
+            opts.icao.is_empty() || opts.icao.iter().any(|i| fir.icao.starts_with(i.as_str()))
+        })
+        .collect();
+    if firs.is_empty() {
+        return Err(eyre!("No FIRs to draw"));
+    }
+    let mut findings: Vec<render::Finding> = errors
+        .iter()
+        .map(render::Finding::from)
+        .filter(|f| {
+            f.owners.is_empty()
+                || f.owners
+                    .iter()
+                    .any(|o| firs.iter().any(|fir| &fir.icao == o))
+        })
+        .collect();
+    findings.extend(render::self_intersections(&firs));
+    findings.iter().for_each(|f| println!("{}", f));
+    render::write_file(&firs, &findings, &opts.output, opts.projection, opts.width)?;
+    println!(
+        "{} FIRs and {} findings drawn to {}",
+        firs.len(),
+        findings.len(),
+        opts.output.display()
+    );
+    Ok(())
+}
+
+fn metadata_command(opts: cli::MetadataOpts) -> eyre::Result<()> {
+    match opts.output.extension().and_then(|e| e.to_str()) {
+        Some("csv") | Some("json") => (),
+        _ => return Err(eyre!("The table has to end with .csv or .json")),
+    }
+    let firs = read_dataset(&opts.input, Mode::Fix)?;
+    let dat = match &opts.vatspy {
+        Some(p) => Some(vatspy_dat::read_file(p)?),
+        None => None,
+    };
+    let rows = metadata::rows(&firs, dat.as_ref());
+    metadata::write_file(&opts.output, &rows)?;
+    println!("{} rows written to {}", rows.len(), opts.output.display());
+    Ok(())
+}
+
+fn tracon_command(opts: cli::TraconOpts) -> eyre::Result<()> {
+    let tracons = tracon::read_file(&opts.input)?;
+    let firs = match (&opts.boundaries, &opts.vatspy) {
+        (Some(b), Some(v)) => Some((read_dataset(b, Mode::Fix)?, vatspy_dat::read_file(v)?)),
+        _ => None,
+    };
+    let diagnostics = tracon::validate(
+        &tracons,
+        firs.as_ref().map(|(firs, dat)| (firs.as_slice(), dat)),
+    );
+    diagnostics.iter().for_each(|d| println!("{}", d));
+    match diagnostics.len() {
+        0 => {
+            println!("{} TRACONs are valid", tracons.len());
+            Ok(())
+        }
+        n => Err(eyre!("{} problems found in {} TRACONs", n, tracons.len())),
+    }
+}
+
+fn airports_command(opts: cli::AirportsOpts) -> eyre::Result<()> {
+    let content = std::fs::read_to_string(&opts.vatspy)?;
+    let dat = vatspy_dat::VatSpyDat::parse(&content)?;
+    let firs = read_dataset(&opts.boundaries, Mode::Fix)?;
+    let diagnostics = airports::check(&dat, &firs);
+    diagnostics.iter().for_each(|d| println!("{}", d));
+    match opts.output {
+        Some(p) => {
+            let fixes = airports::fixes(&diagnostics);
+            std::fs::write(&p, vatspy_dat::set_airport_firs(&content, &fixes))?;
+            println!(
+                "{} of {} airports got a new FIR, written to {}",
+                fixes.len(),
+                dat.airports.len(),
+                p.display()
+            );
+            Ok(())
+        }
+        None => match diagnostics.len() {
+            0 => {
+                println!("All {} airports are inside their FIR", dat.airports.len());
+                Ok(())
+            }
+            n => Err(eyre!(
+                "{} of {} airports do not match their FIR",
+                n,
+                dat.airports.len()
+            )),
+        },
+    }
+}
+
+fn idl_command(opts: cli::IdlOpts) -> eyre::Result<()> {
+    if let Some(p) = &opts.output {
+        if !matches!(Filetype::from_path(p)?, Filetype::GeoJson) {
+            return Err(eyre!("The line can only be written to .geojson or .json"));
+        }
+    }
+    let dat = vatspy_dat::read_file(&opts.vatspy)?;
+    let diagnostics = idl::check(&dat.idl);
+    diagnostics.iter().for_each(|d| println!("{}", d));
+    if !diagnostics.is_empty() {
+        return Err(eyre!(
+            "{} problems found in the [IDL] section",
+            diagnostics.len()
+        ));
+    }
+    let points = idl::polyline(&dat.idl);
+    println!("The IDL runs pole to pole through {} points", points.len());
+    if let Some(p) = opts.output {
+        let firs = match &opts.boundaries {
+            Some(b) => read_dataset(b, Mode::Fix)?,
+            None => Vec::new(),
+        };
+        idl::write_file(&p, &firs, &points)?;
+        println!("{} FIRs and the IDL written to {}", firs.len(), p.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,459 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+// An MBTiles file is an SQLite database with a metadata table and a tiles table. It is written
+// once from start to end, so the database is laid out by hand instead of through SQLite:
+// page 1      file header and the schema (sqlite_master)
+// pages 2..   the tiles table: tiles as they are added, spilling into overflow pages, and the
+//             pages above them once all tiles are written
+// then        the metadata table and the index on (zoom_level, tile_column, tile_row)
+// See https://www.sqlite.org/fileformat2.html
+
+const PAGE_SIZE: usize = 4096;
+/// Largest payload kept on a table leaf page, see "Cell Payload Overflow Pages".
+const MAX_LOCAL: usize = PAGE_SIZE - 35;
+const MIN_LOCAL: usize = (PAGE_SIZE - 12) * 32 / 255 - 23;
+
+const TABLE_LEAF: u8 = 0x0D;
+const TABLE_INTERIOR: u8 = 0x05;
+const INDEX_LEAF: u8 = 0x0A;
+const INDEX_INTERIOR: u8 = 0x02;
+
+const METADATA_SQL: &str = "CREATE TABLE metadata (name text, value text)";
+const TILES_SQL: &str =
+    "CREATE TABLE tiles (zoom_level integer, tile_column integer, tile_row integer, tile_data blob)";
+const INDEX_SQL: &str =
+    "CREATE UNIQUE INDEX tile_index on tiles (zoom_level, tile_column, tile_row)";
+
+/// A column value in a record.
+enum Column<'a> {
+    Int(i64),
+    Text(&'a str),
+    Blob(&'a [u8]),
+}
+
+fn varint(mut v: u64, out: &mut Vec<u8>) {
+    if v >> 56 != 0 {
+        // nine bytes, the last one with all 8 bits
+        let mut bytes = [0u8; 9];
+        bytes[8] = v as u8;
+        v >>= 8;
+        for b in bytes[..8].iter_mut().rev() {
+            *b = (v & 0x7F) as u8 | 0x80;
+            v >>= 7;
+        }
+        out.extend(bytes);
+        return;
+    }
+    let mut bytes = vec![(v & 0x7F) as u8];
+    v >>= 7;
+    while v != 0 {
+        bytes.push((v & 0x7F) as u8 | 0x80);
+        v >>= 7;
+    }
+    out.extend(bytes.iter().rev());
+}
+
+/// Serial type and big endian bytes of an integer, as short as possible.
+fn int(v: i64) -> (u64, Vec<u8>) {
+    match v {
+        0 => (8, vec![]),
+        1 => (9, vec![]),
+        _ => {
+            let (serial, len) = match v {
+                -0x80..=0x7F => (1, 1),
+                -0x8000..=0x7FFF => (2, 2),
+                -0x80_0000..=0x7F_FFFF => (3, 3),
+                -0x8000_0000..=0x7FFF_FFFF => (4, 4),
+                -0x8000_0000_0000..=0x7FFF_FFFF_FFFF => (5, 6),
+                _ => (6, 8),
+            };
+            (serial, v.to_be_bytes()[8 - len..].to_vec())
+        }
+    }
+}
+
+fn record(columns: &[Column]) -> Vec<u8> {
+    let mut header = Vec::new();
+    let mut body = Vec::new();
+    for column in columns {
+        match column {
+            Column::Int(v) => {
+                let (serial, bytes) = int(*v);
+                varint(serial, &mut header);
+                body.extend(bytes);
+            }
+            Column::Text(s) => {
+                varint(s.len() as u64 * 2 + 13, &mut header);
+                body.extend(s.as_bytes());
+            }
+            Column::Blob(b) => {
+                varint(b.len() as u64 * 2 + 12, &mut header);
+                body.extend(*b);
+            }
+        }
+    }
+    // the header length counts itself, which is one byte for any header written here
+    let mut out = Vec::with_capacity(1 + header.len() + body.len());
+    varint(header.len() as u64 + 1, &mut out);
+    out.extend(header);
+    out.extend(body);
+    out
+}
+
+/// Lays out one b-tree page from its cells. `first` is where the page header starts, 100 on page 1.
+fn page(typ: u8, cells: &[Vec<u8>], right: Option<u32>, first: usize) -> Vec<u8> {
+    let mut page = vec![0u8; PAGE_SIZE];
+    let header_len = match right {
+        Some(_) => 12,
+        None => 8,
+    };
+    let mut content = PAGE_SIZE;
+    for (n, cell) in cells.iter().enumerate() {
+        content -= cell.len();
+        page[content..content + cell.len()].copy_from_slice(cell);
+        let at = first + header_len + 2 * n;
+        page[at..at + 2].copy_from_slice(&(content as u16).to_be_bytes());
+    }
+    page[first] = typ;
+    page[first + 3..first + 5].copy_from_slice(&(cells.len() as u16).to_be_bytes());
+    page[first + 5..first + 7].copy_from_slice(&(content as u16).to_be_bytes());
+    if let Some(right) = right {
+        page[first + 8..first + 12].copy_from_slice(&right.to_be_bytes());
+    }
+    page
+}
+
+/// Splits `len` children into as few groups of at most `cap` as possible, all about the same size,
+/// so no interior page is left with only a right-most pointer.
+fn groups(len: usize, cap: usize) -> Vec<std::ops::Range<usize>> {
+    let count = len.div_ceil(cap);
+    (0..count)
+        .map(|n| n * len / count..(n + 1) * len / count)
+        .collect()
+}
+
+/// Room for cells on a page, each taking its own size and 2 bytes for its pointer.
+fn fits(cells: &[Vec<u8>], cell: &[u8], header_len: usize) -> bool {
+    let used: usize = cells.iter().map(|c| c.len() + 2).sum();
+    header_len + used + cell.len() + 2 <= PAGE_SIZE
+}
+
+/// Writes an MBTiles file page by page. Tiles are added with `add` and the file is done with `finish`.
+pub(crate) struct MbTiles {
+    writer: BufWriter<File>,
+    pages: u32,
+    /// Cells of the table leaf page being filled, and the rowid of the last one.
+    cells: Vec<Vec<u8>>,
+    rowid: i64,
+    /// Table leaf pages written, with the last rowid on them.
+    leaves: Vec<(u32, i64)>,
+    /// (zoom_level, tile_column, tile_row, rowid) for the index.
+    keys: Vec<[i64; 4]>,
+}
+
+impl MbTiles {
+    pub(crate) fn create<P: AsRef<Path>>(p: P) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(p)?);
+        // page 1 is written last, when the root pages are known
+        writer.write_all(&[0; PAGE_SIZE])?;
+        Ok(Self {
+            writer,
+            pages: 1,
+            cells: Vec::new(),
+            rowid: 0,
+            leaves: Vec::new(),
+            keys: Vec::new(),
+        })
+    }
+
+    fn write_page(&mut self, page: &[u8]) -> io::Result<u32> {
+        self.writer.write_all(page)?;
+        self.pages += 1;
+        Ok(self.pages)
+    }
+
+    /// Adds a tile, with `y` counted from the top like in XYZ tile URLs.
+    pub(crate) fn add(&mut self, z: u8, x: u32, y: u32, data: &[u8]) -> io::Result<()> {
+        let row = (1i64 << z) - 1 - y as i64;
+        let rowid = self.rowid + 1;
+        self.keys.push([z as i64, x as i64, row, rowid]);
+        let payload = record(&[
+            Column::Int(z as i64),
+            Column::Int(x as i64),
+            Column::Int(row),
+            Column::Blob(data),
+        ]);
+        let cell = self.table_cell(rowid, &payload)?;
+        if !fits(&self.cells, &cell, 8) {
+            self.flush_leaf()?;
+        }
+        self.cells.push(cell);
+        self.rowid = rowid;
+        Ok(())
+    }
+
+    /// A table leaf cell, writing what does not fit on the page to overflow pages.
+    fn table_cell(&mut self, rowid: i64, payload: &[u8]) -> io::Result<Vec<u8>> {
+        let mut cell = Vec::new();
+        varint(payload.len() as u64, &mut cell);
+        varint(rowid as u64, &mut cell);
+        if payload.len() <= MAX_LOCAL {
+            cell.extend(payload);
+            return Ok(cell);
+        }
+        let local = match MIN_LOCAL + (payload.len() - MIN_LOCAL) % (PAGE_SIZE - 4) {
+            k if k <= MAX_LOCAL => k,
+            _ => MIN_LOCAL,
+        };
+        cell.extend(&payload[..local]);
+        let chunks: Vec<&[u8]> = payload[local..].chunks(PAGE_SIZE - 4).collect();
+        // overflow pages are written in order, each pointing to the one after it
+        let first = self.pages + 1;
+        for (n, chunk) in chunks.iter().enumerate() {
+            let mut page = vec![0u8; PAGE_SIZE];
+            if n + 1 < chunks.len() {
+                page[..4].copy_from_slice(&(self.pages + 2).to_be_bytes());
+            }
+            page[4..4 + chunk.len()].copy_from_slice(chunk);
+            self.write_page(&page)?;
+        }
+        cell.extend(first.to_be_bytes());
+        Ok(cell)
+    }
+
+    fn flush_leaf(&mut self) -> io::Result<()> {
+        if self.cells.is_empty() {
+            return Ok(());
+        }
+        let cells = std::mem::take(&mut self.cells);
+        let n = self.write_page(&page(TABLE_LEAF, &cells, None, 0))?;
+        self.leaves.push((n, self.rowid));
+        Ok(())
+    }
+
+    /// Writes the pages above `children` (page, last rowid) until one root page is left.
+    fn table_root(&mut self, mut children: Vec<(u32, i64)>) -> io::Result<u32> {
+        if children.is_empty() {
+            return self.write_page(&page(TABLE_LEAF, &[], None, 0));
+        }
+        // a child page number and the largest rowid as a varint take at most 13 bytes
+        let cap = (PAGE_SIZE - 12) / (13 + 2) + 1;
+        while children.len() > 1 {
+            let mut parents = Vec::new();
+            for group in groups(children.len(), cap) {
+                let (right, key) = children[group.end - 1];
+                let cells: Vec<Vec<u8>> = children[group.start..group.end - 1]
+                    .iter()
+                    .map(|(child, key)| {
+                        let mut cell = child.to_be_bytes().to_vec();
+                        varint(*key as u64, &mut cell);
+                        cell
+                    })
+                    .collect();
+                let page = page(TABLE_INTERIOR, &cells, Some(right), 0);
+                parents.push((self.write_page(&page)?, key));
+            }
+            children = parents;
+        }
+        Ok(children[0].0)
+    }
+
+    /// Writes an index b-tree of sorted `keys`. Leaves are filled in order, and the key
+    /// after a full leaf moves up as the divider between it and the next one.
+    fn index_root(&mut self, keys: &[Vec<u8>]) -> io::Result<u32> {
+        let leaf_cell = |key: &Vec<u8>| {
+            let mut cell = Vec::new();
+            varint(key.len() as u64, &mut cell);
+            cell.extend(key);
+            cell
+        };
+        let mut level: Vec<u32> = Vec::new();
+        let mut dividers: Vec<&Vec<u8>> = Vec::new();
+        let mut cells: Vec<Vec<u8>> = Vec::new();
+        for n in 0..keys.len() {
+            let cell = leaf_cell(&keys[n]);
+            if fits(&cells, &cell, 8) {
+                cells.push(cell);
+                continue;
+            }
+            // the divider needs a key left after it for the next leaf
+            let divider = match n + 1 < keys.len() {
+                true => n,
+                false => {
+                    cells.pop();
+                    n - 1
+                }
+            };
+            level.push(self.write_page(&page(INDEX_LEAF, &cells, None, 0))?);
+            dividers.push(&keys[divider]);
+            cells = (divider + 1..=n).map(|k| leaf_cell(&keys[k])).collect();
+        }
+        level.push(self.write_page(&page(INDEX_LEAF, &cells, None, 0))?);
+
+        let longest = keys.iter().map(Vec::len).max().unwrap_or(0);
+        let cap = (PAGE_SIZE - 12) / (4 + 9 + longest + 2) + 1;
+        while level.len() > 1 {
+            let mut parents = Vec::new();
+            let mut up = Vec::new();
+            for group in groups(level.len(), cap) {
+                let cells: Vec<Vec<u8>> = (group.start..group.end - 1)
+                    .map(|k| {
+                        let mut cell = level[k].to_be_bytes().to_vec();
+                        cell.extend(leaf_cell(dividers[k]));
+                        cell
+                    })
+                    .collect();
+                let page = page(INDEX_INTERIOR, &cells, Some(level[group.end - 1]), 0);
+                parents.push(self.write_page(&page)?);
+                if group.end < level.len() {
+                    up.push(dividers[group.end - 1]);
+                }
+            }
+            level = parents;
+            dividers = up;
+        }
+        Ok(level[0])
+    }
+
+    /// Writes the rest of the tables and the schema. `metadata` is stored as (name, value) rows.
+    pub(crate) fn finish(mut self, metadata: &[(String, String)]) -> io::Result<()> {
+        self.flush_leaf()?;
+        let leaves = std::mem::take(&mut self.leaves);
+        let tiles_root = self.table_root(leaves)?;
+
+        let mut cells = Vec::new();
+        for (n, (name, value)) in metadata.iter().enumerate() {
+            let payload = record(&[Column::Text(name), Column::Text(value)]);
+            cells.push(self.table_cell(n as i64 + 1, &payload)?);
+        }
+        let mut leaves = Vec::new();
+        let mut page_cells: Vec<Vec<u8>> = Vec::new();
+        for (n, cell) in cells.into_iter().enumerate() {
+            if !fits(&page_cells, &cell, 8) {
+                let written = self.write_page(&page(TABLE_LEAF, &page_cells, None, 0))?;
+                leaves.push((written, n as i64));
+                page_cells.clear();
+            }
+            page_cells.push(cell);
+        }
+        if !page_cells.is_empty() {
+            let written = self.write_page(&page(TABLE_LEAF, &page_cells, None, 0))?;
+            leaves.push((written, metadata.len() as i64));
+        }
+        let metadata_root = self.table_root(leaves)?;
+
+        let mut keys = std::mem::take(&mut self.keys);
+        keys.sort_unstable();
+        let keys: Vec<Vec<u8>> = keys
+            .iter()
+            .map(|k| record(&k.iter().map(|v| Column::Int(*v)).collect::<Vec<_>>()))
+            .collect();
+        let index_root = self.index_root(&keys)?;
+
+        let schema = [
+            ("table", "metadata", "metadata", metadata_root, METADATA_SQL),
+            ("table", "tiles", "tiles", tiles_root, TILES_SQL),
+            ("index", "tile_index", "tiles", index_root, INDEX_SQL),
+        ];
+        let cells: Vec<Vec<u8>> = schema
+            .iter()
+            .enumerate()
+            .map(|(n, (typ, name, table, root, sql))| {
+                let payload = record(&[
+                    Column::Text(typ),
+                    Column::Text(name),
+                    Column::Text(table),
+                    Column::Int(*root as i64),
+                    Column::Text(sql),
+                ]);
+                let mut cell = Vec::new();
+                varint(payload.len() as u64, &mut cell);
+                varint(n as u64 + 1, &mut cell);
+                cell.extend(payload);
+                cell
+            })
+            .collect();
+        let mut first = page(TABLE_LEAF, &cells, None, 100);
+        first[..100].copy_from_slice(&self.file_header());
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_all(&first)?;
+        self.writer.flush()
+    }
+
+    fn file_header(&self) -> [u8; 100] {
+        let mut header = [0u8; 100];
+        header[..16].copy_from_slice(b"SQLite format 3\0");
+        header[16..18].copy_from_slice(&(PAGE_SIZE as u16).to_be_bytes());
+        header[18] = 1; // write version, legacy
+        header[19] = 1; // read version, legacy
+        header[21] = 64; // max embedded payload fraction
+        header[22] = 32; // min embedded payload fraction
+        header[23] = 32; // leaf payload fraction
+        header[24..28].copy_from_slice(&1u32.to_be_bytes()); // change counter
+        header[28..32].copy_from_slice(&self.pages.to_be_bytes());
+        header[40..44].copy_from_slice(&1u32.to_be_bytes()); // schema cookie
+        header[44..48].copy_from_slice(&4u32.to_be_bytes()); // schema format
+        header[56..60].copy_from_slice(&1u32.to_be_bytes()); // UTF-8
+        header[68..72].copy_from_slice(b"MPBX"); // application id of MBTiles
+        header[92..96].copy_from_slice(&1u32.to_be_bytes()); // version valid for
+        header[96..100].copy_from_slice(&3_031_001u32.to_be_bytes());
+        header
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::*;
+
+    #[test]
+    fn varints() {
+        let encode = |v| {
+            let mut out = Vec::new();
+            varint(v, &mut out);
+            out
+        };
+        assert_eq!(encode(0), vec![0]);
+        assert_eq!(encode(127), vec![0x7F]);
+        assert_eq!(encode(128), vec![0x81, 0x00]);
+        assert_eq!(encode(16384), vec![0x81, 0x80, 0x00]);
+        assert_eq!(encode(u64::MAX), vec![0xFF; 9]);
+    }
+
+    #[test]
+    fn records() {
+        assert_eq!(
+            record(&[Column::Int(0), Column::Int(300), Column::Text("ab")]),
+            vec![4, 8, 2, 17, 0x01, 0x2C, b'a', b'b']
+        );
+    }
+
+    #[test]
+    fn overflowing_tiles() {
+        let p = std::env::temp_dir().join(format!("mbtiles-test-{}.mbtiles", std::process::id()));
+        let mut mbtiles = MbTiles::create(&p).unwrap();
+        let big = vec![7u8; 3 * PAGE_SIZE];
+        for n in 0..300 {
+            let data = match n % 10 {
+                0 => &big[..],
+                _ => &big[..100],
+            };
+            mbtiles.add(9, n, 0, data).unwrap();
+        }
+        mbtiles
+            .finish(&[("format".to_string(), "pbf".to_string())])
+            .unwrap();
+        let bytes = std::fs::read(&p).unwrap();
+        std::fs::remove_file(&p).unwrap();
+        assert_eq!(&bytes[..16], b"SQLite format 3\0");
+        let pages = u32::from_be_bytes(bytes[28..32].try_into().unwrap()) as usize;
+        assert_eq!(bytes.len(), pages * PAGE_SIZE);
+        // the schema lists three tables and indexes
+        assert_eq!(u16::from_be_bytes([bytes[103], bytes[104]]), 3);
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use itertools::Itertools;
+use rust_decimal::prelude::ToPrimitive;
+use serde::Serialize;
+
+use crate::{
+    csv::quote,
+    fir_boundaries::{with_extentions, FIRBoundary},
+    statistics::{self, FirStats},
+    vatspy_dat::VatSpyDat,
+};
+
+/// One FIRBoundary as a row for a spreadsheet: its statistics, with what is needed to put it on a map by name.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct FirRow {
+    #[serde(flatten)]
+    pub(crate) stats: FirStats,
+    /// ICAO of the FIR an extention belongs to. Empty for FIRs and extentions without a FIR.
+    pub(crate) parent: String,
+    pub(crate) lable_lat: f64,
+    pub(crate) lable_lon: f64,
+    /// Names from the [FIRs] section of VATSpy.dat using this boundary, joined with `; `.
+    pub(crate) name: String,
+    /// Callsign prefixes from the same lines, joined with `; `.
+    pub(crate) callsign_prefix: String,
+}
+
+/// Makes a row for every FIRBoundary, in the order of the file. Without VATSpy.dat name and callsign prefix are empty.
+pub(crate) fn rows(firs: &[FIRBoundary], dat: Option<&VatSpyDat>) -> Vec<FirRow> {
+    let mut parents = vec![String::new(); firs.len()];
+    for group in with_extentions(firs) {
+        let owner = &firs[group[0]];
+        if owner.is_extension {
+            continue;
+        }
+        for &n in &group[1..] {
+            parents[n] = owner.icao.clone();
+        }
+    }
+    firs.iter()
+        .zip(parents)
+        .map(|(fir, parent)| {
+            let (name, callsign_prefix) = match dat {
+                // extentions are drawn with their FIR, only the FIR itself is listed in VATSpy.dat
+                Some(dat) if !fir.is_extension => {
+                    let lines = dat.firs_with_boundary(&fir.icao).collect_vec();
+                    (
+                        lines.iter().map(|f| f.name.as_str()).join("; "),
+                        lines.iter().map(|f| f.callsign_prefix.as_str()).join("; "),
+                    )
+                }
+                _ => (String::new(), String::new()),
+            };
+            FirRow {
+                stats: FirStats::from(fir),
+                parent,
+                lable_lat: fir.lable.lat.to_f64().unwrap(),
+                lable_lon: fir.lable.lon.to_f64().unwrap(),
+                name,
+                callsign_prefix,
+            }
+        })
+        .collect()
+}
+
+impl FirRow {
+    fn to_csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{}",
+            self.stats.to_csv_row(),
+            quote(&self.parent),
+            self.lable_lat,
+            self.lable_lon,
+            quote(&self.name),
+            quote(&self.callsign_prefix),
+        )
+    }
+}
+
+pub(crate) fn write_csv<W: Write>(writer: &mut W, rows: &[FirRow]) -> io::Result<()> {
+    writeln!(
+        writer,
+        "{},Parent,LableLat,LableLon,Name,CallsignPrefix",
+        statistics::CSV_HEADER
+    )?;
+    for row in rows {
+        writeln!(writer, "{}", row.to_csv_row())?;
+    }
+    Ok(())
+}
+
+pub(crate) fn write_json<W: Write>(writer: &mut W, rows: &[FirRow]) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut *writer, rows)?;
+    writeln!(writer)
+}
+
+/// Writes the rows as JSON if the file ends with .json, and as CSV otherwise.
+pub(crate) fn write_file<P: AsRef<Path>>(p: P, rows: &[FirRow]) -> io::Result<()> {
+    let p = p.as_ref();
+    let mut writer = BufWriter::new(File::create(p)?);
+    match p.extension().and_then(|e| e.to_str()) {
+        Some("json") => write_json(&mut writer, rows)?,
+        _ => write_csv(&mut writer, rows)?,
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fir_boundaries::fir;
+
+    #[test]
+    fn rows_with_vatspy_dat() {
+        let firs = vec![
+            fir("ZZZZ", false, &[(0, 0), (1, 0), (1, 1), (0, 1)]),
+            fir("YYYY", false, &[(0, 1), (1, 1), (1, 2), (0, 2)]),
+            fir("ZZZZ", true, &[(0, 2), (1, 2), (1, 3), (0, 3)]),
+        ];
+        let dat = VatSpyDat::parse(
+            "[FIRs]\nZZZZ|Zulu, Control|ZZ|\nZZZO|Zulu Oceanic|ZZO|ZZZZ\nYYYY|Yankee||\n",
+        )
+        .unwrap();
+        let rows = rows(&firs, Some(&dat));
+        assert_eq!(rows[0].name, "Zulu, Control; Zulu Oceanic");
+        assert_eq!(rows[0].callsign_prefix, "ZZ; ZZO");
+        assert_eq!(rows[2].parent, "ZZZZ");
+        assert_eq!(rows[2].name, "");
+        // one degree square at the equator
+        assert!((rows[1].stats.area_km2 - 12_308.78).abs() < 1.0);
+
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &rows).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert!(lines[0].starts_with(statistics::CSV_HEADER));
+        assert!(lines[0].ends_with(",Parent,LableLat,LableLon,Name,CallsignPrefix"));
+        assert!(lines[1].starts_with("ZZZZ,false,false,4,"));
+        assert!(lines[1].ends_with(r#",0,0,1,1,,1,1,"Zulu, Control; Zulu Oceanic",ZZ; ZZO"#));
+        assert!(lines[2].ends_with(",Yankee,"));
+        assert!(lines[3].starts_with("ZZZZ,false,true,4,"));
+        assert!(lines[3].contains(",ZZZZ,1,1,,"));
+
+        let mut json = Vec::new();
+        write_json(&mut json, &rows).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json[2]["parent"], "ZZZZ");
+        assert_eq!(json[0]["bbox"]["max_lat"], 1.0);
+        assert_eq!(json[0]["lable_lat"], 1.0);
+    }
+}
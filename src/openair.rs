@@ -0,0 +1,420 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use indexmap::IndexMap;
+
+use crate::{
+    dms::Dms,
+    fir_boundaries::{with_extentions, FIRBoundary, FIRParsingError, FIRResult, Point},
+    geodesy::{self, Coord, RingBuilder, METRES_PER_NM},
+    lable,
+};
+
+/// Largest angle in degrees between points along arcs and circles, as seen from their center.
+pub(crate) const ARC_STEP: f64 = 5.0;
+
+// format, one record per line, * starts a comment:
+// AC class            starts a new airspace
+// AY type             airspace type in the extended format, like FIR
+// AN name             the first word is used as ICAO, OCEANIC anywhere in it marks an oceanic FIR
+// AL/AH               lower/upper limit, ignored
+// AT coordinate       lable position
+// V X=coordinate      center for the following arcs and circles
+// V D=+ or V D=-      direction of the following arcs, + is clockwise and the default
+// DP coordinate       point
+// DA radius, start, end             arc with radius in NM between two bearings
+// DB coordinate, coordinate         arc between two points
+// DC radius                         circle with radius in NM
+// DY coordinate       airway point, airways are skipped
+// coordinates are written as DD:MM:SS N DDD:MM:SS E, minutes may have decimals instead of seconds
+
+fn openair_error<S: AsRef<str>>(linenr: usize, s: S) -> FIRParsingError {
+    FIRParsingError::FIRParsing(format!("OpenAir line: {}, {}", linenr, s.as_ref()))
+}
+
+/// Parses degrees with optional :minutes and :seconds.
+fn parse_dms(s: &str) -> Option<f64> {
+    let mut value = 0.0;
+    let mut unit = 1.0;
+    for part in s.trim().split(':') {
+        value += part.trim().parse::<f64>().ok()? / unit;
+        unit *= 60.0;
+    }
+    Some(value)
+}
+
+/// Parses a coordinate like `52:07:55 N 004:43:20 E`, with or without the spaces before the hemispheres.
+fn parse_coord(s: &str) -> Option<Coord> {
+    let s = s.to_uppercase();
+    let ns = s.find(['N', 'S'])?;
+    let rest = &s[ns + 1..];
+    let ew = rest.find(['E', 'W'])?;
+    let mut lat = parse_dms(&s[..ns])?;
+    let mut lon = parse_dms(&rest[..ew])?;
+    if s[ns..].starts_with('S') {
+        lat = -lat;
+    }
+    if rest[ew..].starts_with('W') {
+        lon = -lon;
+    }
+    match (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon) {
+        true => Some(Coord { lat, lon }),
+        false => None,
+    }
+}
+
+fn format_dms(value: f64, degree_digits: usize, positive: char, negative: char) -> String {
+    let dms = Dms::new(value, 0);
+    format!(
+        "{:0width$}:{:02}:{:02} {}",
+        dms.degrees,
+        dms.minutes,
+        dms.seconds,
+        dms.hemisphere(positive, negative),
+        width = degree_digits
+    )
+}
+
+/// Writes a coordinate as `DD:MM:SS N DDD:MM:SS E`, rounded to whole seconds.
+fn format_coord(p: &Point) -> String {
+    let c = Coord::from(p);
+    format!(
+        "{} {}",
+        format_dms(c.lat, 2, 'N', 'S'),
+        format_dms(c.lon, 3, 'E', 'W')
+    )
+}
+
+/// An airspace while it is being read.
+#[derive(Default)]
+struct Airspace {
+    linenr: usize,
+    class: String,
+    typ: String,
+    name: String,
+    lable: Option<Coord>,
+    ring: RingBuilder,
+    airway: bool,
+}
+
+/// State set by V records, kept from one airspace to the next like most readers do.
+struct Variables {
+    center: Option<Coord>,
+    clockwise: bool,
+}
+
+fn coord(s: &str, linenr: usize) -> FIRResult<Coord> {
+    parse_coord(s).ok_or_else(|| openair_error(linenr, format!("not a coordinate: {}", s.trim())))
+}
+
+fn number(s: &str, linenr: usize) -> FIRResult<f64> {
+    s.trim()
+        .parse()
+        .map_err(|_| openair_error(linenr, format!("not a number: {}", s.trim())))
+}
+
+fn center(v: &Variables, linenr: usize) -> FIRResult<Coord> {
+    v.center
+        .ok_or_else(|| openair_error(linenr, "arc without a center, V X= is missing"))
+}
+
+fn sweep(start: f64, end: f64, clockwise: bool) -> f64 {
+    match clockwise {
+        true => (end - start).rem_euclid(360.0),
+        false => -(start - end).rem_euclid(360.0),
+    }
+}
+
+/// Reads the record of one line into the airspace.
+fn read_record(
+    airspace: &mut Airspace,
+    v: &mut Variables,
+    record: &str,
+    rest: &str,
+    linenr: usize,
+    step: f64,
+) -> FIRResult<()> {
+    match record {
+        "AY" => airspace.typ = rest.trim().to_string(),
+        "AN" => airspace.name = rest.trim().to_string(),
+        "AT" if airspace.lable.is_none() => airspace.lable = Some(coord(rest, linenr)?),
+        "V" => {
+            let (key, value) = rest
+                .split_once('=')
+                .ok_or_else(|| openair_error(linenr, format!("V without =: {}", rest.trim())))?;
+            match key.trim().to_uppercase().as_str() {
+                "X" => v.center = Some(coord(value, linenr)?),
+                "D" => v.clockwise = value.trim() != "-",
+                _ => (),
+            }
+        }
+        "DP" => airspace.ring.push_points(vec![coord(rest, linenr)?]),
+        "DA" => {
+            let values: Vec<&str> = rest.split(',').collect();
+            if values.len() != 3 {
+                return Err(openair_error(
+                    linenr,
+                    format!("DA needs radius, start and end: {}", rest.trim()),
+                ));
+            }
+            let radius = number(values[0], linenr)? * METRES_PER_NM;
+            let (start, end) = (number(values[1], linenr)?, number(values[2], linenr)?);
+            airspace.ring.push_arc(geodesy::arc_points(
+                center(v, linenr)?,
+                radius,
+                start,
+                sweep(start, end, v.clockwise),
+                step,
+            )?);
+        }
+        "DB" => {
+            let (from, to) = rest.split_once(',').ok_or_else(|| {
+                openair_error(linenr, format!("DB needs two coordinates: {}", rest.trim()))
+            })?;
+            let (from, to) = (coord(from, linenr)?, coord(to, linenr)?);
+            let c = center(v, linenr)?;
+            let (start, end) = (geodesy::bearing(c, from), geodesy::bearing(c, to));
+            airspace.ring.push_arc(geodesy::arc_points(
+                c,
+                geodesy::great_circle_distance(c, from),
+                start,
+                sweep(start, end, v.clockwise),
+                step,
+            )?);
+            airspace.ring.push_points(vec![to]);
+        }
+        "DC" => {
+            let radius = number(rest, linenr)? * METRES_PER_NM;
+            airspace.ring.push_arc(geodesy::arc_points(
+                center(v, linenr)?,
+                radius,
+                0.0,
+                360.0,
+                step,
+            )?);
+        }
+        "DY" => airspace.airway = true,
+        _ => (),
+    }
+    Ok(())
+}
+
+/// Parses OpenAir airspaces into FIRBoundaries. Only airspaces with a class (AC) or type (AY)
+/// in `classes` are read, or every airspace if it is empty. Airways are skipped.
+///
+/// Airspaces named with the same ICAO as an earlier one become extentions of it, and are moved after it.
+/// Airspaces without an AT record get their lable at the pole of inaccessibility of the largest part.
+pub(crate) fn parse(content: &str, classes: &[String], step: f64) -> FIRResult<Vec<FIRBoundary>> {
+    let classes: Vec<String> = classes.iter().map(|c| c.to_uppercase()).collect();
+    let mut airspaces = Vec::new();
+    let mut current: Option<Airspace> = None;
+    let mut v = Variables {
+        center: None,
+        clockwise: true,
+    };
+    for (n, line) in content.lines().enumerate() {
+        let linenr = n + 1;
+        let line = line.split('*').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (record, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let record = record.to_uppercase();
+        if record == "AC" {
+            airspaces.extend(current.take());
+            v.clockwise = true;
+            current = Some(Airspace {
+                linenr,
+                class: rest.trim().to_string(),
+                ..Default::default()
+            });
+            continue;
+        }
+        match current.as_mut() {
+            Some(airspace) => read_record(airspace, &mut v, &record, rest, linenr, step)?,
+            None if record == "V" => read_record(
+                &mut Airspace::default(),
+                &mut v,
+                &record,
+                rest,
+                linenr,
+                step,
+            )?,
+            None => return Err(openair_error(linenr, "record before the first AC")),
+        }
+    }
+    airspaces.extend(current);
+
+    let mut groups: IndexMap<(String, bool), Vec<FIRBoundary>> = IndexMap::new();
+    let mut without_lable = Vec::new();
+    let mut id = 0;
+    for airspace in airspaces {
+        let wanted = classes.is_empty()
+            || classes.contains(&airspace.class.to_uppercase())
+            || classes.contains(&airspace.typ.to_uppercase());
+        if !wanted || airspace.airway {
+            continue;
+        }
+        let corners = geodesy::boundary_corners(&airspace.ring.finish());
+        if corners.is_empty() {
+            continue;
+        }
+        if corners.len() < 3 {
+            return Err(openair_error(
+                airspace.linenr,
+                format!("{} has less than 3 points", airspace.name),
+            ));
+        }
+        let icao = match airspace.name.split_whitespace().next() {
+            Some(icao) => icao.to_uppercase(),
+            None => {
+                return Err(openair_error(
+                    airspace.linenr,
+                    "airspace without a name (AN)",
+                ))
+            }
+        };
+        let is_oseanic = airspace.name.to_uppercase().contains("OCEANIC");
+        let group = groups.entry((icao.clone(), is_oseanic)).or_default();
+        let mut fir = FIRBoundary {
+            id,
+            icao,
+            is_oseanic,
+            is_extension: !group.is_empty(),
+            min_lat: Default::default(),
+            min_lon: Default::default(),
+            max_lat: Default::default(),
+            max_lon: Default::default(),
+            lable: airspace
+                .lable
+                .map(Coord::to_point)
+                .unwrap_or_else(|| corners[0].clone()),
+            boundary_corners: corners,
+        };
+        if airspace.lable.is_none() {
+            without_lable.push(id);
+        }
+        fir.update_bounds();
+        group.push(fir);
+        id += 1;
+    }
+
+    let mut firs: Vec<FIRBoundary> = groups.into_iter().flat_map(|(_, g)| g).collect();
+    for group in with_extentions(&firs) {
+        if without_lable.contains(&firs[group[0]].id) {
+            let lable = lable::group_lable(&firs, &group);
+            for n in &group {
+                if without_lable.contains(&firs[*n].id) {
+                    firs[*n].lable = lable.clone();
+                }
+            }
+        } else {
+            let lable = firs[group[0]].lable.clone();
+            for n in &group[1..] {
+                if without_lable.contains(&firs[*n].id) {
+                    firs[*n].lable = lable.clone();
+                }
+            }
+        }
+    }
+    Ok(firs)
+}
+
+pub(crate) fn read_file<P: AsRef<Path>>(
+    p: P,
+    classes: &[String],
+    step: f64,
+) -> FIRResult<Vec<FIRBoundary>> {
+    parse(&fs::read_to_string(p)?, classes, step)
+}
+
+/// Writes the FIRs as OpenAir airspaces of type FIR in the extended format, extentions as airspaces
+/// of their own with the same name. Coordinates are rounded to whole seconds.
+pub(crate) fn to_writer<W: Write>(firs: &[FIRBoundary], writer: &mut W) -> io::Result<()> {
+    for fir in firs {
+        let name = match fir.is_oseanic {
+            true => format!("{} OCEANIC", fir.icao),
+            false => fir.icao.clone(),
+        };
+        match fir.is_extension {
+            true => writeln!(writer, "* {} extention", name)?,
+            false => writeln!(writer, "* {}", name)?,
+        }
+        writeln!(writer, "AC UNCLASSIFIED")?;
+        writeln!(writer, "AY FIR")?;
+        writeln!(writer, "AN {}", name)?;
+        writeln!(writer, "AL GND")?;
+        writeln!(writer, "AH UNLTD")?;
+        writeln!(writer, "AT {}", format_coord(&fir.lable))?;
+        for p in &fir.boundary_corners {
+            writeln!(writer, "DP {}", format_coord(p))?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn write_file<P: AsRef<Path>>(firs: &[FIRBoundary], p: P) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(p)?);
+    to_writer(firs, &mut writer)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coordinates() {
+        let c = parse_coord("52:07:30 N 004:43:20.4 W").unwrap();
+        assert!((c.lat - 52.125).abs() < 1e-9);
+        assert!((c.lon + (4.0 + 43.0 / 60.0 + 20.4 / 3600.0)).abs() < 1e-9);
+        let c = parse_coord("52:07.5S 4:30E").unwrap();
+        assert!((c.lat + 52.125).abs() < 1e-9);
+        assert!((c.lon - 4.5).abs() < 1e-9);
+        assert!(parse_coord("95:00:00 N 004:00:00 E").is_none());
+        assert_eq!(
+            format_coord(&Point::new("-52.125".parse().unwrap(), "4.5".parse().unwrap()).unwrap()),
+            "52:07:30 S 004:30:00 E"
+        );
+    }
+
+    #[test]
+    fn arcs_and_extentions() {
+        let content = "\
+* a half circle east of 10 E, and an extention
+AC UNCLASSIFIED
+AN ENXX
+V X=59:30:00 N 010:00:00 E
+DP 59:00:00 N 010:00:00 E
+DP 60:00:00 N 010:00:00 E
+DB 60:00:00 N 010:00:00 E, 59:00:00 N 010:00:00 E
+AC D
+AN ENYY
+DP 59:00:00 N 009:00:00 E
+DP 60:00:00 N 009:00:00 E
+DP 60:00:00 N 010:00:00 E
+AC UNCLASSIFIED
+AN ENXX
+V D=-
+DP 59:00:00 N 010:00:00 E
+DA 30, 180, 270
+";
+        let firs = parse(content, &[], 10.0).unwrap();
+        assert_eq!(firs.len(), 3);
+        assert_eq!(firs[0].icao, "ENXX");
+        assert!(firs[1].is_extension);
+        assert_eq!(firs[2].icao, "ENYY");
+        // 2 points and 19 along the arc, where the first and the last are the given points
+        assert_eq!(firs[0].boundary_corners.len(), 2 + 17);
+        assert!(geodesy::contains(
+            &geodesy::ring_coords(&firs[0].boundary_corners),
+            Coord::from(&firs[0].lable)
+        ));
+        let only_d = parse(content, &["d".to_string()], 10.0).unwrap();
+        assert_eq!(only_d.len(), 1);
+    }
+}
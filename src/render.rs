@@ -0,0 +1,893 @@
+use std::{
+    f64::consts::FRAC_PI_4,
+    fmt::{self, Display},
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use crate::{
+    fir_boundaries::{FIRBoundary, FIRParsingError, Point},
+    geodesy::{self, Coord},
+    tiles::crc32,
+};
+
+/// Space around the map, in pixels.
+const MARGIN: f64 = 20.0;
+/// Highest image, as a number of widths, so a narrow FIR does not make an endless image.
+const MAX_ASPECT: f64 = 4.0;
+
+/// Allowed image widths in pixels. The height can be up to `MAX_ASPECT` times the width.
+pub(crate) const MIN_WIDTH: u32 = 16;
+pub(crate) const MAX_WIDTH: u32 = 16384;
+
+const BACKGROUND: [u8; 3] = [255, 255, 255];
+const FIR_COLOR: [u8; 3] = [0x1f, 0x5f, 0xbf];
+const OCEANIC_COLOR: [u8; 3] = [0x0b, 0x8a, 0x7a];
+const EXTENTION_COLOR: [u8; 3] = [0xd9, 0x7a, 0x00];
+const FINDING_COLOR: [u8; 3] = [0xe0, 0x00, 0x00];
+const LABLE_COLOR: [u8; 3] = [0x22, 0x22, 0x22];
+const FILL_OPACITY: f64 = 0.15;
+/// Dash and gap length in pixels of extention outlines.
+const DASH: (f64, f64) = (6.0, 4.0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Projection {
+    /// Longitude and latitude as a grid, with longitudes shortened to be true at the middle latitude.
+    Equirectangular,
+    Mercator,
+    /// Lambert azimuthal equal-area centered on the map, keeping areas and shapes near the center true.
+    Lambert,
+}
+
+impl FromStr for Projection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "equirectangular" => Ok(Self::Equirectangular),
+            "mercator" => Ok(Self::Mercator),
+            "lambert" => Ok(Self::Lambert),
+            s => Err(format!(
+                "unknown projection: {}, expected one of: equirectangular, mercator, lambert",
+                s
+            )),
+        }
+    }
+}
+
+impl Projection {
+    /// Position on the map in any unit, with x to the east and y to the north.
+    fn project(self, c: Coord, center: Coord) -> [f64; 2] {
+        match self {
+            Projection::Equirectangular => [c.lon * center.lat.to_radians().cos(), c.lat],
+            Projection::Mercator => {
+                let lat = c.lat.clamp(-85.0, 85.0).to_radians();
+                [c.lon, (FRAC_PI_4 + lat / 2.0).tan().ln().to_degrees()]
+            }
+            Projection::Lambert => {
+                let (lat, lat0) = (c.lat.to_radians(), center.lat.to_radians());
+                let dlon = geodesy::normalize_lon(c.lon - center.lon).to_radians();
+                let k = (2.0
+                    / (1.0 + lat0.sin() * lat.sin() + lat0.cos() * lat.cos() * dlon.cos()))
+                .sqrt();
+                [
+                    k * lat.cos() * dlon.sin(),
+                    k * (lat0.cos() * lat.sin() - lat0.sin() * lat.cos() * dlon.cos()),
+                ]
+            }
+        }
+    }
+}
+
+/// Something wrong found in the dataset, shown in red on the map.
+#[derive(Debug, Clone)]
+pub(crate) struct Finding {
+    /// FIRs the finding is about, their outlines are drawn in red.
+    pub(crate) owners: Vec<String>,
+    /// Where exactly, marked with a red circle.
+    pub(crate) points: Vec<Point>,
+    pub(crate) message: String,
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<&FIRParsingError> for Finding {
+    fn from(e: &FIRParsingError) -> Self {
+        let (owners, points) = match e {
+            FIRParsingError::DuplicatePointError { points, owner } => {
+                (vec![owner.clone()], points.iter().cloned().collect())
+            }
+            FIRParsingError::LableOutsideFir { owner, lable } => {
+                (vec![owner.clone()], vec![lable.clone()])
+            }
+            FIRParsingError::PointOutOfRange(p) => (Vec::new(), vec![p.clone()]),
+            FIRParsingError::AirspaceDrawDirection(owner)
+            | FIRParsingError::WrongMinMax(_, owner)
+            | FIRParsingError::CoordinateNotation { owner, .. } => {
+                (vec![owner.clone()], Vec::new())
+            }
+            FIRParsingError::ExtentionNotAfterFir(owners) => {
+                (owners.iter().cloned().collect(), Vec::new())
+            }
+            FIRParsingError::MultipleFirs(owners) => (owners.keys().cloned().collect(), Vec::new()),
+            _ => (Vec::new(), Vec::new()),
+        };
+        Self {
+            owners,
+            points,
+            message: e.to_string(),
+        }
+    }
+}
+
+/// Finds boundaries crossing themselves. Validation does not look for these, as VATSpy draws them anyway.
+pub(crate) fn self_intersections(firs: &[FIRBoundary]) -> Vec<Finding> {
+    firs.iter()
+        .filter_map(|fir| {
+            let crossings =
+                geodesy::self_intersections(&geodesy::ring_coords(&fir.boundary_corners));
+            if crossings.is_empty() {
+                return None;
+            }
+            let points: Vec<Point> = crossings.into_iter().map(Coord::to_point).collect();
+            Some(Finding {
+                owners: vec![fir.icao.clone()],
+                message: format!(
+                    "Self intersection: FIR: {}{}, crosses itself at: {}.",
+                    fir.icao,
+                    match fir.is_extension {
+                        true => " (extention)",
+                        false => "",
+                    },
+                    points
+                        .iter()
+                        .map(|p| p.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                points,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Fir,
+    Oceanic,
+    Extention,
+}
+
+impl Kind {
+    fn color(self) -> [u8; 3] {
+        match self {
+            Kind::Fir => FIR_COLOR,
+            Kind::Oceanic => OCEANIC_COLOR,
+            Kind::Extention => EXTENTION_COLOR,
+        }
+    }
+}
+
+/// Everything to draw, in pixels with y down.
+struct Scene {
+    width: u32,
+    height: u32,
+    shapes: Vec<(Vec<[f64; 2]>, Kind)>,
+    lables: Vec<([f64; 2], String)>,
+    /// Outlines of FIRs with findings.
+    marked: Vec<Vec<[f64; 2]>>,
+    findings: Vec<[f64; 2]>,
+}
+
+fn scene(firs: &[FIRBoundary], findings: &[Finding], projection: Projection, width: u32) -> Scene {
+    let rings: Vec<Vec<Coord>> = firs
+        .iter()
+        .map(|fir| geodesy::ring_coords(&fir.boundary_corners))
+        .collect();
+    let all = || rings.iter().flatten();
+    let (min_lat, max_lat) = all().fold((f64::MAX, f64::MIN), |(a, b), c| {
+        (a.min(c.lat), b.max(c.lat))
+    });
+    let (min_lon, max_lon) = all().fold((f64::MAX, f64::MIN), |(a, b), c| {
+        (a.min(c.lon), b.max(c.lon))
+    });
+    let center = Coord {
+        lat: (min_lat + max_lat) / 2.0,
+        lon: (min_lon + max_lon) / 2.0,
+    };
+    let projected: Vec<Vec<[f64; 2]>> = rings
+        .iter()
+        .map(|ring| {
+            ring.iter()
+                .map(|c| projection.project(*c, center))
+                .collect()
+        })
+        .collect();
+    // lables and findings are moved a world east or west to be next to the unwrapped rings
+    let near = |c: Coord| {
+        let lon = center.lon + geodesy::normalize_lon(c.lon - center.lon);
+        projection.project(Coord { lat: c.lat, lon }, center)
+    };
+    let lable_points: Vec<([f64; 2], String)> = firs
+        .iter()
+        .filter(|fir| !fir.is_extension)
+        .map(|fir| (near(Coord::from(&fir.lable)), fir.icao.clone()))
+        .collect();
+    let finding_points: Vec<[f64; 2]> = findings
+        .iter()
+        .flat_map(|f| &f.points)
+        .map(|p| near(Coord::from(p)))
+        .collect();
+    // a lable or finding outside its FIR is still framed
+    let points = || {
+        projected
+            .iter()
+            .flatten()
+            .chain(lable_points.iter().map(|(p, _)| p))
+            .chain(&finding_points)
+    };
+    let (min_x, max_x) =
+        points().fold((f64::MAX, f64::MIN), |(a, b), p| (a.min(p[0]), b.max(p[0])));
+    let (min_y, max_y) =
+        points().fold((f64::MAX, f64::MIN), |(a, b), p| (a.min(p[1]), b.max(p[1])));
+    let inner = width as f64 - 2.0 * MARGIN;
+    let scale = match (max_x - min_x, max_y - min_y) {
+        (w, h) if w <= 0.0 && h <= 0.0 => 1.0,
+        (w, h) => (inner / w).min(inner * MAX_ASPECT / h),
+    };
+    let height = ((max_y - min_y) * scale + 2.0 * MARGIN)
+        .ceil()
+        .max(2.0 * MARGIN) as u32;
+    let to_screen = |p: [f64; 2]| {
+        [
+            MARGIN + (p[0] - min_x) * scale,
+            MARGIN + (max_y - p[1]) * scale,
+        ]
+    };
+
+    let shapes = firs
+        .iter()
+        .zip(&projected)
+        .map(|(fir, ring)| {
+            let kind = match (fir.is_extension, fir.is_oseanic) {
+                (true, _) => Kind::Extention,
+                (false, true) => Kind::Oceanic,
+                (false, false) => Kind::Fir,
+            };
+            (ring.iter().map(|p| to_screen(*p)).collect(), kind)
+        })
+        .collect();
+    let lables = lable_points
+        .into_iter()
+        .map(|(p, icao)| (to_screen(p), icao))
+        .collect();
+    let marked = firs
+        .iter()
+        .zip(&projected)
+        .filter(|(fir, _)| findings.iter().any(|f| f.owners.contains(&fir.icao)))
+        .map(|(_, ring)| ring.iter().map(|p| to_screen(*p)).collect())
+        .collect();
+    let findings = finding_points.into_iter().map(to_screen).collect();
+    Scene {
+        width,
+        height,
+        shapes,
+        lables,
+        marked,
+        findings,
+    }
+}
+
+fn hex(color: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+fn svg_path(ring: &[[f64; 2]]) -> String {
+    let mut d = String::new();
+    for (n, p) in ring.iter().enumerate() {
+        let command = match n {
+            0 => 'M',
+            _ => 'L',
+        };
+        d.push_str(&format!("{}{:.2} {:.2} ", command, p[0], p[1]));
+    }
+    d.push('Z');
+    d
+}
+
+fn write_svg<W: Write>(scene: &Scene, w: &mut W) -> io::Result<()> {
+    writeln!(
+        w,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+        scene.width, scene.height
+    )?;
+    writeln!(
+        w,
+        r#"<rect width="100%" height="100%" fill="{}"/>"#,
+        hex(BACKGROUND)
+    )?;
+    for (ring, kind) in &scene.shapes {
+        let dash = match kind {
+            Kind::Extention => format!(r#" stroke-dasharray="{} {}""#, DASH.0, DASH.1),
+            _ => String::new(),
+        };
+        writeln!(
+            w,
+            r#"<path d="{0}" fill="{2}" fill-opacity="{1}" stroke="{2}" stroke-width="1"{3}/>"#,
+            svg_path(ring),
+            FILL_OPACITY,
+            hex(kind.color()),
+            dash
+        )?;
+    }
+    for ring in &scene.marked {
+        writeln!(
+            w,
+            r#"<path d="{}" fill="none" stroke="{}" stroke-width="2.5"/>"#,
+            svg_path(ring),
+            hex(FINDING_COLOR)
+        )?;
+    }
+    for (p, icao) in &scene.lables {
+        writeln!(
+            w,
+            r#"<circle cx="{:.2}" cy="{:.2}" r="2.5" fill="{}"/>"#,
+            p[0],
+            p[1],
+            hex(LABLE_COLOR)
+        )?;
+        writeln!(
+            w,
+            r#"<text x="{:.2}" y="{:.2}" font-family="sans-serif" font-size="11" fill="{}" stroke="{}" stroke-width="3" paint-order="stroke">{}</text>"#,
+            p[0] + 5.0,
+            p[1] - 4.0,
+            hex(LABLE_COLOR),
+            hex(BACKGROUND),
+            escape(icao)
+        )?;
+    }
+    for p in &scene.findings {
+        writeln!(
+            w,
+            r#"<circle cx="{:.2}" cy="{:.2}" r="6" fill="none" stroke="{}" stroke-width="2"/>"#,
+            p[0],
+            p[1],
+            hex(FINDING_COLOR)
+        )?;
+    }
+    writeln!(w, "</svg>")
+}
+
+/// Escapes text for SVG and HTML, both in elements and in attribute values within double quotes.
+pub(crate) fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A 5 by 7 pixel font for the lables in PNG images, a row per byte with the left pixel in bit 4.
+/// Lower case is drawn as upper case, and other characters as a box.
+const FONT: [(char, [u8; 7]); 38] = [
+    (
+        'A',
+        [
+            0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+        ],
+    ),
+    (
+        'B',
+        [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110,
+        ],
+    ),
+    (
+        'C',
+        [
+            0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110,
+        ],
+    ),
+    (
+        'D',
+        [
+            0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110,
+        ],
+    ),
+    (
+        'E',
+        [
+            0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111,
+        ],
+    ),
+    (
+        'F',
+        [
+            0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000,
+        ],
+    ),
+    (
+        'G',
+        [
+            0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111,
+        ],
+    ),
+    (
+        'H',
+        [
+            0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+        ],
+    ),
+    (
+        'I',
+        [
+            0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+        ],
+    ),
+    (
+        'J',
+        [
+            0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100,
+        ],
+    ),
+    (
+        'K',
+        [
+            0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001,
+        ],
+    ),
+    (
+        'L',
+        [
+            0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111,
+        ],
+    ),
+    (
+        'M',
+        [
+            0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001,
+        ],
+    ),
+    (
+        'N',
+        [
+            0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001,
+        ],
+    ),
+    (
+        'O',
+        [
+            0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+        ],
+    ),
+    (
+        'P',
+        [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000,
+        ],
+    ),
+    (
+        'Q',
+        [
+            0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101,
+        ],
+    ),
+    (
+        'R',
+        [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001,
+        ],
+    ),
+    (
+        'S',
+        [
+            0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110,
+        ],
+    ),
+    (
+        'T',
+        [
+            0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100,
+        ],
+    ),
+    (
+        'U',
+        [
+            0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+        ],
+    ),
+    (
+        'V',
+        [
+            0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100,
+        ],
+    ),
+    (
+        'W',
+        [
+            0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010,
+        ],
+    ),
+    (
+        'X',
+        [
+            0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001,
+        ],
+    ),
+    (
+        'Y',
+        [
+            0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100,
+        ],
+    ),
+    (
+        'Z',
+        [
+            0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111,
+        ],
+    ),
+    (
+        '0',
+        [
+            0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110,
+        ],
+    ),
+    (
+        '1',
+        [
+            0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+        ],
+    ),
+    (
+        '2',
+        [
+            0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111,
+        ],
+    ),
+    (
+        '3',
+        [
+            0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110,
+        ],
+    ),
+    (
+        '4',
+        [
+            0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010,
+        ],
+    ),
+    (
+        '5',
+        [
+            0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110,
+        ],
+    ),
+    (
+        '6',
+        [
+            0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110,
+        ],
+    ),
+    (
+        '7',
+        [
+            0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000,
+        ],
+    ),
+    (
+        '8',
+        [
+            0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110,
+        ],
+    ),
+    (
+        '9',
+        [
+            0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100,
+        ],
+    ),
+    (
+        '-',
+        [
+            0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000,
+        ],
+    ),
+    (
+        '_',
+        [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111,
+        ],
+    ),
+];
+
+fn glyph(c: char) -> [u8; 7] {
+    FONT.iter()
+        .find(|(f, _)| *f == c.to_ascii_uppercase())
+        .map(|(_, g)| *g)
+        .unwrap_or([
+            0b11111, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11111,
+        ])
+}
+
+/// An RGB image drawn on the CPU. Shapes are first drawn into `mask` as coverage from 0 to 1,
+/// and then blended onto the image in one color, so overlapping parts of a shape are not blended twice.
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 3]>,
+    mask: Vec<f32>,
+    touched: Vec<usize>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> Self {
+        let (width, height) = (width as usize, height as usize);
+        Self {
+            width,
+            height,
+            pixels: vec![BACKGROUND; width * height],
+            mask: vec![0.0; width * height],
+            touched: Vec::new(),
+        }
+    }
+
+    fn cover(&mut self, x: i64, y: i64, coverage: f64) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 || coverage <= 0.0 {
+            return;
+        }
+        let i = y as usize * self.width + x as usize;
+        if self.mask[i] == 0.0 {
+            self.touched.push(i);
+        }
+        self.mask[i] = self.mask[i].max(coverage.min(1.0) as f32);
+    }
+
+    /// Blends what is in the mask onto the image and clears the mask.
+    fn blend(&mut self, color: [u8; 3], opacity: f64) {
+        for i in std::mem::take(&mut self.touched) {
+            let alpha = self.mask[i] as f64 * opacity;
+            let pixel = &mut self.pixels[i];
+            for (p, c) in pixel.iter_mut().zip(&color) {
+                *p = (*p as f64 * (1.0 - alpha) + *c as f64 * alpha).round() as u8;
+            }
+            self.mask[i] = 0.0;
+        }
+    }
+
+    /// Covers the inside of a ring, with pixel centers inside by the even-odd rule.
+    fn fill(&mut self, ring: &[[f64; 2]]) {
+        let min_y = ring
+            .iter()
+            .map(|p| p[1])
+            .fold(f64::MAX, f64::min)
+            .max(0.0)
+            .floor() as i64;
+        let max_y = ring
+            .iter()
+            .map(|p| p[1])
+            .fold(f64::MIN, f64::max)
+            .min(self.height as f64)
+            .ceil() as i64;
+        for y in min_y..max_y {
+            let cy = y as f64 + 0.5;
+            let mut xs: Vec<f64> = ring
+                .iter()
+                .zip(ring.iter().cycle().skip(1))
+                .filter(|(a, b)| (a[1] > cy) != (b[1] > cy))
+                .map(|(a, b)| a[0] + (cy - a[1]) / (b[1] - a[1]) * (b[0] - a[0]))
+                .collect();
+            xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            for pair in xs.chunks(2) {
+                if let [from, to] = pair {
+                    let from = (from - 0.5).ceil().max(0.0) as i64;
+                    let to = (to - 0.5).floor().min(self.width as f64 - 1.0) as i64;
+                    (from..=to).for_each(|x| self.cover(x, y, 1.0));
+                }
+            }
+        }
+    }
+
+    /// Covers a round dot, with soft edges.
+    fn dot(&mut self, p: [f64; 2], radius: f64) {
+        let r = radius.ceil() as i64 + 1;
+        let (px, py) = (p[0].floor() as i64, p[1].floor() as i64);
+        for y in py - r..=py + r {
+            for x in px - r..=px + r {
+                let d = ((x as f64 + 0.5 - p[0]).powi(2) + (y as f64 + 0.5 - p[1]).powi(2)).sqrt();
+                self.cover(x, y, radius + 0.5 - d);
+            }
+        }
+    }
+
+    /// Covers the outline of a ring `width` pixels wide, dashed if `dash` is given.
+    fn stroke(&mut self, ring: &[[f64; 2]], width: f64, dash: Option<(f64, f64)>) {
+        let mut along = 0.0;
+        for (a, b) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+            let length = ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt();
+            let steps = (length * 2.0).ceil().max(1.0) as usize;
+            for step in 0..=steps {
+                let t = step as f64 / steps as f64;
+                let drawn = match dash {
+                    Some((on, off)) => (along + t * length) % (on + off) < on,
+                    None => true,
+                };
+                if drawn {
+                    self.dot(
+                        [a[0] + t * (b[0] - a[0]), a[1] + t * (b[1] - a[1])],
+                        width / 2.0,
+                    );
+                }
+            }
+            along += length;
+        }
+    }
+
+    /// Covers the outline of a circle.
+    fn circle(&mut self, p: [f64; 2], radius: f64, width: f64) {
+        let r = (radius + width).ceil() as i64 + 1;
+        let (px, py) = (p[0].floor() as i64, p[1].floor() as i64);
+        for y in py - r..=py + r {
+            for x in px - r..=px + r {
+                let d = ((x as f64 + 0.5 - p[0]).powi(2) + (y as f64 + 0.5 - p[1]).powi(2)).sqrt();
+                self.cover(x, y, width / 2.0 + 0.5 - (d - radius).abs());
+            }
+        }
+    }
+
+    /// Covers text with its top left corner at `p`.
+    fn text(&mut self, p: [f64; 2], text: &str) {
+        let (x0, y0) = (p[0].round() as i64, p[1].round() as i64);
+        for (n, c) in text.chars().enumerate() {
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for column in 0..5 {
+                    if bits & (0b10000 >> column) != 0 {
+                        self.cover(x0 + n as i64 * 6 + column, y0 + row as i64, 1.0);
+                    }
+                }
+            }
+        }
+    }
+
+    fn write_png<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let chunk = |w: &mut W, typ: &[u8], data: &[u8]| -> io::Result<()> {
+            w.write_all(&(data.len() as u32).to_be_bytes())?;
+            let mut crc = typ.to_vec();
+            crc.extend(data);
+            w.write_all(&crc)?;
+            w.write_all(&crc32(&crc).to_be_bytes())
+        };
+        w.write_all(&[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'])?;
+        let mut header = Vec::new();
+        header.extend((self.width as u32).to_be_bytes());
+        header.extend((self.height as u32).to_be_bytes());
+        header.extend([8, 2, 0, 0, 0]); // 8 bit RGB, no interlace
+        chunk(w, b"IHDR", &header)?;
+        let mut raw = Vec::with_capacity(self.height * (1 + 3 * self.width));
+        for row in self.pixels.chunks(self.width) {
+            raw.push(0); // no filter
+            row.iter().for_each(|p| raw.extend(p));
+        }
+        chunk(
+            w,
+            b"IDAT",
+            &miniz_oxide::deflate::compress_to_vec_zlib(&raw, 6),
+        )?;
+        chunk(w, b"IEND", &[])
+    }
+}
+
+fn draw(scene: &Scene) -> Canvas {
+    let mut canvas = Canvas::new(scene.width, scene.height);
+    for (ring, kind) in &scene.shapes {
+        canvas.fill(ring);
+        canvas.blend(kind.color(), FILL_OPACITY);
+        let dash = match kind {
+            Kind::Extention => Some(DASH),
+            _ => None,
+        };
+        canvas.stroke(ring, 1.0, dash);
+        canvas.blend(kind.color(), 1.0);
+    }
+    for ring in &scene.marked {
+        canvas.stroke(ring, 2.5, None);
+    }
+    canvas.blend(FINDING_COLOR, 1.0);
+    for (p, icao) in &scene.lables {
+        // a background around the text, so it can be read on top of borders
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                canvas.text([p[0] + 5.0 + dx as f64, p[1] - 11.0 + dy as f64], icao);
+            }
+        }
+    }
+    canvas.blend(BACKGROUND, 1.0);
+    for (p, icao) in &scene.lables {
+        canvas.dot(*p, 2.5);
+        canvas.text([p[0] + 5.0, p[1] - 11.0], icao);
+    }
+    canvas.blend(LABLE_COLOR, 1.0);
+    for p in &scene.findings {
+        canvas.circle(*p, 6.0, 2.0);
+    }
+    canvas.blend(FINDING_COLOR, 1.0);
+    canvas
+}
+
+/// Draws the FIRs and findings as an SVG image, for embedding it in another document.
+pub(crate) fn to_svg<W: Write>(
+    firs: &[FIRBoundary],
+    findings: &[Finding],
+    projection: Projection,
+    width: u32,
+    w: &mut W,
+) -> io::Result<()> {
+    write_svg(&scene(firs, findings, projection, width), w)
+}
+
+/// Draws the FIRs to an SVG or PNG file, depending on the extention of `p`, `width` pixels wide.
+/// FIRs are blue, oceanic FIRs green and extentions orange with dashed outlines. Lables are marked with
+/// their ICAO, and findings drawn in red: the outline of the FIRs they are about, and circles where they are.
+pub(crate) fn write_file<P: AsRef<Path>>(
+    firs: &[FIRBoundary],
+    findings: &[Finding],
+    p: P,
+    projection: Projection,
+    width: u32,
+) -> io::Result<()> {
+    let p = p.as_ref();
+    let scene = scene(firs, findings, projection, width);
+    let mut w = BufWriter::new(File::create(p)?);
+    match p.extension().and_then(|e| e.to_str()) {
+        Some("png") => draw(&scene).write_png(&mut w)?,
+        _ => write_svg(&scene, &mut w)?,
+    }
+    w.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fir_boundaries::fir;
+
+    #[test]
+    fn svg_with_findings() {
+        let firs = vec![
+            fir("ZZZZ", false, &[(-1, 0), (1, 0), (1, 2), (-1, 2)]),
+            fir("ZZZZ", true, &[(-1, 2), (1, 2), (-1, 4), (1, 4)]),
+        ];
+        let findings = self_intersections(&firs);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].points,
+            vec![Point::new(0.into(), 3.into()).unwrap()]
+        );
+
+        let scene = scene(&firs, &findings, Projection::Equirectangular, 440);
+        // 4 by 2 degrees at the equator, 400 pixels wide inside the margins
+        assert_eq!(scene.height, 240);
+        let mut svg = Vec::new();
+        write_svg(&scene, &mut svg).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+        assert_eq!(svg.matches("<path").count(), 4);
+        assert!(svg.contains("stroke-dasharray"));
+        assert!(svg.contains(">ZZZZ</text>"));
+        assert!(svg.contains(r#"<circle cx="320.00" cy="120.00" r="6""#));
+    }
+
+    #[test]
+    fn png_size() {
+        let firs = vec![fir("ZZZZ", false, &[(-1, 0), (1, 0), (1, 2), (-1, 2)])];
+        let canvas = draw(&scene(&firs, &[], Projection::Equirectangular, 100));
+        let mut png = Vec::new();
+        canvas.write_png(&mut png).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+        assert_eq!(&png[16..24], &[0, 0, 0, 100, 0, 0, 0, 100]);
+        // the middle of the FIR is filled
+        assert_ne!(canvas.pixels[50 * 100 + 50], BACKGROUND);
+    }
+}
@@ -0,0 +1,242 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use indexmap::IndexSet;
+
+use crate::{
+    fir_boundaries::FIRBoundary,
+    render::{self, escape, Finding, Projection},
+    statistics::{self, DatasetStats},
+};
+
+/// Width of the map drawn for every FIR with findings, in pixels.
+const MAP_WIDTH: u32 = 480;
+
+const STYLE: &str = r#"body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }
+th { background: #eee; cursor: pointer; user-select: none; }
+th.sorted-up::after { content: " \25B2"; }
+th.sorted-down::after { content: " \25BC"; }
+section { display: inline-block; vertical-align: top; margin: 0 1em 1em 0; }
+section ul { max-width: 480px; padding-left: 1.2em; }
+.ok { color: #0b8a3a; }"#;
+
+/// Sorts a table when a header is clicked, numbers as numbers and the rest as text.
+const SCRIPT: &str = r#"document.querySelectorAll("table.sortable th").forEach(function (th, column) {
+  th.addEventListener("click", function () {
+    var body = th.closest("table").tBodies[0];
+    var up = !th.classList.contains("sorted-up");
+    th.parentNode.querySelectorAll("th").forEach(function (h) { h.classList.remove("sorted-up", "sorted-down"); });
+    th.classList.add(up ? "sorted-up" : "sorted-down");
+    var value = function (row) { return row.cells[column].textContent; };
+    Array.from(body.rows)
+      .sort(function (a, b) {
+        var x = value(a), y = value(b);
+        var n = parseFloat(x) - parseFloat(y);
+        var order = isNaN(n) ? x.localeCompare(y) : n;
+        return up ? order : -order;
+      })
+      .forEach(function (row) { body.appendChild(row); });
+  });
+});"#;
+
+/// Splits a message like `Lable outside FIR: FIR: BIRD, has ...` into its kind and the rest.
+fn kind(message: &str) -> (&str, &str) {
+    match message.split_once(": ") {
+        Some((kind, rest)) => (kind, rest),
+        None => ("", message),
+    }
+}
+
+fn anchor(icao: &str) -> String {
+    format!("fir-{}", escape(icao))
+}
+
+/// Writes an HTML page with the findings in a sortable table, a map of every FIR with findings and dataset statistics.
+/// Styles and scripts are inline, so the file can be opened offline or attached somewhere.
+pub(crate) fn write_report<W: Write>(
+    w: &mut W,
+    title: &str,
+    firs: &[FIRBoundary],
+    findings: &[Finding],
+) -> io::Result<()> {
+    let owners: IndexSet<&str> = findings
+        .iter()
+        .flat_map(|f| f.owners.iter().map(|o| o.as_str()))
+        .collect();
+    let stats = DatasetStats::new(&statistics::fir_stats(firs));
+
+    writeln!(w, "<!DOCTYPE html>")?;
+    writeln!(w, r#"<html lang="en">"#)?;
+    writeln!(w, "<head>")?;
+    writeln!(w, r#"<meta charset="utf-8">"#)?;
+    writeln!(w, "<title>Validation report: {}</title>", escape(title))?;
+    writeln!(w, "<style>\n{}\n</style>", STYLE)?;
+    writeln!(w, "</head>")?;
+    writeln!(w, "<body>")?;
+    writeln!(w, "<h1>Validation report: {}</h1>", escape(title))?;
+    writeln!(
+        w,
+        "<p>Made by vatspy_geojson_converter {}.</p>",
+        env!("CARGO_PKG_VERSION")
+    )?;
+
+    writeln!(w, "<h2>Dataset</h2>")?;
+    writeln!(w, "<table>")?;
+    writeln!(
+        w,
+        "<thead><tr><th>Statistic</th><th>Value</th></tr></thead>"
+    )?;
+    writeln!(w, "<tbody>")?;
+    let rows = [
+        ("FIRs", stats.firs.to_string()),
+        ("Oceanic FIRs", stats.oceanic.to_string()),
+        ("Extentions", stats.extensions.to_string()),
+        ("Vertices", stats.vertices.to_string()),
+        ("Total area km²", format!("{:.1}", stats.area_km2)),
+        ("Total area NM²", format!("{:.1}", stats.area_nm2)),
+        ("Total perimeter km", format!("{:.1}", stats.perimeter_km)),
+        ("Total perimeter NM", format!("{:.1}", stats.perimeter_nm)),
+        ("Findings", findings.len().to_string()),
+        ("FIRs with findings", owners.len().to_string()),
+    ];
+    for (name, value) in rows.iter() {
+        writeln!(w, "<tr><td>{}</td><td>{}</td></tr>", name, value)?;
+    }
+    writeln!(w, "</tbody>")?;
+    writeln!(w, "</table>")?;
+
+    writeln!(w, "<h2>Findings</h2>")?;
+    if findings.is_empty() {
+        writeln!(w, r#"<p class="ok">No findings, the dataset is valid.</p>"#)?;
+    } else {
+        writeln!(w, r#"<table class="sortable">"#)?;
+        writeln!(
+            w,
+            "<thead><tr><th>FIR</th><th>Kind</th><th>Message</th><th>Location</th></tr></thead>"
+        )?;
+        writeln!(w, "<tbody>")?;
+        for f in findings {
+            let (kind, message) = kind(&f.message);
+            let location = f
+                .points
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            // a finding about several FIRs gets a row for each of them
+            let firs: Vec<String> = match f.owners.is_empty() {
+                true => vec![String::new()],
+                false => f
+                    .owners
+                    .iter()
+                    .map(|o| format!(r##"<a href="#{}">{}</a>"##, anchor(o), escape(o)))
+                    .collect(),
+            };
+            for fir in firs {
+                writeln!(
+                    w,
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    fir,
+                    escape(kind),
+                    escape(message),
+                    location
+                )?;
+            }
+        }
+        writeln!(w, "</tbody>")?;
+        writeln!(w, "</table>")?;
+    }
+
+    if !owners.is_empty() {
+        writeln!(w, "<h2>FIRs with findings</h2>")?;
+    }
+    for owner in &owners {
+        let group: Vec<FIRBoundary> = firs.iter().filter(|f| f.icao == *owner).cloned().collect();
+        let own: Vec<Finding> = findings
+            .iter()
+            .filter(|f| f.owners.iter().any(|o| o == owner))
+            .cloned()
+            .collect();
+        writeln!(w, r#"<section id="{}">"#, anchor(owner))?;
+        writeln!(w, "<h3>{}</h3>", escape(owner))?;
+        match group.is_empty() {
+            true => writeln!(w, "<p>Not in the dataset.</p>")?,
+            false => render::to_svg(&group, &own, Projection::Equirectangular, MAP_WIDTH, w)?,
+        }
+        writeln!(w, "<ul>")?;
+        for f in &own {
+            writeln!(w, "<li>{}</li>", escape(&f.message))?;
+        }
+        writeln!(w, "</ul>")?;
+        writeln!(w, "</section>")?;
+    }
+
+    writeln!(w, "<script>\n{}\n</script>", SCRIPT)?;
+    writeln!(w, "</body>")?;
+    writeln!(w, "</html>")
+}
+
+pub(crate) fn write_file<P: AsRef<Path>>(
+    p: P,
+    title: &str,
+    firs: &[FIRBoundary],
+    findings: &[Finding],
+) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(p)?);
+    write_report(&mut w, title, firs, findings)?;
+    w.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fir_boundaries::fir;
+
+    #[test]
+    fn findings_with_maps() {
+        let firs = vec![
+            fir("ZZZZ", false, &[(-1, 0), (1, 0), (1, 2), (-1, 2)]),
+            fir("ZZZZ", true, &[(-1, 2), (1, 2), (-1, 4), (1, 4)]),
+            fir("YYYY", false, &[(-1, 4), (1, 4), (1, 6), (-1, 6)]),
+        ];
+        let findings = render::self_intersections(&firs);
+        let mut html = Vec::new();
+        write_report(&mut html, "<test>", &firs, &findings).unwrap();
+        let html = String::from_utf8(html).unwrap();
+        assert!(html.contains("<title>Validation report: &lt;test&gt;</title>"));
+        assert!(html.contains(
+            r##"<tr><td><a href="#fir-ZZZZ">ZZZZ</a></td><td>Self intersection</td><td>FIR: ZZZZ (extention), crosses itself at: 0|3.</td><td>0|3</td></tr>"##
+        ));
+        assert!(html.contains("<tr><td>FIRs with findings</td><td>1</td></tr>"));
+        // only the FIR with findings gets a map
+        assert!(html.contains(r#"<section id="fir-ZZZZ">"#));
+        assert!(!html.contains(r#"<section id="fir-YYYY">"#));
+        assert_eq!(html.matches("<svg").count(), 1);
+    }
+
+    #[test]
+    fn valid_dataset() {
+        let firs = vec![fir("ZZZZ", false, &[(-1, 0), (1, 0), (1, 2), (-1, 2)])];
+        let mut html = Vec::new();
+        write_report(&mut html, "test", &firs, &[]).unwrap();
+        let html = String::from_utf8(html).unwrap();
+        assert!(html.contains("No findings, the dataset is valid."));
+        assert!(!html.contains("<svg"));
+    }
+
+    #[test]
+    fn quotes_in_attributes() {
+        let firs = vec![fir("Z\"Z", false, &[(-1, 2), (1, 2), (-1, 4), (1, 4)])];
+        let findings = render::self_intersections(&firs);
+        let mut html = Vec::new();
+        write_report(&mut html, "test", &firs, &findings).unwrap();
+        let html = String::from_utf8(html).unwrap();
+        assert!(html.contains(r##"<a href="#fir-Z&quot;Z">Z&quot;Z</a>"##));
+        assert!(html.contains(r#"<section id="fir-Z&quot;Z">"#));
+    }
+}
@@ -0,0 +1,490 @@
+use std::{
+    convert::{TryFrom, TryInto},
+    fs, io,
+    ops::Range,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use indexmap::IndexMap;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+
+use crate::{
+    fir_boundaries::{with_extentions, FIRBoundary, FIRParsingError, FIRResult, Point},
+    geodesy::{self, Coord},
+    lable,
+    topology::open_ring,
+};
+
+// a shapefile is 4 files with the same name:
+// .shp  the polygons, a 100 byte header followed by one record per FIR
+// .shx  where every record starts in the .shp file
+// .dbf  the attributes of every record, a dBase III table
+// .prj  the coordinate system, always WGS 84 here
+// numbers in the headers are big endian and the rest little endian, lengths are counted in 16 bit words
+
+const FILE_CODE: i32 = 9994;
+const VERSION: i32 = 1000;
+const HEADER_LEN: usize = 100;
+const NULL_SHAPE: i32 = 0;
+const POLYGON: i32 = 5;
+const POLYGON_Z: i32 = 15;
+const POLYGON_M: i32 = 25;
+
+const PRJ: &str = r#"GEOGCS["GCS_WGS_1984",DATUM["D_WGS_1984",SPHEROID["WGS_1984",6378137.0,298.257223563]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433]]"#;
+
+/// A column in the .dbf file: name, type, width and decimals.
+struct Field(&'static str, u8, u8, u8);
+
+const FIELDS: [Field; 5] = [
+    Field("ICAO", b'C', 16, 0),
+    Field("OCEANIC", b'L', 1, 0),
+    Field("EXTENSION", b'L', 1, 0),
+    Field("LABLE_LAT", b'N', 12, 6),
+    Field("LABLE_LON", b'N', 12, 6),
+];
+
+fn shp_error<S: AsRef<str>>(s: S) -> FIRParsingError {
+    FIRParsingError::FIRParsing(format!("Shapefile: {}", s.as_ref()))
+}
+
+/// Whether a ring is clockwise with longitude as x, which is how shapefiles draw outer rings.
+/// This is worked out from the area and not taken from FIRBoundaries.dat, so holes and
+/// outer rings from other programs are told apart correctly.
+fn is_clockwise(ring: &[Coord]) -> bool {
+    geodesy::signed_area(ring) < 0.0
+}
+
+/// The ring of a FIR as shapefile points: clockwise and closed.
+fn shape_ring(corners: &[Point]) -> Vec<(f64, f64)> {
+    let mut ring = open_ring(corners);
+    if !is_clockwise(&geodesy::ring_coords(&ring)) {
+        ring.reverse();
+    }
+    ring.iter()
+        .chain(ring.first())
+        .map(|p| (p.lon.to_f64().unwrap(), p.lat.to_f64().unwrap()))
+        .collect()
+}
+
+/// Bounding box as (xmin, ymin, xmax, ymax).
+fn bbox<'a, I: Iterator<Item = &'a (f64, f64)>>(points: I) -> [f64; 4] {
+    points.fold(
+        [f64::MAX, f64::MAX, f64::MIN, f64::MIN],
+        |[xmin, ymin, xmax, ymax], (x, y)| [xmin.min(*x), ymin.min(*y), xmax.max(*x), ymax.max(*y)],
+    )
+}
+
+fn header(file_len: usize, bbox: [f64; 4]) -> Vec<u8> {
+    let mut h = Vec::with_capacity(HEADER_LEN);
+    h.extend(FILE_CODE.to_be_bytes());
+    h.extend([0; 20]);
+    h.extend((file_len as i32 / 2).to_be_bytes());
+    h.extend(VERSION.to_le_bytes());
+    h.extend(POLYGON.to_le_bytes());
+    bbox.iter().for_each(|v| h.extend(v.to_le_bytes()));
+    h.extend([0; 32]); // z and m ranges
+    h
+}
+
+/// Today's date as (years since 1900, month, day), for the .dbf header.
+fn today() -> (u8, u8, u8) {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86400)
+        .unwrap_or_default() as i64;
+    // days to a civil date, from Howard Hinnant's date algorithms
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    ((year - 1900) as u8, month as u8, day as u8)
+}
+
+fn dbf(firs: &[FIRBoundary]) -> Vec<u8> {
+    let header_len = 32 + 32 * FIELDS.len() + 1;
+    let record_len = 1 + FIELDS.iter().map(|f| f.2 as usize).sum::<usize>();
+    let (year, month, day) = today();
+    let mut d = vec![3, year, month, day];
+    d.extend((firs.len() as u32).to_le_bytes());
+    d.extend((header_len as u16).to_le_bytes());
+    d.extend((record_len as u16).to_le_bytes());
+    d.extend([0; 20]);
+    for Field(name, kind, length, decimals) in &FIELDS {
+        let mut descriptor = [0; 32];
+        descriptor[..name.len()].copy_from_slice(name.as_bytes());
+        descriptor[11] = *kind;
+        descriptor[16] = *length;
+        descriptor[17] = *decimals;
+        d.extend(descriptor);
+    }
+    d.push(0x0D);
+    let logical = |b| match b {
+        true => "T",
+        false => "F",
+    };
+    for fir in firs {
+        d.push(b' ');
+        let values = [
+            format!("{:<16}", fir.icao),
+            logical(fir.is_oseanic).to_string(),
+            logical(fir.is_extension).to_string(),
+            format!("{:>12.6}", fir.lable.lat.to_f64().unwrap()),
+            format!("{:>12.6}", fir.lable.lon.to_f64().unwrap()),
+        ];
+        for (value, Field(_, _, length, _)) in values.iter().zip(&FIELDS) {
+            let mut bytes = value.as_bytes().to_vec();
+            bytes.resize(*length as usize, b' ');
+            d.extend(bytes);
+        }
+    }
+    d.push(0x1A);
+    d
+}
+
+/// Writes the FIRs as a shapefile, `p` being the .shp file. The .shx, .dbf and .prj files are
+/// written next to it. Every FIR and extention is a record of its own, with its ring clockwise.
+pub(crate) fn write_file<P: AsRef<Path>>(firs: &[FIRBoundary], p: P) -> io::Result<()> {
+    let p = p.as_ref();
+    let rings: Vec<Vec<(f64, f64)>> = firs
+        .iter()
+        .map(|fir| shape_ring(&fir.boundary_corners))
+        .collect();
+    let mut records = Vec::new();
+    let mut index = Vec::new();
+    for (n, ring) in rings.iter().enumerate() {
+        let mut content = Vec::new();
+        content.extend(POLYGON.to_le_bytes());
+        bbox(ring.iter())
+            .iter()
+            .for_each(|v| content.extend(v.to_le_bytes()));
+        content.extend(1i32.to_le_bytes()); // parts
+        content.extend((ring.len() as i32).to_le_bytes());
+        content.extend(0i32.to_le_bytes()); // the part starts at the first point
+        for (x, y) in ring {
+            content.extend(x.to_le_bytes());
+            content.extend(y.to_le_bytes());
+        }
+        index.extend(((HEADER_LEN + records.len()) as i32 / 2).to_be_bytes());
+        index.extend((content.len() as i32 / 2).to_be_bytes());
+        records.extend((n as i32 + 1).to_be_bytes());
+        records.extend((content.len() as i32 / 2).to_be_bytes());
+        records.extend(content);
+    }
+    let bbox = match firs.is_empty() {
+        true => [0.0; 4],
+        false => bbox(rings.iter().flatten()),
+    };
+    let mut shp = header(HEADER_LEN + records.len(), bbox);
+    shp.extend(records);
+    let mut shx = header(HEADER_LEN + index.len(), bbox);
+    shx.extend(index);
+    fs::write(p, shp)?;
+    fs::write(p.with_extension("shx"), shx)?;
+    fs::write(p.with_extension("dbf"), dbf(firs))?;
+    fs::write(p.with_extension("prj"), PRJ)
+}
+
+/// Reads numbers out of a byte buffer, failing when it runs out.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take<const N: usize>(&mut self) -> FIRResult<[u8; N]> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + N)
+            .ok_or_else(|| shp_error("the file ends in the middle of a record"))?;
+        self.pos += N;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn be_i32(&mut self) -> FIRResult<i32> {
+        self.take().map(i32::from_be_bytes)
+    }
+
+    fn le_i32(&mut self) -> FIRResult<i32> {
+        self.take().map(i32::from_le_bytes)
+    }
+
+    fn le_f64(&mut self) -> FIRResult<f64> {
+        self.take().map(f64::from_le_bytes)
+    }
+}
+
+/// A length or number of parts or points, which has to be positive.
+fn count(n: i32) -> FIRResult<usize> {
+    usize::try_from(n).map_err(|_| shp_error(format!("negative length or count: {}", n)))
+}
+
+/// Reads the rings of every record of a .shp file. Null shapes have no rings.
+fn parse_shp(bytes: &[u8]) -> FIRResult<Vec<Vec<Vec<Coord>>>> {
+    let mut r = Reader { bytes, pos: 0 };
+    if r.be_i32()? != FILE_CODE {
+        return Err(shp_error("not a .shp file"));
+    }
+    r.pos = 32;
+    match r.le_i32()? {
+        POLYGON | POLYGON_Z | POLYGON_M => (),
+        n => return Err(shp_error(format!("shape type {} is not a polygon", n))),
+    }
+    r.pos = HEADER_LEN;
+    let mut shapes = Vec::new();
+    while r.pos < bytes.len() {
+        let _number = r.be_i32()?;
+        let length = count(r.be_i32()?)? * 2;
+        let next = r.pos + length;
+        let shape_type = r.le_i32()?;
+        if shape_type == NULL_SHAPE {
+            shapes.push(Vec::new());
+            r.pos = next;
+            continue;
+        }
+        r.pos += 32; // bounding box
+        let parts = count(r.le_i32()?)?;
+        let points = count(r.le_i32()?)?;
+        let mut starts = (0..parts)
+            .map(|_| r.le_i32().and_then(count))
+            .collect::<FIRResult<Vec<_>>>()?;
+        starts.push(points);
+        // z and m values come after the points and are left out
+        let coords = (0..points)
+            .map(|_| Ok((r.le_f64()?, r.le_f64()?)))
+            .map(|xy: FIRResult<(f64, f64)>| xy.map(|(lon, lat)| Coord { lat, lon }))
+            .collect::<FIRResult<Vec<_>>>()?;
+        shapes.push(
+            starts
+                .windows(2)
+                .map(|w| coords.get(w[0]..w[1]).map(<[Coord]>::to_vec))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| shp_error("a part starts outside the record"))?,
+        );
+        r.pos = next;
+    }
+    Ok(shapes)
+}
+
+/// Reads the rows of a .dbf file as column name to value, with the values trimmed.
+fn parse_dbf(bytes: &[u8]) -> FIRResult<Vec<IndexMap<String, String>>> {
+    let header = |range: Range<usize>| {
+        bytes
+            .get(range)
+            .ok_or_else(|| shp_error("the .dbf file ends in the middle of its header"))
+    };
+    let records = u32::from_le_bytes(header(4..8)?.try_into().unwrap()) as usize;
+    let header_len = u16::from_le_bytes(header(8..10)?.try_into().unwrap()) as usize;
+    let record_len = u16::from_le_bytes(header(10..12)?.try_into().unwrap()) as usize;
+    let mut fields = Vec::new();
+    let mut pos = 32;
+    while pos + 32 <= header_len && bytes.get(pos) != Some(&0x0D) {
+        let descriptor = header(pos..pos + 32)?;
+        let name = descriptor[..11].split(|b| *b == 0).next().unwrap();
+        fields.push((
+            String::from_utf8_lossy(name).to_uppercase(),
+            descriptor[16] as usize,
+        ));
+        pos += 32;
+    }
+    (0..records)
+        .map(|n| {
+            let start = header_len + n * record_len;
+            let record = bytes
+                .get(start..start + record_len)
+                .ok_or_else(|| shp_error("the .dbf file ends in the middle of a record"))?;
+            let mut pos = 1; // deleted flag
+            let mut row = IndexMap::new();
+            for (name, length) in &fields {
+                let value = record
+                    .get(pos..pos + length)
+                    .ok_or_else(|| shp_error("a .dbf record is shorter than its columns"))?;
+                row.insert(
+                    name.clone(),
+                    String::from_utf8_lossy(value).trim().to_string(),
+                );
+                pos += length;
+            }
+            Ok(row)
+        })
+        .collect()
+}
+
+fn flag(value: Option<&String>) -> bool {
+    matches!(
+        value.map(|v| v.to_uppercase()).as_deref(),
+        Some("T") | Some("Y") | Some("1") | Some("TRUE")
+    )
+}
+
+/// Reads a shapefile, `p` being the .shp file with the .dbf file next to it.
+/// Every outer ring becomes a FIR, where the rings after the first of a record are read as
+/// extentions. Holes are left out. Records without a lable get it at the pole of
+/// inaccessibility of the largest part of the FIR.
+pub(crate) fn read_file<P: AsRef<Path>>(p: P) -> FIRResult<Vec<FIRBoundary>> {
+    let p = p.as_ref();
+    parse(&fs::read(p)?, &fs::read(p.with_extension("dbf"))?)
+}
+
+fn parse(shp: &[u8], dbf: &[u8]) -> FIRResult<Vec<FIRBoundary>> {
+    let shapes = parse_shp(shp)?;
+    let rows = parse_dbf(dbf)?;
+    if shapes.len() != rows.len() {
+        return Err(shp_error(format!(
+            "the .shp file has {} records, but the .dbf file has {}",
+            shapes.len(),
+            rows.len()
+        )));
+    }
+    let mut firs = Vec::new();
+    let mut lables = Vec::new();
+    for (n, (rings, row)) in shapes.into_iter().zip(rows).enumerate() {
+        let icao = match row.get("ICAO") {
+            Some(icao) if !icao.is_empty() => icao.clone(),
+            _ => return Err(shp_error(format!("record {} has no ICAO", n + 1))),
+        };
+        let lable = match (row.get("LABLE_LAT"), row.get("LABLE_LON")) {
+            (Some(lat), Some(lon)) => match (lat.parse(), lon.parse()) {
+                (Ok(lat), Ok(lon)) => Some(Point::new(
+                    Decimal::normalize(&lat),
+                    Decimal::normalize(&lon),
+                )?),
+                _ => None,
+            },
+            _ => None,
+        };
+        let outer = rings
+            .iter()
+            .filter(|ring| is_clockwise(ring))
+            .map(|ring| geodesy::boundary_corners(ring))
+            .filter(|corners| corners.len() >= 3);
+        for (part, corners) in outer.enumerate() {
+            let mut fir = FIRBoundary {
+                id: firs.len(),
+                icao: icao.clone(),
+                is_oseanic: flag(row.get("OCEANIC")),
+                is_extension: part != 0 || flag(row.get("EXTENSION")),
+                min_lat: Default::default(),
+                min_lon: Default::default(),
+                max_lat: Default::default(),
+                max_lon: Default::default(),
+                lable: corners[0].clone(),
+                boundary_corners: corners,
+            };
+            fir.update_bounds();
+            firs.push(fir);
+            lables.push(lable.clone());
+        }
+    }
+
+    for group in with_extentions(&firs) {
+        let fallback = match &lables[group[0]] {
+            Some(p) => p.clone(),
+            None => lable::group_lable(&firs, &group),
+        };
+        for n in group {
+            firs[n].lable = lables[n].clone().unwrap_or_else(|| fallback.clone());
+        }
+    }
+    Ok(firs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fir_boundaries::{fir, polygon_or_hole, Fill};
+
+    #[test]
+    fn round_trip() {
+        let firs = vec![
+            fir("EZZZ", false, &[(0, 0), (2, 0), (2, 2), (0, 2)]),
+            fir("EZZZ", true, &[(2, 2), (4, 2), (4, 4), (2, 4)]),
+            fir("LZZZ", false, &[(0, 2), (2, 2), (2, 4), (0, 4)]),
+        ];
+        assert!(firs
+            .iter()
+            .all(|f| polygon_or_hole(&f.boundary_corners) == Fill::Polygon));
+        let p = std::env::temp_dir().join(format!("fir-test-{}.shp", std::process::id()));
+        write_file(&firs, &p).unwrap();
+        let read = read_file(&p).unwrap();
+        for e in ["shp", "shx", "dbf", "prj"] {
+            fs::remove_file(p.with_extension(e)).unwrap();
+        }
+        assert_eq!(read.len(), 3);
+        for (read, fir) in read.iter().zip(&firs) {
+            assert_eq!(read.icao, fir.icao);
+            assert_eq!(read.is_extension, fir.is_extension);
+            assert_eq!(read.lable, fir.lable);
+            assert_eq!(read.boundary_corners, fir.boundary_corners);
+        }
+    }
+
+    #[test]
+    fn multipart_with_hole() {
+        let ring = |points: &[(f64, f64)]| -> Vec<Coord> {
+            points
+                .iter()
+                .map(|(lat, lon)| Coord {
+                    lat: *lat,
+                    lon: *lon,
+                })
+                .collect()
+        };
+        // clockwise outer rings with a counterclockwise hole in the first
+        let outer = ring(&[(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0), (0.0, 0.0)]);
+        let hole = ring(&[(1.0, 1.0), (1.0, 2.0), (2.0, 2.0), (2.0, 1.0), (1.0, 1.0)]);
+        let second = ring(&[(5.0, 0.0), (6.0, 0.0), (6.0, 1.0), (5.0, 0.0)]);
+        assert!(is_clockwise(&outer) && !is_clockwise(&hole) && is_clockwise(&second));
+
+        let mut content = Vec::new();
+        content.extend(POLYGON.to_le_bytes());
+        content.extend([0; 32]);
+        content.extend(3i32.to_le_bytes());
+        content.extend(14i32.to_le_bytes());
+        for start in [0i32, 5, 10] {
+            content.extend(start.to_le_bytes());
+        }
+        for c in outer.iter().chain(&hole).chain(&second) {
+            content.extend(c.lon.to_le_bytes());
+            content.extend(c.lat.to_le_bytes());
+        }
+        let mut shp = header(HEADER_LEN + 8 + content.len(), [0.0; 4]);
+        shp.extend(1i32.to_be_bytes());
+        shp.extend((content.len() as i32 / 2).to_be_bytes());
+        shp.extend(content);
+        assert_eq!(parse_shp(&shp).unwrap()[0].len(), 3);
+
+        let dbf = dbf(&[fir("EZZZ", false, &[(0, 0), (1, 0), (1, 1)])]);
+        assert_eq!(parse_dbf(&dbf).unwrap()[0]["LABLE_LAT"], "1.000000");
+        let read = parse(&shp, &dbf).unwrap();
+        assert_eq!(read.len(), 2);
+        assert!(!read[0].is_extension && read[1].is_extension);
+        assert_eq!(read[0].boundary_corners.len(), 4);
+        assert_eq!(read[1].boundary_corners.len(), 3);
+        assert!(read
+            .iter()
+            .all(|f| polygon_or_hole(&f.boundary_corners) == Fill::Polygon));
+    }
+
+    #[test]
+    fn truncated_files() {
+        let mut shp = header(HEADER_LEN + 12, [0.0; 4]);
+        shp.extend(1i32.to_be_bytes());
+        shp.extend(i32::MIN.to_be_bytes());
+        shp.extend(POLYGON.to_le_bytes());
+        assert!(parse_shp(&shp)
+            .unwrap_err()
+            .to_string()
+            .contains("negative length or count"));
+
+        let dbf = dbf(&[fir("EZZZ", false, &[(0, 0), (1, 0), (1, 1)])]);
+        for len in [0, 10, 40, dbf.len() - 2] {
+            assert!(parse_dbf(&dbf[..len]).is_err(), "{}", len);
+        }
+    }
+}
@@ -0,0 +1,334 @@
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use crate::{
+    fir_boundaries::{polygon_or_hole, FIRBoundary},
+    geodesy::{self, Coord},
+    topology::{open_ring, Topology},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Algorithm {
+    DouglasPeucker,
+    Visvalingam,
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "douglas-peucker" | "dp" => Ok(Self::DouglasPeucker),
+            "visvalingam" | "vw" => Ok(Self::Visvalingam),
+            s => Err(format!(
+                "unknown algorithm: {}, expected one of: douglas-peucker, visvalingam",
+                s
+            )),
+        }
+    }
+}
+
+/// What happened to one FIRBoundary during simplification.
+#[derive(Debug, Clone)]
+pub(crate) struct SimplifyReport {
+    pub(crate) icao: String,
+    pub(crate) is_extension: bool,
+    pub(crate) vertices_before: usize,
+    pub(crate) vertices_after: usize,
+    /// Largest distance in nautical miles from a removed point to the simplified boundary.
+    pub(crate) max_deviation_nm: f64,
+}
+
+impl Display for SimplifyReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:<8} {:<9} {:>7} -> {:>7} ({:>5.1}% removed), max deviation: {:.3} NM",
+            self.icao,
+            match self.is_extension {
+                true => "extension",
+                false => "",
+            },
+            self.vertices_before,
+            self.vertices_after,
+            100.0 * (self.vertices_before - self.vertices_after) as f64
+                / self.vertices_before as f64,
+            self.max_deviation_nm,
+        )
+    }
+}
+
+/// Area in square nautical miles of the triangle `a`, `b`, `c`.
+fn triangle_area(a: Coord, b: Coord, c: Coord) -> f64 {
+    let (bx, by) = geodesy::project_nm(a, b);
+    let (cx, cy) = geodesy::project_nm(a, c);
+    (bx * cy - by * cx).abs() / 2.0
+}
+
+fn douglas_peucker(arc: &[Coord], tolerance: f64, keep: &mut [bool]) {
+    let mut stack = vec![(0, arc.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        keep[first] = true;
+        keep[last] = true;
+        let farthest = (first + 1..last)
+            .map(|i| {
+                (
+                    i,
+                    geodesy::segment_distance_nm(arc[i], arc[first], arc[last]),
+                )
+            })
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        if let Some((i, d)) = farthest {
+            if d > tolerance {
+                stack.push((first, i));
+                stack.push((i, last));
+            }
+        }
+    }
+}
+
+#[derive(PartialEq)]
+struct Candidate {
+    area: f64,
+    index: usize,
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    // reversed so the BinaryHeap pops the smallest area first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .area
+            .partial_cmp(&self.area)
+            .unwrap()
+            .then(other.index.cmp(&self.index))
+    }
+}
+
+fn visvalingam(arc: &[Coord], tolerance: f64, keep: &mut [bool]) {
+    let n = arc.len();
+    let threshold = tolerance * tolerance;
+    let mut prev: Vec<usize> = (0..n).map(|i| i.saturating_sub(1)).collect();
+    let mut next: Vec<usize> = (0..n).map(|i| (i + 1).min(n - 1)).collect();
+    let mut areas = vec![f64::INFINITY; n];
+    let mut heap = BinaryHeap::new();
+    for i in 1..n - 1 {
+        areas[i] = triangle_area(arc[i - 1], arc[i], arc[i + 1]);
+        heap.push(Candidate {
+            area: areas[i],
+            index: i,
+        });
+    }
+    keep.iter_mut().for_each(|k| *k = true);
+    while let Some(Candidate { area, index }) = heap.pop() {
+        if !keep[index] || area != areas[index] {
+            continue; // outdated entry
+        }
+        if area >= threshold {
+            break;
+        }
+        keep[index] = false;
+        let (p, q) = (prev[index], next[index]);
+        next[p] = q;
+        prev[q] = p;
+        for i in [p, q] {
+            if i != 0 && i != n - 1 {
+                // the area of a point may never drop below one already removed, as in Visvalingam and Whyatt
+                areas[i] = triangle_area(arc[prev[i]], arc[i], arc[next[i]]).max(area);
+                heap.push(Candidate {
+                    area: areas[i],
+                    index: i,
+                });
+            }
+        }
+    }
+}
+
+/// Simplifies one arc, returning which points to keep and the largest deviation of a removed point.
+fn simplify_arc(arc: &[Coord], tolerance: f64, algorithm: Algorithm) -> (Vec<bool>, f64) {
+    let mut keep = vec![false; arc.len()];
+    if arc.len() <= 2 {
+        return (vec![true; arc.len()], 0.0);
+    }
+    // A closed arc has the same start and end point, split it in the point farthest away from the start.
+    let split = match arc[0] == arc[arc.len() - 1] {
+        true => (1..arc.len() - 1)
+            .max_by(|a, b| {
+                geodesy::distance(arc[0], arc[*a])
+                    .partial_cmp(&geodesy::distance(arc[0], arc[*b]))
+                    .unwrap()
+            })
+            .unwrap(),
+        false => arc.len() - 1,
+    };
+    for (start, end) in [(0, split), (split, arc.len() - 1)] {
+        if end <= start {
+            continue;
+        }
+        let part = &arc[start..=end];
+        let mut part_keep = vec![false; part.len()];
+        match algorithm {
+            Algorithm::DouglasPeucker => douglas_peucker(part, tolerance, &mut part_keep),
+            Algorithm::Visvalingam => visvalingam(part, tolerance, &mut part_keep),
+        }
+        keep[start..=end]
+            .iter_mut()
+            .zip(part_keep)
+            .for_each(|(k, p)| *k |= p);
+    }
+    let deviation = max_deviation(arc, &keep);
+    (keep, deviation)
+}
+
+fn max_deviation(arc: &[Coord], keep: &[bool]) -> f64 {
+    let kept: Vec<usize> = (0..arc.len()).filter(|i| keep[*i]).collect();
+    kept.windows(2)
+        .flat_map(|w| (w[0] + 1..w[1]).map(move |i| (i, w[0], w[1])))
+        .map(|(i, a, b)| geodesy::segment_distance_nm(arc[i], arc[a], arc[b]))
+        .fold(0.0, f64::max)
+}
+
+/// Simplifies all boundaries with the given tolerance in nautical miles.
+///
+/// Borders shared between FIRs are simplified once and used for both FIRs, so neighbours still meet exactly.
+/// If a boundary would collapse or change draw direction, all of its borders are kept as they were.
+pub(crate) fn simplify(
+    firs: &mut [FIRBoundary],
+    tolerance: f64,
+    algorithm: Algorithm,
+) -> Vec<SimplifyReport> {
+    let topology = Topology::new(firs.iter().map(|f| f.boundary_corners.as_slice()));
+    let simplified: Vec<(Vec<bool>, f64)> = topology
+        .arcs
+        .iter()
+        .map(|arc| {
+            let coords: Vec<Coord> = arc.iter().map(Coord::from).collect();
+            simplify_arc(&coords, tolerance, algorithm)
+        })
+        .collect();
+    let mut frozen = vec![false; topology.arcs.len()];
+    let arcs_for = |frozen: &[bool]| -> Vec<Vec<_>> {
+        topology
+            .arcs
+            .iter()
+            .zip(&simplified)
+            .zip(frozen)
+            .map(|((arc, (keep, _)), frozen)| {
+                arc.iter()
+                    .zip(keep)
+                    .filter(|(_, k)| **k || *frozen)
+                    .map(|(p, _)| p.clone())
+                    .collect()
+            })
+            .collect()
+    };
+
+    let arcs = arcs_for(&frozen);
+    for n in 0..firs.len() {
+        let ring = topology.ring(n, &arcs);
+        let original = topology.ring(n, &topology.arcs);
+        let collapsed =
+            ring.len() < 3 || geodesy::area(&geodesy::ring_coords(&ring)) < f64::EPSILON;
+        if collapsed || polygon_or_hole(&ring) != polygon_or_hole(&original) {
+            topology.rings[n]
+                .iter()
+                .for_each(|r| frozen[r.index] = true);
+        }
+    }
+    let arcs = arcs_for(&frozen);
+
+    firs.iter_mut()
+        .enumerate()
+        .map(|(n, fir)| {
+            let before = open_ring(&fir.boundary_corners).len();
+            fir.boundary_corners = topology.ring(n, &arcs);
+            fir.update_bounds();
+            SimplifyReport {
+                icao: fir.icao.clone(),
+                is_extension: fir.is_extension,
+                vertices_before: before,
+                vertices_after: fir.boundary_corners.len(),
+                max_deviation_nm: topology.rings[n]
+                    .iter()
+                    .filter(|r| !frozen[r.index])
+                    .map(|r| simplified[r.index].1)
+                    .fold(0.0, f64::max),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::*;
+    use crate::fir_boundaries::Point;
+
+    fn fir(icao: &str, points: Vec<(Decimal, Decimal)>) -> FIRBoundary {
+        let mut fir = FIRBoundary {
+            id: 0,
+            icao: icao.to_string(),
+            is_oseanic: false,
+            is_extension: false,
+            min_lat: Decimal::ZERO,
+            min_lon: Decimal::ZERO,
+            max_lat: Decimal::ZERO,
+            max_lon: Decimal::ZERO,
+            lable: Point::new(Decimal::ZERO, Decimal::ZERO).unwrap(),
+            boundary_corners: points
+                .into_iter()
+                .map(|(lat, lon)| Point::new(lat, lon).unwrap())
+                .collect(),
+        };
+        fir.update_bounds();
+        fir
+    }
+
+    #[test]
+    fn shared_border_stays_shared() {
+        // a slightly wiggly border along longitude 10
+        let border: Vec<(Decimal, Decimal)> = (0..100)
+            .map(|i| {
+                (
+                    Decimal::new(5800 + i, 2),
+                    Decimal::new(100_000 + (i % 3), 4),
+                )
+            })
+            .collect();
+        let mut west = vec![(58.into(), 5.into()), (Decimal::new(5899, 2), 5.into())];
+        west.extend(border.iter().rev().cloned());
+        let mut east = border.clone();
+        east.extend(vec![
+            (Decimal::new(5899, 2), 15.into()),
+            (58.into(), 15.into()),
+        ]);
+        let mut firs = vec![fir("WEST", west), fir("EAST", east)];
+
+        let report = simplify(&mut firs, 0.1, Algorithm::DouglasPeucker);
+
+        assert!(report.iter().all(|r| r.vertices_after < r.vertices_before));
+        assert!(report.iter().all(|r| r.max_deviation_nm <= 0.1));
+        let on_border = |fir: &FIRBoundary| -> Vec<Point> {
+            fir.boundary_corners
+                .iter()
+                .filter(|p| p.lon > 9.into() && p.lon < 11.into())
+                .cloned()
+                .collect()
+        };
+        let (west_border, east_border) = (on_border(&firs[0]), on_border(&firs[1]));
+        assert_eq!(west_border.len(), east_border.len());
+        assert!(west_border.iter().all(|p| east_border.contains(p)));
+    }
+}
@@ -0,0 +1,322 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display},
+};
+
+use crate::{
+    fir_boundaries::{FIRBoundary, Point},
+    geodesy::{self, Coord, METRES_PER_NM},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SnapKind {
+    /// The point was moved onto a point of the neighbour.
+    Vertex,
+    /// The point was moved onto an edge of the neighbour, and added to the neighbour as well.
+    Edge,
+    /// The point of the neighbour was added to an edge of this FIR.
+    Inserted,
+}
+
+/// One change made to a FIR while snapping borders.
+#[derive(Debug, Clone)]
+pub(crate) struct Snap {
+    pub(crate) icao: String,
+    pub(crate) neighbour: String,
+    pub(crate) kind: SnapKind,
+    pub(crate) original: Point,
+    pub(crate) snapped: Point,
+    pub(crate) distance_m: f64,
+}
+
+impl Display for Snap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            SnapKind::Vertex => write!(
+                f,
+                "{}: moved {} to {}, a point of {} ({:.1} m)",
+                self.icao, self.original, self.snapped, self.neighbour, self.distance_m
+            ),
+            SnapKind::Edge => write!(
+                f,
+                "{}: moved {} to {}, on the border of {} ({:.1} m)",
+                self.icao, self.original, self.snapped, self.neighbour, self.distance_m
+            ),
+            SnapKind::Inserted => write!(
+                f,
+                "{}: added {} from {} ({:.1} m from the border)",
+                self.icao, self.snapped, self.neighbour, self.distance_m
+            ),
+        }
+    }
+}
+
+/// Points and edges of a ring, bucketed by latitude so nearby ones are quick to find.
+struct RingIndex {
+    band: f64,
+    vertices: HashMap<i64, Vec<usize>>,
+    edges: HashMap<i64, Vec<usize>>,
+}
+
+impl RingIndex {
+    fn new(ring: &[Coord], band: f64) -> Self {
+        let mut vertices: HashMap<i64, Vec<usize>> = HashMap::new();
+        let mut edges: HashMap<i64, Vec<usize>> = HashMap::new();
+        for (n, c) in ring.iter().enumerate() {
+            vertices
+                .entry((c.lat / band).floor() as i64)
+                .or_default()
+                .push(n);
+            let next = ring[(n + 1) % ring.len()];
+            let (low, high) = (c.lat.min(next.lat), c.lat.max(next.lat));
+            for b in (low / band).floor() as i64..=(high / band).floor() as i64 {
+                edges.entry(b).or_default().push(n);
+            }
+        }
+        Self {
+            band,
+            vertices,
+            edges,
+        }
+    }
+
+    fn near<'a>(
+        &'a self,
+        map: &'a HashMap<i64, Vec<usize>>,
+        lat: f64,
+    ) -> impl Iterator<Item = usize> + 'a {
+        let b = (lat / self.band).floor() as i64;
+        (b - 1..=b + 1).flat_map(move |b| map.get(&b).into_iter().flatten().copied())
+    }
+
+    /// Closest vertex within `tolerance` nautical miles.
+    fn vertex(&self, ring: &[Coord], p: Coord, tolerance: f64) -> Option<(usize, f64)> {
+        self.near(&self.vertices, p.lat)
+            .map(|n| (n, distance_nm(p, ring[n])))
+            .filter(|(_, d)| *d <= tolerance)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+    }
+
+    /// Closest point on an edge within `tolerance` nautical miles, as (edge, point, distance).
+    fn edge(&self, ring: &[Coord], p: Coord, tolerance: f64) -> Option<(usize, Coord, f64)> {
+        let mut edges: Vec<usize> = self.near(&self.edges, p.lat).collect();
+        edges.sort_unstable();
+        edges.dedup();
+        edges
+            .into_iter()
+            .map(|n| {
+                let (c, d) = geodesy::closest_on_segment(p, ring[n], ring[(n + 1) % ring.len()]);
+                (n, c, d)
+            })
+            .filter(|(_, _, d)| *d <= tolerance)
+            .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap())
+    }
+}
+
+fn distance_nm(a: Coord, b: Coord) -> f64 {
+    let (x, y) = geodesy::project_nm(a, b);
+    (x * x + y * y).sqrt()
+}
+
+/// Adds points to the edges of a ring. `insertions` holds the index of the edge start and the point.
+fn insert_points(ring: &mut Vec<Point>, mut insertions: Vec<(usize, Point)>) {
+    if insertions.is_empty() {
+        return;
+    }
+    insertions.sort_by(|a, b| {
+        a.0.cmp(&b.0).then_with(|| {
+            let start = Coord::from(&ring[a.0]);
+            distance_nm(start, Coord::from(&a.1))
+                .partial_cmp(&distance_nm(start, Coord::from(&b.1)))
+                .unwrap()
+        })
+    });
+    let mut result = Vec::with_capacity(ring.len() + insertions.len());
+    let mut insertions = insertions.into_iter().peekable();
+    for (n, p) in ring.iter().enumerate() {
+        result.push(p.clone());
+        while let Some((_, point)) = insertions.next_if(|(edge, _)| *edge == n) {
+            result.push(point);
+        }
+    }
+    *ring = result;
+}
+
+fn bbox(ring: &[Coord]) -> (f64, f64, f64, f64) {
+    ring.iter().fold(
+        (
+            f64::INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NEG_INFINITY,
+        ),
+        |(a, b, c, d), p| (a.min(p.lat), b.min(p.lon), c.max(p.lat), d.max(p.lon)),
+    )
+}
+
+fn close_to(a: &[Coord], b: &[Coord], margin: f64) -> bool {
+    let (a, b) = (bbox(a), bbox(b));
+    let lat = a.0 <= b.2 + margin && b.0 <= a.2 + margin;
+    let lon = [-360.0, 0.0, 360.0]
+        .iter()
+        .any(|shift| a.1 <= b.3 + shift + margin && b.1 + shift <= a.3 + margin);
+    lat && lon
+}
+
+/// A change to a ring: what was done, the point before and after, and how far it moved in nautical miles.
+type Change = (SnapKind, Point, Point, f64);
+
+/// Snaps the points of `moving` onto `reference`. Returns the changes made to both rings.
+fn snap_pair(
+    reference: &mut Vec<Point>,
+    moving: &mut Vec<Point>,
+    tolerance: f64,
+) -> (Vec<Change>, Vec<Change>) {
+    let band = (tolerance / 60.0).max(0.05);
+    let (mut reference_changes, mut moving_changes) = (Vec::new(), Vec::new());
+
+    let coords: Vec<Coord> = reference.iter().map(Coord::from).collect();
+    let index = RingIndex::new(&coords, band);
+    let shared: HashSet<Point> = reference.iter().cloned().collect();
+    let mut insertions = Vec::new();
+    for point in moving.iter_mut().filter(|p| !shared.contains(*p)) {
+        let c = Coord::from(&*point);
+        if let Some((n, d)) = index.vertex(&coords, c, tolerance) {
+            moving_changes.push((SnapKind::Vertex, point.clone(), reference[n].clone(), d));
+            *point = reference[n].clone();
+        } else if let Some((edge, closest, d)) = index.edge(&coords, c, tolerance) {
+            let snapped = closest.to_point();
+            moving_changes.push((SnapKind::Edge, point.clone(), snapped.clone(), d));
+            reference_changes.push((SnapKind::Inserted, snapped.clone(), snapped.clone(), 0.0));
+            insertions.push((edge, snapped.clone()));
+            *point = snapped;
+        }
+    }
+    insert_points(reference, insertions);
+
+    // points of the reference lying on an edge of the moving ring are added to it
+    let coords: Vec<Coord> = moving.iter().map(Coord::from).collect();
+    let index = RingIndex::new(&coords, band);
+    let shared: HashSet<Point> = moving.iter().cloned().collect();
+    let insertions: Vec<_> = reference
+        .iter()
+        .filter(|p| !shared.contains(*p))
+        .filter_map(|p| {
+            index
+                .edge(&coords, Coord::from(p), tolerance)
+                .map(|(edge, _, d)| (edge, p.clone(), d))
+        })
+        .collect();
+    for (_, p, d) in &insertions {
+        moving_changes.push((SnapKind::Inserted, p.clone(), p.clone(), *d));
+    }
+    insert_points(
+        moving,
+        insertions.into_iter().map(|(e, p, _)| (e, p)).collect(),
+    );
+
+    reference.dedup();
+    moving.dedup();
+    (reference_changes, moving_changes)
+}
+
+/// Heals borders between neighbouring FIRs by snapping points less than `tolerance` metres apart.
+///
+/// The FIR earliest in the dataset is kept as it is, and the points of the later one
+/// are moved onto its points, or onto its edges, in which case the point is added to both FIRs.
+/// Afterwards points of the earlier FIR lying on an edge of the later one are added to that edge,
+/// so both sides of a border use the same points.
+pub(crate) fn snap(firs: &mut [FIRBoundary], tolerance: f64) -> Vec<Snap> {
+    let tolerance = tolerance / METRES_PER_NM;
+    let margin = tolerance / 60.0;
+    let mut closed = Vec::with_capacity(firs.len());
+    for fir in firs.iter_mut() {
+        let corners = &mut fir.boundary_corners;
+        closed.push(corners.len() > 1 && corners[0] == corners[corners.len() - 1]);
+        if *closed.last().unwrap() {
+            corners.pop();
+        }
+    }
+
+    let mut report = Vec::new();
+    for j in 1..firs.len() {
+        for i in 0..j {
+            let (a, b) = (
+                geodesy::ring_coords(&firs[i].boundary_corners),
+                geodesy::ring_coords(&firs[j].boundary_corners),
+            );
+            if !close_to(&a, &b, margin) {
+                continue;
+            }
+            let (head, tail) = firs.split_at_mut(j);
+            let (reference, moving) = (&mut head[i], &mut tail[0]);
+            let (reference_changes, moving_changes) = snap_pair(
+                &mut reference.boundary_corners,
+                &mut moving.boundary_corners,
+                tolerance,
+            );
+            let changes = reference_changes
+                .into_iter()
+                .map(|c| (c, &reference.icao, &moving.icao))
+                .chain(
+                    moving_changes
+                        .into_iter()
+                        .map(|c| (c, &moving.icao, &reference.icao)),
+                );
+            for ((kind, original, snapped, d), icao, neighbour) in changes {
+                report.push(Snap {
+                    icao: icao.clone(),
+                    neighbour: neighbour.clone(),
+                    kind,
+                    original,
+                    snapped,
+                    distance_m: d * METRES_PER_NM,
+                });
+            }
+        }
+    }
+
+    for (fir, closed) in firs.iter_mut().zip(closed) {
+        if closed {
+            fir.boundary_corners.push(fir.boundary_corners[0].clone());
+        }
+        fir.update_bounds();
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::*;
+
+    fn point(lat: &str, lon: &str) -> Point {
+        Point::new(lat.parse().unwrap(), lon.parse().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn snaps_onto_vertex_and_edge() {
+        let mut reference = vec![
+            point("58", "5"),
+            point("62", "5"),
+            point("62", "10"),
+            point("58", "10"),
+        ];
+        // 62|10.00005 is a few metres from the corner, 60|10.00003 a few metres from the edge
+        let mut moving = vec![
+            point("58", "10"),
+            point("60", "10.00003"),
+            point("62", "10.00005"),
+            point("62", "15"),
+            point("58", "15"),
+        ];
+        let (reference_changes, moving_changes) =
+            snap_pair(&mut reference, &mut moving, 10.0 / METRES_PER_NM);
+        assert_eq!(moving_changes.len(), 2);
+        assert_eq!(reference_changes.len(), 1);
+        assert_eq!(moving[2], point("62", "10"));
+        assert_eq!(moving[1].lon, Decimal::from(10));
+        assert!(reference.contains(&moving[1]));
+    }
+}
@@ -0,0 +1,235 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    path::Path,
+};
+
+use color_eyre::eyre::{self, eyre};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fir_boundaries::{self, with_extentions, FIRBoundary},
+    geo_json::GeoJson,
+    topology::open_ring,
+    Mode,
+};
+
+pub(crate) const MANIFEST: &str = "manifest.json";
+
+/// One FIR or extention in a file of a split dataset.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ManifestEntry {
+    pub(crate) icao: String,
+    pub(crate) is_oceanic: bool,
+    pub(crate) is_extension: bool,
+}
+
+impl From<&FIRBoundary> for ManifestEntry {
+    fn from(fir: &FIRBoundary) -> Self {
+        Self {
+            icao: fir.icao.clone(),
+            is_oceanic: fir.is_oseanic,
+            is_extension: fir.is_extension,
+        }
+    }
+}
+
+/// A FIR with its extentions, in the order they are in the file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ManifestFile {
+    pub(crate) file: String,
+    pub(crate) entries: Vec<ManifestEntry>,
+}
+
+/// Lists the files of a split dataset in the order they are joined.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Manifest {
+    pub(crate) files: Vec<ManifestFile>,
+}
+
+/// Name of the file for a FIR, without extention. Oceanic FIRs sharing an ICAO with a land FIR get their own file.
+fn file_stem(fir: &FIRBoundary) -> String {
+    let icao: String = fir
+        .icao
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect();
+    match fir.is_oseanic {
+        true => format!("{}-oceanic", icao),
+        false => icao,
+    }
+}
+
+/// Writes every FIR with its extentions to a file of its own in `dir`, and a manifest keeping the order.
+/// `extention` is the file extention to use: dat, geojson, json, openair, kml, shp, wkt, wkb or topojson.
+pub(crate) fn split(firs: &[FIRBoundary], dir: &Path, extention: &str) -> eyre::Result<Manifest> {
+    fs::create_dir_all(dir)?;
+    let mut taken = HashSet::new();
+    let mut files = Vec::new();
+    for group in with_extentions(firs) {
+        let stem = file_stem(&firs[group[0]]);
+        let name = (1..)
+            .map(|n| match n {
+                1 => format!("{}.{}", stem, extention),
+                n => format!("{}-{}.{}", stem, n, extention),
+            })
+            .find(|name| !taken.contains(&name.to_lowercase()))
+            .unwrap();
+        taken.insert(name.to_lowercase());
+        let part: Vec<FIRBoundary> = group.iter().map(|n| firs[*n].clone()).collect();
+        crate::write_dataset(&part, &dir.join(&name))?;
+        files.push(ManifestFile {
+            file: name,
+            entries: part.iter().map(ManifestEntry::from).collect(),
+        });
+    }
+    let manifest = Manifest { files };
+    serde_json::to_writer_pretty(File::create(dir.join(MANIFEST))?, &manifest)?;
+    Ok(manifest)
+}
+
+/// Reads one file of a split dataset. GeoJSON rings are opened again, as they are in .dat files, before they are
+/// validated like every other type.
+fn read_part(p: &Path, mode: Mode) -> eyre::Result<Vec<FIRBoundary>> {
+    match crate::Filetype::from_path(p)? {
+        crate::Filetype::Dat
+        | crate::Filetype::OpenAir
+        | crate::Filetype::Kml
+        | crate::Filetype::Shapefile
+        | crate::Filetype::Wkt
+        | crate::Filetype::Wkb
+        | crate::Filetype::TopoJson => crate::read_dataset(p, mode),
+        crate::Filetype::GeoJson => {
+            let gj: GeoJson = serde_json::from_reader(File::open(p)?)?;
+            let mut firs = fir_boundaries::convert_from_geojson(gj);
+            for fir in firs.iter_mut() {
+                fir.boundary_corners = open_ring(&fir.boundary_corners);
+            }
+            Ok(fir_boundaries::validate(firs, mode)??)
+        }
+        crate::Filetype::FlatGeobuf => Err(eyre!("FlatGeobuf files can only be written")),
+    }
+}
+
+/// Reads the files listed in the manifest of `dir` in order, and checks them against the manifest.
+/// Every problem found is returned together, with the file it was found in.
+pub(crate) fn join(dir: &Path, mode: Mode) -> eyre::Result<Vec<FIRBoundary>> {
+    let manifest: Manifest = serde_json::from_reader(File::open(dir.join(MANIFEST))?)
+        .map_err(|e| eyre!("{}: {}", dir.join(MANIFEST).display(), e))?;
+    let mut problems = Vec::new();
+    let mut firs = Vec::new();
+    let mut seen = HashSet::new();
+    for file in &manifest.files {
+        if !seen.insert(file.file.to_lowercase()) {
+            problems.push(format!(
+                "{}: listed more than once in the manifest",
+                file.file
+            ));
+            continue;
+        }
+        let part = match read_part(&dir.join(&file.file), mode) {
+            Ok(part) => part,
+            Err(e) => {
+                problems.push(format!("{}: {}", file.file, e));
+                continue;
+            }
+        };
+        let entries: Vec<ManifestEntry> = part.iter().map(ManifestEntry::from).collect();
+        if entries != file.entries {
+            problems.push(format!(
+                "{}: the manifest lists {}, the file has {}",
+                file.file,
+                describe(&file.entries),
+                describe(&entries)
+            ));
+            continue;
+        }
+        firs.extend(part);
+    }
+
+    let mut unlisted: Vec<String> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .filter(|name| name != MANIFEST && crate::Filetype::from_path(Path::new(name)).is_ok())
+        .filter(|name| !seen.contains(&name.to_lowercase()))
+        .collect();
+    unlisted.sort();
+    for name in unlisted {
+        problems.push(format!("{}: not listed in the manifest", name));
+    }
+
+    let mut firs_seen = HashSet::new();
+    for fir in firs.iter().filter(|f| !f.is_extension) {
+        if !firs_seen.insert((fir.icao.as_str(), fir.is_oseanic)) {
+            problems.push(format!("{}: defined in more than one file", fir.icao));
+        }
+    }
+
+    match problems.is_empty() {
+        true => {
+            for (n, fir) in firs.iter_mut().enumerate() {
+                fir.id = n;
+            }
+            Ok(firs)
+        }
+        false => Err(eyre!("{}", problems.join("\n"))),
+    }
+}
+
+fn describe(entries: &[ManifestEntry]) -> String {
+    entries
+        .iter()
+        .map(|e| {
+            let mut s = e.icao.clone();
+            if e.is_oceanic {
+                s.push_str(" (oceanic)");
+            }
+            if e.is_extension {
+                s.push_str(" (extention)");
+            }
+            s
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fir_boundaries::{fir, Fill};
+
+    #[test]
+    fn split_and_join_keeps_order() {
+        let dir = std::env::temp_dir().join(format!("split-test-{}", std::process::id()));
+        let firs = vec![
+            fir("ZZZZ", false, &[(0, 0), (2, 0), (2, 2), (0, 2)]),
+            fir("ZZZZ", true, &[(2, 0), (4, 0), (4, 2), (2, 2)]),
+            fir("AAAA", false, &[(0, 2), (2, 2), (2, 4), (0, 4)]),
+        ];
+        let manifest = split(&firs, &dir, "dat").unwrap();
+        assert_eq!(manifest.files.len(), 2);
+        assert_eq!(manifest.files[0].file, "ZZZZ.dat");
+        let joined = join(&dir, Mode::Fix).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let entries: Vec<ManifestEntry> = joined.iter().map(ManifestEntry::from).collect();
+        let expected: Vec<ManifestEntry> = firs.iter().map(ManifestEntry::from).collect();
+        assert_eq!(entries, expected);
+        assert_eq!(joined[1].boundary_corners, firs[1].boundary_corners);
+    }
+
+    #[test]
+    fn geojson_parts_are_validated() {
+        let dir = std::env::temp_dir().join(format!("split-geojson-test-{}", std::process::id()));
+        let mut firs = vec![fir("ZZZZ", false, &[(0, 0), (2, 0), (2, 2), (0, 2)])];
+        firs[0].boundary_corners.reverse();
+        split(&firs, &dir, "geojson").unwrap();
+        let strict = join(&dir, Mode::Strict);
+        let fixed = join(&dir, Mode::Fix);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(strict.is_err());
+        assert_eq!(fixed.unwrap()[0].polygon_or_hole(), Fill::Polygon);
+    }
+}
@@ -0,0 +1,386 @@
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display},
+    io::{self, Write},
+    str::FromStr,
+};
+
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::{
+    csv::quote,
+    fir_boundaries::FIRBoundary,
+    geodesy::{self, Coord, METRES_PER_NM},
+};
+
+const SQUARE_METRES_PER_KM2: f64 = 1e6;
+const SQUARE_METRES_PER_NM2: f64 = METRES_PER_NM * METRES_PER_NM;
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct BoundingBox {
+    pub(crate) min_lat: f64,
+    pub(crate) min_lon: f64,
+    pub(crate) max_lat: f64,
+    pub(crate) max_lon: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct FirStats {
+    pub(crate) icao: String,
+    pub(crate) is_oceanic: bool,
+    pub(crate) is_extension: bool,
+    pub(crate) vertices: usize,
+    pub(crate) area_km2: f64,
+    pub(crate) area_nm2: f64,
+    pub(crate) perimeter_km: f64,
+    pub(crate) perimeter_nm: f64,
+    pub(crate) centroid_lat: f64,
+    pub(crate) centroid_lon: f64,
+    pub(crate) bbox: BoundingBox,
+}
+
+impl From<&FIRBoundary> for FirStats {
+    fn from(fir: &FIRBoundary) -> Self {
+        let ring = geodesy::ring_coords(&fir.boundary_corners);
+        let area = geodesy::area(&ring);
+        let perimeter = geodesy::perimeter(&ring);
+        let centroid = geodesy::centroid(&ring);
+        Self {
+            icao: fir.icao.clone(),
+            is_oceanic: fir.is_oseanic,
+            is_extension: fir.is_extension,
+            vertices: ring.len(),
+            area_km2: area / SQUARE_METRES_PER_KM2,
+            area_nm2: area / SQUARE_METRES_PER_NM2,
+            perimeter_km: perimeter / 1000.0,
+            perimeter_nm: perimeter / METRES_PER_NM,
+            centroid_lat: centroid.lat,
+            centroid_lon: centroid.lon,
+            bbox: bounding_box(&ring),
+        }
+    }
+}
+
+/// Bounding box of an unwrapped ring. If the ring crosses the antimeridian `min_lon` is larger than `max_lon`,
+/// the same way as in FIRBoundaries.dat.
+fn bounding_box(ring: &[Coord]) -> BoundingBox {
+    let (min_lat, max_lat) = ring
+        .iter()
+        .map(|c| c.lat)
+        .minmax_by(|a, b| a.partial_cmp(b).unwrap())
+        .into_option()
+        .unwrap();
+    let (min_lon, max_lon) = ring
+        .iter()
+        .map(|c| c.lon)
+        .minmax_by(|a, b| a.partial_cmp(b).unwrap())
+        .into_option()
+        .unwrap();
+    BoundingBox {
+        min_lat,
+        min_lon: geodesy::normalize_lon(min_lon),
+        max_lat,
+        max_lon: geodesy::normalize_lon(max_lon),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct DatasetStats {
+    pub(crate) firs: usize,
+    pub(crate) extensions: usize,
+    pub(crate) oceanic: usize,
+    pub(crate) vertices: usize,
+    pub(crate) area_km2: f64,
+    pub(crate) area_nm2: f64,
+    pub(crate) perimeter_km: f64,
+    pub(crate) perimeter_nm: f64,
+}
+
+impl DatasetStats {
+    pub(crate) fn new(stats: &[FirStats]) -> Self {
+        Self {
+            firs: stats.iter().filter(|s| !s.is_extension).count(),
+            extensions: stats.iter().filter(|s| s.is_extension).count(),
+            oceanic: stats
+                .iter()
+                .filter(|s| s.is_oceanic && !s.is_extension)
+                .count(),
+            vertices: stats.iter().map(|s| s.vertices).sum(),
+            area_km2: stats.iter().map(|s| s.area_km2).sum(),
+            area_nm2: stats.iter().map(|s| s.area_nm2).sum(),
+            perimeter_km: stats.iter().map(|s| s.perimeter_km).sum(),
+            perimeter_nm: stats.iter().map(|s| s.perimeter_nm).sum(),
+        }
+    }
+}
+
+pub(crate) fn fir_stats(firs: &[FIRBoundary]) -> Vec<FirStats> {
+    firs.iter().map(FirStats::from).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SortKey {
+    Icao,
+    Area,
+    Perimeter,
+    Vertices,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "icao" => Ok(Self::Icao),
+            "area" => Ok(Self::Area),
+            "perimeter" => Ok(Self::Perimeter),
+            "vertices" => Ok(Self::Vertices),
+            s => Err(format!(
+                "unknown sort key: {}, expected one of: icao, area, perimeter, vertices",
+                s
+            )),
+        }
+    }
+}
+
+impl SortKey {
+    fn compare(self, a: &FirStats, b: &FirStats) -> Ordering {
+        match self {
+            SortKey::Icao => a.icao.cmp(&b.icao),
+            SortKey::Area => a.area_km2.partial_cmp(&b.area_km2).unwrap(),
+            SortKey::Perimeter => a.perimeter_km.partial_cmp(&b.perimeter_km).unwrap(),
+            SortKey::Vertices => a.vertices.cmp(&b.vertices),
+        }
+    }
+}
+
+pub(crate) fn sort(stats: &mut [FirStats], key: SortKey, descending: bool) {
+    stats.sort_by(|a, b| match descending {
+        true => key.compare(b, a),
+        false => key.compare(a, b),
+    })
+}
+
+/// Keeps the FIRs where the ICAO starts with any of the given prefixes. An empty filter keeps everything.
+pub(crate) fn filter(stats: Vec<FirStats>, prefixes: &[String]) -> Vec<FirStats> {
+    match prefixes.is_empty() {
+        true => stats,
+        false => stats
+            .into_iter()
+            .filter(|s| prefixes.iter().any(|p| s.icao.starts_with(p.as_str())))
+            .collect(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OutputFormat {
+    Table,
+    Csv,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Self::Table),
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            s => Err(format!(
+                "unknown format: {}, expected one of: table, csv, json",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    dataset: &'a DatasetStats,
+    firs: &'a [FirStats],
+}
+
+pub(crate) const CSV_HEADER: &str = "ICAO,IsOceanic,IsExtension,Vertices,AreaKm2,AreaNm2,PerimeterKm,PerimeterNm,CentroidLat,CentroidLon,MinLat,MinLon,MaxLat,MaxLon";
+
+impl FirStats {
+    pub(crate) fn to_csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{:.3},{:.3},{:.3},{:.3},{:.6},{:.6},{},{},{},{}",
+            quote(&self.icao),
+            self.is_oceanic,
+            self.is_extension,
+            self.vertices,
+            self.area_km2,
+            self.area_nm2,
+            self.perimeter_km,
+            self.perimeter_nm,
+            self.centroid_lat,
+            self.centroid_lon,
+            self.bbox.min_lat,
+            self.bbox.min_lon,
+            self.bbox.max_lat,
+            self.bbox.max_lon,
+        )
+    }
+}
+
+impl Display for FirStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match (self.is_oceanic, self.is_extension) {
+            (true, true) => "oceanic ext",
+            (true, false) => "oceanic",
+            (false, true) => "extension",
+            (false, false) => "",
+        };
+        write!(
+            f,
+            "{:<8} {:<11} {:>7} {:>14.1} {:>14.1} {:>10.1} {:>10.1} {:>9.4} {:>10.4}  [{}, {}, {}, {}]",
+            self.icao,
+            kind,
+            self.vertices,
+            self.area_km2,
+            self.area_nm2,
+            self.perimeter_km,
+            self.perimeter_nm,
+            self.centroid_lat,
+            self.centroid_lon,
+            self.bbox.min_lat,
+            self.bbox.min_lon,
+            self.bbox.max_lat,
+            self.bbox.max_lon,
+        )
+    }
+}
+
+impl Display for DatasetStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "FIRs: {} ({} oceanic), extensions: {}, vertices: {}",
+            self.firs, self.oceanic, self.extensions, self.vertices
+        )?;
+        write!(
+            f,
+            "Total area: {:.1} km² ({:.1} NM²), total perimeter: {:.1} km ({:.1} NM)",
+            self.area_km2, self.area_nm2, self.perimeter_km, self.perimeter_nm
+        )
+    }
+}
+
+pub(crate) fn write_report<W: Write>(
+    writer: &mut W,
+    stats: &[FirStats],
+    format: OutputFormat,
+) -> io::Result<()> {
+    let dataset = DatasetStats::new(stats);
+    match format {
+        OutputFormat::Table => {
+            writeln!(
+                writer,
+                "{:<8} {:<11} {:>7} {:>14} {:>14} {:>10} {:>10} {:>9} {:>10}  bbox",
+                "ICAO",
+                "type",
+                "points",
+                "area km²",
+                "area NM²",
+                "perim km",
+                "perim NM",
+                "cent lat",
+                "cent lon"
+            )?;
+            for s in stats {
+                writeln!(writer, "{}", s)?;
+            }
+            writeln!(writer)?;
+            writeln!(writer, "{}", dataset)
+        }
+        OutputFormat::Csv => {
+            writeln!(writer, "{}", CSV_HEADER)?;
+            for s in stats {
+                writeln!(writer, "{}", s.to_csv_row())?;
+            }
+            Ok(())
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(
+                &mut *writer,
+                &Report {
+                    dataset: &dataset,
+                    firs: stats,
+                },
+            )?;
+            writeln!(writer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fir_boundaries::fir;
+
+    fn stats() -> Vec<FirStats> {
+        fir_stats(&[
+            fir("EZZZ", false, &[(0, 0), (0, 2), (2, 2), (2, 0)]),
+            fir("LZZZ", false, &[(0, 0), (0, 1), (1, 1), (1, 0)]),
+            fir("EYYY", false, &[(0, 0), (0, 3), (3, 3), (3, 0)]),
+        ])
+    }
+
+    fn icaos(stats: &[FirStats]) -> Vec<&str> {
+        stats.iter().map(|s| s.icao.as_str()).collect()
+    }
+
+    #[test]
+    fn sort_by_area() {
+        let mut stats = stats();
+        sort(&mut stats, SortKey::Area, false);
+        assert_eq!(icaos(&stats), ["LZZZ", "EZZZ", "EYYY"]);
+        sort(&mut stats, SortKey::Area, true);
+        assert_eq!(icaos(&stats), ["EYYY", "EZZZ", "LZZZ"]);
+    }
+
+    #[test]
+    fn filter_by_prefix() {
+        assert_eq!(
+            icaos(&filter(stats(), &["E".to_string()])),
+            ["EZZZ", "EYYY"]
+        );
+        assert_eq!(
+            icaos(&filter(stats(), &["EZ".to_string(), "L".to_string()])),
+            ["EZZZ", "LZZZ"]
+        );
+        assert_eq!(filter(stats(), &[]).len(), 3);
+    }
+
+    #[test]
+    fn csv_report() {
+        let mut csv = Vec::new();
+        write_report(&mut csv, &stats()[..1], OutputFormat::Csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], CSV_HEADER);
+        let row = crate::csv::fields(lines[1]);
+        assert_eq!(row.len(), CSV_HEADER.split(',').count());
+        assert_eq!(row[..4], ["EZZZ", "false", "false", "4"]);
+        assert_eq!(row[10..], ["0", "0", "2", "2"]);
+    }
+
+    #[test]
+    fn json_report() {
+        let mut json = Vec::new();
+        write_report(&mut json, &stats()[..1], OutputFormat::Json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json["dataset"]["firs"], 1);
+        assert_eq!(json["dataset"]["vertices"], 4);
+        let fir = &json["firs"][0];
+        assert_eq!(fir["icao"], "EZZZ");
+        assert_eq!(fir["is_oceanic"], false);
+        assert_eq!(fir["vertices"], 4);
+        assert_eq!(fir["bbox"]["max_lat"], 2.0);
+        assert!(fir["area_km2"].as_f64().unwrap() > 0.0);
+    }
+}
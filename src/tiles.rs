@@ -0,0 +1,554 @@
+use std::{
+    collections::BTreeMap,
+    f64::consts::PI,
+    fs::{self, File},
+    io,
+    path::Path,
+};
+
+use indexmap::IndexSet;
+use serde_json::json;
+
+use crate::{
+    fir_boundaries::{with_extentions, FIRBoundary},
+    geo_json::Properties,
+    geodesy::{self, Coord, METRES_PER_NM},
+    mbtiles::MbTiles,
+    simplify::{self, Algorithm},
+};
+
+/// Highest zoom level that can be rendered. At zoom 14 a tile is about 2.5 km wide at the equator.
+pub(crate) const MAX_ZOOM_LIMIT: u8 = 14;
+
+/// Size of a tile in tile units.
+const EXTENT: f64 = 4096.0;
+/// Tile units drawn outside a tile on every side, so lines along the tile edge are not seen.
+const BUFFER: f64 = 64.0;
+/// Boundaries are simplified so no point moves more than this many tile units, a quarter pixel at 512 pixels a tile.
+const SIMPLIFY_UNITS: f64 = 2.0;
+/// Web mercator stops here, making the world square.
+const MAX_LAT: f64 = 85.051_128_779_806_59;
+const EQUATOR_NM: f64 = 2.0 * PI * geodesy::WGS84_A / METRES_PER_NM;
+
+const FIR_LAYER: &str = "firs";
+const LABLE_LAYER: &str = "lables";
+
+const POINT: u64 = 1;
+const POLYGON: u64 = 3;
+const MOVE_TO: u32 = 1;
+const LINE_TO: u32 = 2;
+const CLOSE_PATH: u32 = 7;
+
+// Mapbox Vector Tiles are protocol buffers, see https://github.com/mapbox/vector-tile-spec/tree/master/2.1
+// Tile    { repeated Layer layers = 3; }
+// Layer   { uint32 version = 15; string name = 1; repeated Feature features = 2;
+//           repeated string keys = 3; repeated Value values = 4; uint32 extent = 5; }
+// Feature { uint64 id = 1; repeated uint32 tags = 2 [packed]; GeomType type = 3; repeated uint32 geometry = 4 [packed]; }
+// Value   { string string_value = 1; double double_value = 3; bool bool_value = 7; }
+
+fn varint(mut v: u64, out: &mut Vec<u8>) {
+    while v >= 0x80 {
+        out.push(v as u8 | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+fn field_varint(field: u64, v: u64, out: &mut Vec<u8>) {
+    varint(field << 3, out);
+    varint(v, out);
+}
+
+fn field_bytes(field: u64, bytes: &[u8], out: &mut Vec<u8>) {
+    varint(field << 3 | 2, out);
+    varint(bytes.len() as u64, out);
+    out.extend(bytes);
+}
+
+fn packed(field: u64, values: &[u32], out: &mut Vec<u8>) {
+    let mut bytes = Vec::new();
+    values.iter().for_each(|v| varint(*v as u64, &mut bytes));
+    field_bytes(field, &bytes, out);
+}
+
+fn zigzag(v: i32) -> u32 {
+    ((v << 1) ^ (v >> 31)) as u32
+}
+
+fn command(id: u32, count: usize) -> u32 {
+    id | (count as u32) << 3
+}
+
+/// An attribute value of a feature, as an encoded Value message.
+fn value(v: &serde_json::Value) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    match v {
+        serde_json::Value::String(s) => field_bytes(1, s.as_bytes(), &mut out),
+        serde_json::Value::Number(n) => {
+            varint(3 << 3 | 1, &mut out);
+            out.extend(n.as_f64()?.to_le_bytes());
+        }
+        serde_json::Value::Bool(b) => field_varint(7, *b as u64, &mut out),
+        _ => return None,
+    }
+    Some(out)
+}
+
+/// Attributes of a feature: the properties of a GeoJSON feature that are strings, numbers or booleans.
+/// The lable is a point, and has a layer of its own.
+fn attributes(fir: &FIRBoundary) -> Vec<(String, serde_json::Value)> {
+    match serde_json::to_value(Properties::from(fir)) {
+        Ok(serde_json::Value::Object(map)) => map
+            .into_iter()
+            .filter(|(_, v)| v.is_string() || v.is_number() || v.is_boolean())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// One layer of a tile, with its keys and values shared by all features.
+#[derive(Default)]
+struct Layer {
+    features: Vec<Vec<u8>>,
+    keys: IndexSet<String>,
+    values: IndexSet<Vec<u8>>,
+}
+
+impl Layer {
+    fn add(
+        &mut self,
+        id: usize,
+        typ: u64,
+        geometry: &[u32],
+        attributes: &[(String, serde_json::Value)],
+    ) {
+        let mut tags = Vec::new();
+        for (key, v) in attributes {
+            if let Some(v) = value(v) {
+                tags.push(self.keys.insert_full(key.clone()).0 as u32);
+                tags.push(self.values.insert_full(v).0 as u32);
+            }
+        }
+        let mut feature = Vec::new();
+        field_varint(1, id as u64, &mut feature);
+        packed(2, &tags, &mut feature);
+        field_varint(3, typ, &mut feature);
+        packed(4, geometry, &mut feature);
+        self.features.push(feature);
+    }
+
+    fn encode(&self, name: &str, out: &mut Vec<u8>) {
+        if self.features.is_empty() {
+            return;
+        }
+        let mut layer = Vec::new();
+        field_varint(15, 2, &mut layer);
+        field_bytes(1, name.as_bytes(), &mut layer);
+        self.features
+            .iter()
+            .for_each(|f| field_bytes(2, f, &mut layer));
+        self.keys
+            .iter()
+            .for_each(|k| field_bytes(3, k.as_bytes(), &mut layer));
+        self.values
+            .iter()
+            .for_each(|v| field_bytes(4, v, &mut layer));
+        field_varint(5, EXTENT as u64, &mut layer);
+        field_bytes(3, &layer, out);
+    }
+}
+
+#[derive(Default)]
+struct Tile {
+    firs: Layer,
+    lables: Layer,
+}
+
+impl Tile {
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.firs.encode(FIR_LAYER, &mut out);
+        self.lables.encode(LABLE_LAYER, &mut out);
+        out
+    }
+}
+
+/// Web mercator position in tile units, with the world `size` units wide and (0, 0) in the north west.
+fn project(c: &Coord, size: f64) -> [f64; 2] {
+    let lat = c.lat.clamp(-MAX_LAT, MAX_LAT).to_radians();
+    [
+        (c.lon + 180.0) / 360.0 * size,
+        (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI) / 2.0 * size,
+    ]
+}
+
+/// Cuts away the part of a ring outside `min..=max` along `axis`, one side at a time (Sutherland-Hodgman).
+fn clip(ring: &[[f64; 2]], axis: usize, min: f64, max: f64) -> Vec<[f64; 2]> {
+    let side = |ring: &[[f64; 2]], inside: &dyn Fn(f64) -> bool, edge: f64| {
+        let mut out = Vec::with_capacity(ring.len());
+        for (n, p) in ring.iter().enumerate() {
+            let prev = ring[(n + ring.len() - 1) % ring.len()];
+            if inside(p[axis]) != inside(prev[axis]) {
+                let t = (edge - prev[axis]) / (p[axis] - prev[axis]);
+                let mut cut = [0.0; 2];
+                cut[axis] = edge;
+                cut[1 - axis] = prev[1 - axis] + t * (p[1 - axis] - prev[1 - axis]);
+                out.push(cut);
+            }
+            if inside(p[axis]) {
+                out.push(*p);
+            }
+        }
+        out
+    };
+    let ring = side(ring, &|v| v >= min, min);
+    side(&ring, &|v| v <= max, max)
+}
+
+/// Geometry commands for rings in tile units, outer rings drawn clockwise on screen as MVT wants.
+fn polygon_commands(rings: &[Vec<[f64; 2]>]) -> Vec<u32> {
+    let mut commands = Vec::new();
+    let mut cursor = [0, 0];
+    for ring in rings {
+        let mut points: Vec<[i32; 2]> = ring
+            .iter()
+            .map(|p| [p[0].round() as i32, p[1].round() as i32])
+            .collect();
+        points.dedup();
+        while points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        let area: i64 = (0..points.len())
+            .map(|n| {
+                let (a, b) = (points[n], points[(n + 1) % points.len()]);
+                a[0] as i64 * b[1] as i64 - b[0] as i64 * a[1] as i64
+            })
+            .sum();
+        if points.len() < 3 || area == 0 {
+            continue;
+        }
+        if area < 0 {
+            points.reverse();
+        }
+        for (n, p) in points.iter().enumerate() {
+            match n {
+                0 => commands.push(command(MOVE_TO, 1)),
+                1 => commands.push(command(LINE_TO, points.len() - 1)),
+                _ => (),
+            }
+            commands.push(zigzag(p[0] - cursor[0]));
+            commands.push(zigzag(p[1] - cursor[1]));
+            cursor = *p;
+        }
+        commands.push(command(CLOSE_PATH, 1));
+    }
+    commands
+}
+
+/// Rings of a FIR and its extentions in tile units at zoom `z`. Rings reaching past the antimeridian
+/// are also drawn a world to the other side, so both halves end up in their tiles.
+fn project_group(firs: &[FIRBoundary], group: &[usize], z: u8) -> Vec<Vec<[f64; 2]>> {
+    let size = EXTENT * (1u32 << z) as f64;
+    let mut rings = Vec::new();
+    for n in group {
+        let ring: Vec<[f64; 2]> = geodesy::ring_coords(&firs[*n].boundary_corners)
+            .iter()
+            .map(|c| project(c, size))
+            .collect();
+        let min = ring.iter().map(|p| p[0]).fold(f64::MAX, f64::min);
+        let max = ring.iter().map(|p| p[0]).fold(f64::MIN, f64::max);
+        if min < 0.0 {
+            rings.push(ring.iter().map(|p| [p[0] + size, p[1]]).collect());
+        }
+        if max > size {
+            rings.push(ring.iter().map(|p| [p[0] - size, p[1]]).collect());
+        }
+        rings.push(ring);
+    }
+    rings
+}
+
+/// Range of tiles along one axis touched by `min..=max` in tile units, buffer included.
+fn tile_range(min: f64, max: f64, tiles: u32) -> std::ops::RangeInclusive<u32> {
+    let first = ((min - BUFFER) / EXTENT).floor().max(0.0) as u32;
+    let last = ((max + BUFFER) / EXTENT)
+        .floor()
+        .min(tiles as f64 - 1.0)
+        .max(0.0) as u32;
+    first..=last
+}
+
+/// Where the tiles go: a directory of {z}/{x}/{y}.pbf files, or an MBTiles file with gzipped tiles.
+enum Output<'a> {
+    Directory(&'a Path),
+    MbTiles(MbTiles),
+}
+
+impl Output<'_> {
+    fn write(&mut self, z: u8, x: u32, y: u32, tile: &[u8]) -> io::Result<()> {
+        match self {
+            Output::Directory(dir) => {
+                let dir = dir.join(z.to_string()).join(x.to_string());
+                fs::create_dir_all(&dir)?;
+                fs::write(dir.join(format!("{}.pbf", y)), tile)
+            }
+            Output::MbTiles(mbtiles) => mbtiles.add(z, x, y, &gzip(tile)),
+        }
+    }
+}
+
+/// Renders the FIRs into tiles for every zoom level in `zooms`, simplified for each level,
+/// and writes them to `p`: an MBTiles file if it ends with .mbtiles, otherwise a directory.
+/// Every FIR with its extentions is one feature in the firs layer, and its lable a point in the lables layer.
+/// Returns the number of tiles written.
+pub(crate) fn write_tiles<P: AsRef<Path>>(
+    firs: &[FIRBoundary],
+    p: P,
+    zooms: std::ops::RangeInclusive<u8>,
+) -> io::Result<usize> {
+    let p = p.as_ref();
+    let mut output = match p.extension().map(|e| e == "mbtiles") {
+        Some(true) => Output::MbTiles(MbTiles::create(p)?),
+        _ => {
+            fs::create_dir_all(p)?;
+            Output::Directory(p)
+        }
+    };
+    let groups = with_extentions(firs);
+    let mut count = 0;
+    for z in zooms.clone() {
+        let tiles = 1u32 << z;
+        let size = EXTENT * tiles as f64;
+        let mut simplified = firs.to_vec();
+        let tolerance = SIMPLIFY_UNITS * EQUATOR_NM / size;
+        simplify::simplify(&mut simplified, tolerance, Algorithm::DouglasPeucker);
+        let projected: Vec<Vec<Vec<[f64; 2]>>> = groups
+            .iter()
+            .map(|group| project_group(&simplified, group, z))
+            .collect();
+        let lables: Vec<[f64; 2]> = groups
+            .iter()
+            .map(|group| project(&Coord::from(&firs[group[0]].lable), size))
+            .collect();
+        let attributes: Vec<_> = groups.iter().map(|g| attributes(&firs[g[0]])).collect();
+
+        for x in 0..tiles {
+            let (left, right) = (x as f64 * EXTENT, (x + 1) as f64 * EXTENT);
+            let mut column: BTreeMap<u32, Tile> = BTreeMap::new();
+            for (id, rings) in projected.iter().enumerate() {
+                let strips: Vec<Vec<[f64; 2]>> = rings
+                    .iter()
+                    .map(|ring| clip(ring, 0, left - BUFFER, right + BUFFER))
+                    .filter(|ring| ring.len() >= 3)
+                    .collect();
+                let top = strips
+                    .iter()
+                    .flatten()
+                    .map(|p| p[1])
+                    .fold(f64::MAX, f64::min);
+                let bottom = strips
+                    .iter()
+                    .flatten()
+                    .map(|p| p[1])
+                    .fold(f64::MIN, f64::max);
+                if strips.is_empty() {
+                    continue;
+                }
+                for y in tile_range(top, bottom, tiles) {
+                    let (upper, lower) = (y as f64 * EXTENT, (y + 1) as f64 * EXTENT);
+                    let local: Vec<Vec<[f64; 2]>> = strips
+                        .iter()
+                        .map(|ring| clip(ring, 1, upper - BUFFER, lower + BUFFER))
+                        .map(|ring| ring.iter().map(|p| [p[0] - left, p[1] - upper]).collect())
+                        .collect();
+                    let commands = polygon_commands(&local);
+                    if !commands.is_empty() {
+                        let tile = column.entry(y).or_default();
+                        tile.firs.add(id + 1, POLYGON, &commands, &attributes[id]);
+                    }
+                }
+            }
+            for (id, lable) in lables.iter().enumerate() {
+                let (lx, ly) = ((lable[0] / EXTENT).floor(), (lable[1] / EXTENT).floor());
+                if lx != x as f64 || ly < 0.0 || ly >= tiles as f64 {
+                    continue;
+                }
+                let local = [
+                    (lable[0] - left).round() as i32,
+                    (lable[1] - ly * EXTENT).round() as i32,
+                ];
+                let commands = [command(MOVE_TO, 1), zigzag(local[0]), zigzag(local[1])];
+                let tile = column.entry(ly as u32).or_default();
+                tile.lables.add(id + 1, POINT, &commands, &attributes[id]);
+            }
+            for (y, tile) in column {
+                output.write(z, x, y, &tile.encode())?;
+                count += 1;
+            }
+        }
+    }
+
+    let metadata = metadata(firs, zooms);
+    match output {
+        Output::Directory(dir) => {
+            let map: serde_json::Map<String, serde_json::Value> = metadata
+                .into_iter()
+                .map(|(k, v)| (k, serde_json::Value::String(v)))
+                .collect();
+            serde_json::to_writer_pretty(File::create(dir.join("metadata.json"))?, &map)?;
+        }
+        Output::MbTiles(mbtiles) => mbtiles.finish(&metadata)?,
+    }
+    Ok(count)
+}
+
+/// The metadata of an MBTiles file, also written as metadata.json next to a directory of tiles.
+fn metadata(firs: &[FIRBoundary], zooms: std::ops::RangeInclusive<u8>) -> Vec<(String, String)> {
+    let corners = || {
+        firs.iter()
+            .flat_map(|f| f.boundary_corners.iter().map(Coord::from))
+    };
+    let bounds = [
+        corners().map(|c| c.lon).fold(180.0, f64::min).max(-180.0),
+        corners()
+            .map(|c| c.lat)
+            .fold(MAX_LAT, f64::min)
+            .max(-MAX_LAT),
+        corners().map(|c| c.lon).fold(-180.0, f64::max).min(180.0),
+        corners()
+            .map(|c| c.lat)
+            .fold(-MAX_LAT, f64::max)
+            .min(MAX_LAT),
+    ];
+    let fields: serde_json::Map<String, serde_json::Value> = firs
+        .first()
+        .map(attributes)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(k, v)| {
+            let typ = match v {
+                serde_json::Value::String(_) => "String",
+                serde_json::Value::Bool(_) => "Boolean",
+                _ => "Number",
+            };
+            (k, json!(typ))
+        })
+        .collect();
+    let layer = |id: &str| json!({"id": id, "fields": fields, "minzoom": zooms.start(), "maxzoom": zooms.end()});
+    let json = json!({ "vector_layers": [layer(FIR_LAYER), layer(LABLE_LAYER)] });
+    vec![
+        ("name".to_string(), "FIR boundaries".to_string()),
+        ("format".to_string(), "pbf".to_string()),
+        ("type".to_string(), "overlay".to_string()),
+        ("minzoom".to_string(), zooms.start().to_string()),
+        ("maxzoom".to_string(), zooms.end().to_string()),
+        (
+            "bounds".to_string(),
+            format!("{},{},{},{}", bounds[0], bounds[1], bounds[2], bounds[3]),
+        ),
+        (
+            "center".to_string(),
+            format!(
+                "{:.6},{:.6},{}",
+                (bounds[0] + bounds[2]) / 2.0,
+                (bounds[1] + bounds[3]) / 2.0,
+                zooms.start()
+            ),
+        ),
+        ("json".to_string(), json.to_string()),
+    ]
+}
+
+/// CRC-32 as used by gzip and PNG.
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for b in data {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xEDB8_8320,
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
+
+/// Tiles in MBTiles files are gzipped, which is what tile servers expect.
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+    out.extend(miniz_oxide::deflate::compress_to_vec(data, 6));
+    out.extend(crc32(data).to_le_bytes());
+    out.extend((data.len() as u32).to_le_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fir_boundaries::fir;
+
+    #[test]
+    fn square_is_clockwise_on_screen() {
+        // counter clockwise on screen, with y down
+        let ring = vec![[10.0, 10.0], [10.0, 20.0], [20.0, 20.0], [20.0, 10.0]];
+        assert_eq!(
+            polygon_commands(&[ring]),
+            vec![
+                command(MOVE_TO, 1),
+                zigzag(20),
+                zigzag(10),
+                command(LINE_TO, 3),
+                zigzag(0),
+                zigzag(10),
+                zigzag(-10),
+                zigzag(0),
+                zigzag(0),
+                zigzag(-10),
+                command(CLOSE_PATH, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn clipped_to_tile() {
+        let ring = vec![
+            [-100.0, -100.0],
+            [100.0, -100.0],
+            [100.0, 100.0],
+            [-100.0, 100.0],
+        ];
+        let ring = clip(&clip(&ring, 0, 0.0, 50.0), 1, 0.0, 50.0);
+        assert_eq!(ring.len(), 4);
+        assert!(ring
+            .iter()
+            .all(|p| (0.0..=50.0).contains(&p[0]) && (0.0..=50.0).contains(&p[1])));
+    }
+
+    #[test]
+    fn pyramid_in_directory() {
+        let dir = std::env::temp_dir().join(format!("tiles-test-{}", std::process::id()));
+        // a FIR crossing the equator and the prime meridian, touching all four tiles at zoom 1
+        let firs = vec![fir(
+            "ZZZZ",
+            false,
+            &[(-10, -10), (10, -10), (10, 10), (-10, 10)],
+        )];
+        let count = write_tiles(&firs, &dir, 0..=1).unwrap();
+        assert_eq!(count, 5);
+        let tile = fs::read(dir.join("0").join("0").join("0.pbf")).unwrap();
+        let metadata = fs::read_to_string(dir.join("metadata.json")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        // one layer per tag 3 at the start, version 2 and the layer name first
+        assert_eq!(tile[0], 3 << 3 | 2);
+        assert!(tile.windows(4).any(|w| w == b"firs"));
+        assert!(tile.windows(6).any(|w| w == b"lables"));
+        assert!(tile.windows(4).any(|w| w == b"ZZZZ"));
+        assert!(metadata.contains("vector_layers"));
+    }
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+}
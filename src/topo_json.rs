@@ -0,0 +1,294 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use indexmap::IndexMap;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fir_boundaries::{with_extentions, FIRBoundary, FIRParsingError, FIRResult, Point},
+    geo_json::Properties,
+    geodesy::{self, Coord},
+    topology::{open_ring, Topology},
+};
+
+/// Decimals kept when nothing else is asked for, as many as FIRBoundaries.dat uses.
+pub(crate) const DECIMALS: u32 = 6;
+
+/// Name of the object holding the FIRs.
+const OBJECT: &str = "firs";
+
+/// A TopoJSON file, with positions of type `P`: quantized when writing and any numbers when reading.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct TopoJson<P> {
+    #[serde(rename = "type")]
+    typ: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    transform: Option<Transform>,
+    objects: IndexMap<String, Collection>,
+    arcs: Vec<Vec<P>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Transform {
+    scale: [f64; 2],
+    translate: [f64; 2],
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Collection {
+    #[serde(rename = "type")]
+    typ: String,
+    geometries: Vec<TopoGeometry>,
+}
+
+/// A polygon is a list of rings and a ring a list of arcs, where `!n` is arc `n` walked backwards.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum TopoGeometry {
+    Polygon {
+        properties: Option<Properties>,
+        arcs: Vec<Vec<i64>>,
+    },
+    MultiPolygon {
+        properties: Option<Properties>,
+        arcs: Vec<Vec<Vec<i64>>>,
+    },
+}
+
+/// Encodes the FIRs as TopoJSON, every FIR with its extentions as one multipolygon.
+/// Borders shared between FIRs become one arc used by both, and positions are kept with
+/// `decimals` decimals, stored as steps from the previous position.
+pub(crate) fn encode(firs: &[FIRBoundary], decimals: u32) -> TopoJson<[i64; 2]> {
+    let rings: Vec<Vec<Point>> = firs
+        .iter()
+        .map(|fir| open_ring(&fir.boundary_corners))
+        .collect();
+    let topology = Topology::new(rings.iter().map(Vec::as_slice));
+
+    let points = || rings.iter().flatten();
+    let translate = [
+        points().map(|p| p.lon).min().unwrap_or_default(),
+        points().map(|p| p.lat).min().unwrap_or_default(),
+    ];
+    let factor = Decimal::from(10u64.pow(decimals));
+    let quantize = |p: &Point| {
+        [
+            ((p.lon - translate[0]) * factor).round().to_i64().unwrap(),
+            ((p.lat - translate[1]) * factor).round().to_i64().unwrap(),
+        ]
+    };
+    let arcs = topology
+        .arcs
+        .iter()
+        .map(|arc| {
+            let mut positions: Vec<[i64; 2]> = arc.iter().map(quantize).collect();
+            // points that end up in the same position are removed, but an arc needs two positions
+            positions.dedup();
+            if positions.len() == 1 {
+                positions.push(positions[0]);
+            }
+            let mut previous = [0, 0];
+            positions
+                .into_iter()
+                .map(|[x, y]| {
+                    let delta = [x - previous[0], y - previous[1]];
+                    previous = [x, y];
+                    delta
+                })
+                .collect()
+        })
+        .collect();
+
+    let geometries = with_extentions(firs)
+        .into_iter()
+        .map(|group| TopoGeometry::MultiPolygon {
+            properties: Some((&firs[group[0]]).into()),
+            arcs: group
+                .iter()
+                .map(|n| {
+                    vec![topology.rings[*n]
+                        .iter()
+                        .map(|r| match r.reversed {
+                            true => !(r.index as i64),
+                            false => r.index as i64,
+                        })
+                        .collect()]
+                })
+                .collect(),
+        })
+        .collect();
+    let mut objects = IndexMap::new();
+    objects.insert(
+        OBJECT.to_string(),
+        Collection {
+            typ: "GeometryCollection".to_string(),
+            geometries,
+        },
+    );
+    TopoJson {
+        typ: "Topology".to_string(),
+        transform: Some(Transform {
+            scale: [1.0 / factor.to_f64().unwrap(); 2],
+            translate: [
+                translate[0].to_f64().unwrap(),
+                translate[1].to_f64().unwrap(),
+            ],
+        }),
+        objects,
+        arcs,
+    }
+}
+
+pub(crate) fn write_file<P: AsRef<Path>>(
+    firs: &[FIRBoundary],
+    p: P,
+    decimals: u32,
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(p)?);
+    serde_json::to_writer(&mut writer, &encode(firs, decimals))?;
+    writer.flush()
+}
+
+fn topojson_error<S: AsRef<str>>(s: S) -> FIRParsingError {
+    FIRParsingError::FIRParsing(format!("TopoJSON: {}", s.as_ref()))
+}
+
+/// Turns the arcs back into coordinates, undoing the quantization if there is a transform.
+fn decode_arcs(topo: &TopoJson<Vec<f64>>) -> FIRResult<Vec<Vec<Coord>>> {
+    topo.arcs
+        .iter()
+        .map(|arc| {
+            let mut previous = [0.0, 0.0];
+            arc.iter()
+                .map(|position| {
+                    let (x, y) = match position[..] {
+                        [x, y, ..] => (x, y),
+                        _ => return Err(topojson_error("position with less than 2 numbers")),
+                    };
+                    Ok(match &topo.transform {
+                        Some(t) => {
+                            previous = [previous[0] + x, previous[1] + y];
+                            Coord {
+                                lon: previous[0] * t.scale[0] + t.translate[0],
+                                lat: previous[1] * t.scale[1] + t.translate[1],
+                            }
+                        }
+                        None => Coord { lon: x, lat: y },
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Puts a ring together from its arcs, where every arc starts where the one before it ends.
+fn ring(refs: &[i64], arcs: &[Vec<Coord>]) -> FIRResult<Vec<Coord>> {
+    let mut ring = Vec::new();
+    for r in refs {
+        let (index, reversed) = match *r < 0 {
+            true => (!*r as usize, true),
+            false => (*r as usize, false),
+        };
+        let arc = arcs
+            .get(index)
+            .ok_or_else(|| topojson_error(format!("arc {} does not exist", index)))?;
+        let skip = (!ring.is_empty()) as usize;
+        match reversed {
+            true => ring.extend(arc.iter().rev().skip(skip)),
+            false => ring.extend(arc.iter().skip(skip)),
+        }
+    }
+    Ok(ring)
+}
+
+/// Decodes TopoJSON back into FIRs. Every polygon or multipolygon in any object is read,
+/// with the first polygon as the FIR and the rest as extentions. Holes are left out.
+pub(crate) fn decode(topo: &TopoJson<Vec<f64>>) -> FIRResult<Vec<FIRBoundary>> {
+    let arcs = decode_arcs(topo)?;
+    let mut firs = Vec::new();
+    for geometry in topo.objects.values().flat_map(|o| &o.geometries) {
+        let (properties, polygons) = match geometry {
+            TopoGeometry::Polygon { properties, arcs } => (properties, vec![arcs.clone()]),
+            TopoGeometry::MultiPolygon { properties, arcs } => (properties, arcs.clone()),
+        };
+        let properties = properties
+            .as_ref()
+            .ok_or_else(|| topojson_error("geometry without properties"))?;
+        for (part, polygon) in polygons.iter().enumerate() {
+            let outer = polygon.first().ok_or_else(|| {
+                topojson_error(format!("{}: polygon without rings", properties.icao))
+            })?;
+            let corners = geodesy::boundary_corners(&ring(outer, &arcs)?);
+            if corners.len() < 3 {
+                return Err(topojson_error(format!(
+                    "{}: polygon with less than 3 points",
+                    properties.icao
+                )));
+            }
+            let mut fir = FIRBoundary {
+                id: firs.len(),
+                icao: properties.icao.clone(),
+                is_oseanic: properties.is_oceanic,
+                is_extension: part != 0,
+                min_lat: Default::default(),
+                min_lon: Default::default(),
+                max_lat: Default::default(),
+                max_lon: Default::default(),
+                lable: properties.lable.clone(),
+                boundary_corners: corners,
+            };
+            fir.update_bounds();
+            firs.push(fir);
+        }
+    }
+    Ok(firs)
+}
+
+pub(crate) fn read_file<P: AsRef<Path>>(p: P) -> FIRResult<Vec<FIRBoundary>> {
+    let topo: TopoJson<Vec<f64>> = serde_json::from_reader(BufReader::new(File::open(p)?))
+        .map_err(|e| topojson_error(e.to_string()))?;
+    decode(&topo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fir_boundaries::fir;
+
+    #[test]
+    fn shared_arcs_round_trip() {
+        let firs = vec![
+            fir("EZZZ", false, &[(0, 0), (2, 0), (2, 2), (0, 2)]),
+            fir("EZZZ", true, &[(2, 2), (4, 2), (4, 4), (2, 4)]),
+            fir("LZZZ", false, &[(0, 2), (2, 2), (2, 4), (0, 4)]),
+        ];
+        let topo = encode(&firs, DECIMALS);
+        assert_eq!(topo.objects[OBJECT].geometries.len(), 2);
+        // LZZZ shares a border with both parts of EZZZ, which are stored once
+        let references: usize = match &topo.objects[OBJECT].geometries[0] {
+            TopoGeometry::MultiPolygon { arcs, .. } => arcs.iter().flatten().map(Vec::len).sum(),
+            _ => unreachable!(),
+        };
+        assert_eq!(topo.arcs.len(), 5);
+        assert_eq!(references, 4);
+
+        let json = serde_json::to_string(&topo).unwrap();
+        let read = decode(&serde_json::from_str(&json).unwrap()).unwrap();
+        assert_eq!(read.len(), 3);
+        for (read, fir) in read.iter().zip(&firs) {
+            assert_eq!(read.icao, fir.icao);
+            assert_eq!(read.is_extension, fir.is_extension);
+            let mut corners = read.boundary_corners.clone();
+            let start = corners
+                .iter()
+                .position(|p| *p == fir.boundary_corners[0])
+                .unwrap();
+            corners.rotate_left(start);
+            assert_eq!(corners, fir.boundary_corners);
+        }
+    }
+}
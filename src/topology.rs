@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use indexmap::IndexSet;
+
+use crate::fir_boundaries::Point;
+
+/// Reference to an arc from a ring. If `reversed` is set the arc is walked from the end to the start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ArcRef {
+    pub(crate) index: usize,
+    pub(crate) reversed: bool,
+}
+
+/// Rings split into arcs, where every border shared between two rings is stored only once.
+///
+/// A ring is cut at every junction: a point where the neighbouring points differ between the rings using it.
+/// The arcs between two junctions are then the same in every ring following that border,
+/// possibly drawn in the other direction.
+#[derive(Debug)]
+pub(crate) struct Topology {
+    /// Every arc includes both its end points. Arcs of rings without junctions start and end in the same point.
+    pub(crate) arcs: Vec<Vec<Point>>,
+    pub(crate) rings: Vec<Vec<ArcRef>>,
+    /// First point of every ring, so rebuilt rings start where they used to.
+    starts: Vec<Point>,
+}
+
+/// Removes the closing point and repeated points from a ring.
+pub(crate) fn open_ring(points: &[Point]) -> Vec<Point> {
+    let mut ring: Vec<Point> = points.to_vec();
+    ring.dedup();
+    while ring.len() > 1 && ring[0] == ring[ring.len() - 1] {
+        ring.pop();
+    }
+    ring
+}
+
+fn find_junctions(rings: &[Vec<Point>]) -> IndexSet<Point> {
+    let mut neighbours: HashMap<&Point, (&Point, &Point)> = HashMap::new();
+    let mut junctions = IndexSet::new();
+    for ring in rings {
+        let n = ring.len();
+        for (i, p) in ring.iter().enumerate() {
+            let prev = &ring[(i + n - 1) % n];
+            let next = &ring[(i + 1) % n];
+            match neighbours.get(p) {
+                None => {
+                    neighbours.insert(p, (prev, next));
+                }
+                Some(&(a, b)) => {
+                    if !((a == prev && b == next) || (a == next && b == prev)) {
+                        junctions.insert(p.clone());
+                    }
+                }
+            }
+        }
+    }
+    junctions
+}
+
+impl Topology {
+    pub(crate) fn new<'a, I>(rings: I) -> Self
+    where
+        I: IntoIterator<Item = &'a [Point]>,
+    {
+        let rings: Vec<Vec<Point>> = rings.into_iter().map(open_ring).collect();
+        let junctions = find_junctions(&rings);
+        let mut arcs: Vec<Vec<Point>> = Vec::new();
+        let mut lookup: HashMap<Vec<Point>, usize> = HashMap::new();
+        let mut refs = Vec::with_capacity(rings.len());
+        let starts = rings.iter().map(|r| r[0].clone()).collect();
+        for ring in rings {
+            let n = ring.len();
+            let start = match ring.iter().position(|p| junctions.contains(p)) {
+                Some(s) => s,
+                // a ring without junctions is one closed arc, start it in a point every ring with the same border agrees on
+                None => ring.iter().enumerate().min_by_key(|(_, p)| *p).unwrap().0,
+            };
+            let mut ring_arcs = Vec::new();
+            let mut current = vec![ring[start].clone()];
+            for i in 1..=n {
+                let p = &ring[(start + i) % n];
+                current.push(p.clone());
+                if i == n || junctions.contains(p) {
+                    let arc = std::mem::replace(&mut current, vec![p.clone()]);
+                    ring_arcs.push(Self::insert_arc(&mut arcs, &mut lookup, arc));
+                }
+            }
+            refs.push(ring_arcs);
+        }
+        Self {
+            arcs,
+            rings: refs,
+            starts,
+        }
+    }
+
+    fn insert_arc(
+        arcs: &mut Vec<Vec<Point>>,
+        lookup: &mut HashMap<Vec<Point>, usize>,
+        arc: Vec<Point>,
+    ) -> ArcRef {
+        if let Some(&index) = lookup.get(&arc) {
+            return ArcRef {
+                index,
+                reversed: false,
+            };
+        }
+        let reversed: Vec<Point> = arc.iter().rev().cloned().collect();
+        if let Some(&index) = lookup.get(&reversed) {
+            return ArcRef {
+                index,
+                reversed: true,
+            };
+        }
+        let index = arcs.len();
+        lookup.insert(arc.clone(), index);
+        arcs.push(arc);
+        ArcRef {
+            index,
+            reversed: false,
+        }
+    }
+
+    /// Puts a ring back together from the given arcs. The ring is returned without a closing point.
+    pub(crate) fn ring(&self, ring: usize, arcs: &[Vec<Point>]) -> Vec<Point> {
+        let mut points = Vec::new();
+        for r in &self.rings[ring] {
+            let arc = &arcs[r.index];
+            match r.reversed {
+                true => points.extend(arc.iter().rev().skip(1).cloned()),
+                false => points.extend(arc.iter().skip(1).cloned()),
+            }
+        }
+        if let Some(start) = points.iter().position(|p| *p == self.starts[ring]) {
+            points.rotate_left(start);
+        }
+        points
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ring(points: &[(i64, i64)]) -> Vec<Point> {
+        points
+            .iter()
+            .map(|(lat, lon)| Point::new((*lat).into(), (*lon).into()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn shared_border_is_one_arc() {
+        let a = ring(&[(0, 0), (2, 0), (2, 1), (2, 2), (0, 2), (0, 1)]);
+        let b = ring(&[(0, 2), (2, 2), (2, 4), (0, 4)]);
+        let topology = Topology::new(vec![a.as_slice(), b.as_slice()]);
+        let shared = topology.rings[0]
+            .iter()
+            .find(|r| topology.rings[1].iter().any(|o| o.index == r.index))
+            .unwrap();
+        assert_eq!(
+            topology.arcs[shared.index],
+            ring(&[(2, 2), (0, 2)]),
+            "{:?}",
+            topology
+        );
+        assert_eq!(topology.arcs.len(), 3);
+    }
+
+    #[test]
+    fn rings_are_rebuilt() {
+        let a = ring(&[(0, 0), (2, 0), (2, 1), (2, 2), (0, 2), (0, 1)]);
+        let b = ring(&[(0, 2), (2, 2), (2, 4), (0, 4)]);
+        let topology = Topology::new(vec![a.as_slice(), b.as_slice()]);
+        for (n, original) in [a, b].iter().enumerate() {
+            assert_eq!(&topology.ring(n, &topology.arcs), original);
+        }
+    }
+}
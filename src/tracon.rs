@@ -0,0 +1,444 @@
+use std::{
+    fmt::{self, Display},
+    fs::File,
+    io::BufReader,
+    path::Path,
+};
+
+use color_eyre::eyre;
+use indexmap::{IndexMap, IndexSet};
+use itertools::Itertools;
+use serde::Deserialize;
+
+use crate::{
+    fir_boundaries::{try_polygon_or_hole, with_extentions, FIRBoundary, Fill, Point},
+    geodesy::{self, Coord},
+    vatspy_dat::{Airport, VatSpyDat},
+};
+
+/// TRACON points closer than this to the border of their FIR are counted as inside, as shared borders are often
+/// drawn with slightly different points in the two datasets.
+const BORDER_TOLERANCE_NM: f64 = 0.5;
+
+// format (SimAware TRACONBoundaries.json):
+// {"type": "FeatureCollection", "features": [{"type": "Feature",
+//   "properties": {"id": "A90", "prefix": ["BOS", "A90"], "name": "Boston Approach"},
+//   "geometry": {"type": "MultiPolygon", "coordinates": [[[[lon, lat], ...]]]}}]}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct TraconCollection {
+    pub(crate) features: Vec<Tracon>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Tracon {
+    pub(crate) properties: TraconProperties,
+    pub(crate) geometry: TraconGeometry,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct TraconProperties {
+    pub(crate) id: String,
+    /// Callsign prefixes of the controllers of the TRACON.
+    #[serde(default)]
+    pub(crate) prefix: Vec<String>,
+    #[serde(default)]
+    pub(crate) name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", content = "coordinates")]
+pub(crate) enum TraconGeometry {
+    Polygon(Vec<Vec<Point>>),
+    MultiPolygon(Vec<Vec<Vec<Point>>>),
+}
+
+impl TraconGeometry {
+    /// Every polygon, with the outer ring first and then the holes.
+    pub(crate) fn polygons(&self) -> Vec<&[Vec<Point>]> {
+        match self {
+            TraconGeometry::Polygon(p) => vec![p.as_slice()],
+            TraconGeometry::MultiPolygon(ps) => ps.iter().map(|p| p.as_slice()).collect(),
+        }
+    }
+
+    /// The outer rings.
+    fn outer_rings(&self) -> Vec<&[Point]> {
+        self.polygons()
+            .into_iter()
+            .filter_map(|p| p.first().map(|r| r.as_slice()))
+            .collect()
+    }
+}
+
+/// Problems found in the TRACONs.
+#[derive(Debug, Clone)]
+pub(crate) enum Diagnostic {
+    /// A ring with less than 3 different points, or without area.
+    Degenerate {
+        tracon: String,
+        part: usize,
+    },
+    /// An outer ring drawn counterclockwise or a hole drawn clockwise, the opposite of FIRBoundaries.dat.
+    DrawDirection {
+        tracon: String,
+        part: usize,
+        hole: bool,
+    },
+    Duplicates {
+        tracon: String,
+        points: Vec<Point>,
+    },
+    SelfIntersection {
+        tracon: String,
+        points: Vec<Point>,
+    },
+    /// The same prefix is used by more than one TRACON, so controllers can not be told apart.
+    PrefixCollision {
+        prefix: String,
+        tracons: Vec<String>,
+    },
+    /// No airport in VATSpy.dat has the id or a prefix of the TRACON as ICAO or IATA/LID.
+    NoAirport {
+        tracon: String,
+        name: String,
+    },
+    /// The airport of the TRACON is not inside any FIR.
+    AirportOutsideFirs {
+        tracon: String,
+        airport: String,
+        name: String,
+        /// The FIR of the airport in VATSpy.dat.
+        fir: String,
+    },
+    /// Points of the TRACON outside the FIR containing its airport.
+    OutsideFir {
+        tracon: String,
+        airport: String,
+        fir: String,
+        points: Vec<Point>,
+    },
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Diagnostic::Degenerate { tracon, part } => {
+                write!(f, "TRACON {}: ring {} has no area", tracon, part)
+            }
+            Diagnostic::DrawDirection { tracon, part, hole } => write!(
+                f,
+                "TRACON {}: {} {} is drawn {}, it needs to be drawn {} like FIRs",
+                tracon,
+                match hole {
+                    true => "hole in ring",
+                    false => "ring",
+                },
+                part,
+                match hole {
+                    true => "clockwise",
+                    false => "counterclockwise",
+                },
+                match hole {
+                    true => "counterclockwise",
+                    false => "clockwise",
+                }
+            ),
+            Diagnostic::Duplicates { tracon, points } => write!(
+                f,
+                "TRACON {}: has duplicate points: {}",
+                tracon,
+                points.iter().join(", ")
+            ),
+            Diagnostic::SelfIntersection { tracon, points } => write!(
+                f,
+                "TRACON {}: crosses itself at: {}",
+                tracon,
+                points.iter().join(", ")
+            ),
+            Diagnostic::PrefixCollision { prefix, tracons } => write!(
+                f,
+                "Prefix {} is used by more than one TRACON: {}",
+                prefix,
+                tracons.join(", ")
+            ),
+            Diagnostic::NoAirport { tracon, name } => write!(
+                f,
+                "TRACON {} ({}): no airport in VATSpy.dat matches its id or prefixes",
+                tracon, name
+            ),
+            Diagnostic::AirportOutsideFirs {
+                tracon,
+                airport,
+                name,
+                fir,
+            } => write!(
+                f,
+                "TRACON {}: its airport {} ({}) is not inside any FIR, VATSpy.dat puts it in {}",
+                tracon, airport, name, fir
+            ),
+            Diagnostic::OutsideFir {
+                tracon,
+                airport,
+                fir,
+                points,
+            } => write!(
+                f,
+                "TRACON {}: {} point(s) are outside FIR {} containing its airport {}: {}",
+                tracon,
+                points.len(),
+                fir,
+                airport,
+                points.iter().join(", ")
+            ),
+        }
+    }
+}
+
+pub(crate) fn read_file<P: AsRef<Path>>(p: P) -> eyre::Result<Vec<Tracon>> {
+    let collection: TraconCollection = serde_json::from_reader(BufReader::new(File::open(p)?))?;
+    Ok(collection.features)
+}
+
+/// Checks draw direction, duplicate points and self intersections of every ring, like for FIRs.
+fn check_geometry(tracon: &Tracon, diagnostics: &mut Vec<Diagnostic>) {
+    let id = &tracon.properties.id;
+    let mut part = 0;
+    let mut duplicates = IndexSet::new();
+    let mut crossings = Vec::new();
+    for polygon in tracon.geometry.polygons() {
+        for (n, ring) in polygon.iter().enumerate() {
+            part += 1;
+            // GeoJSON rings end with their first point
+            let open = match ring.len() > 1 && ring[0] == ring[ring.len() - 1] {
+                true => &ring[..ring.len() - 1],
+                false => &ring[..],
+            };
+            let mut seen = IndexSet::new();
+            for point in open {
+                if !seen.insert(point) {
+                    duplicates.insert(point.clone());
+                }
+            }
+            let closed = open.iter().chain(open.first()).cloned().collect_vec();
+            let expected = match n {
+                0 => Fill::Polygon,
+                _ => Fill::Hole,
+            };
+            match try_polygon_or_hole(&closed) {
+                Some(fill) if seen.len() >= 3 => {
+                    if fill != expected {
+                        diagnostics.push(Diagnostic::DrawDirection {
+                            tracon: id.clone(),
+                            part,
+                            hole: n > 0,
+                        })
+                    }
+                }
+                _ => diagnostics.push(Diagnostic::Degenerate {
+                    tracon: id.clone(),
+                    part,
+                }),
+            }
+            crossings.extend(
+                geodesy::self_intersections(&geodesy::ring_coords(open))
+                    .into_iter()
+                    .map(Coord::to_point),
+            );
+        }
+    }
+    if !duplicates.is_empty() {
+        diagnostics.push(Diagnostic::Duplicates {
+            tracon: id.clone(),
+            points: duplicates.into_iter().collect(),
+        });
+    }
+    if !crossings.is_empty() {
+        diagnostics.push(Diagnostic::SelfIntersection {
+            tracon: id.clone(),
+            points: crossings,
+        });
+    }
+}
+
+/// Finds prefixes used by more than one TRACON.
+fn check_prefixes(tracons: &[Tracon]) -> Vec<Diagnostic> {
+    let mut users: IndexMap<&str, IndexSet<&str>> = IndexMap::new();
+    for tracon in tracons {
+        for prefix in &tracon.properties.prefix {
+            users
+                .entry(prefix.as_str())
+                .or_default()
+                .insert(tracon.properties.id.as_str());
+        }
+    }
+    users
+        .into_iter()
+        .filter(|(_, ids)| ids.len() > 1)
+        .map(|(prefix, ids)| Diagnostic::PrefixCollision {
+            prefix: prefix.to_string(),
+            tracons: ids.into_iter().map(String::from).collect(),
+        })
+        .collect()
+}
+
+/// The airport of a TRACON: the first with the id, and then with one of the prefixes, as ICAO or IATA/LID.
+/// Real airports are preferred over pseudo airports.
+fn airport<'a>(tracon: &Tracon, dat: &'a VatSpyDat) -> Option<&'a Airport> {
+    let keys = std::iter::once(&tracon.properties.id).chain(&tracon.properties.prefix);
+    keys.filter(|k| !k.is_empty()).find_map(|key| {
+        dat.airports
+            .iter()
+            .filter(|a| a.icao == *key || a.iata == *key)
+            .min_by_key(|a| a.is_pseudo)
+    })
+}
+
+/// Tells if `p` is inside one of the rings, or closer than BORDER_TOLERANCE_NM to one of them.
+fn inside(rings: &[Vec<Coord>], p: Coord) -> bool {
+    rings.iter().any(|ring| {
+        geodesy::contains(ring, p)
+            || ring
+                .iter()
+                .zip(ring.iter().cycle().skip(1))
+                .any(|(a, b)| geodesy::segment_distance_nm(p, *a, *b) < BORDER_TOLERANCE_NM)
+    })
+}
+
+/// Checks that every TRACON is inside the FIR, with its extentions, that contains its airport.
+fn check_firs(tracons: &[Tracon], firs: &[FIRBoundary], dat: &VatSpyDat) -> Vec<Diagnostic> {
+    let groups: Vec<(String, Vec<Vec<Coord>>)> = with_extentions(firs)
+        .into_iter()
+        .map(|g| {
+            (
+                firs[g[0]].icao.clone(),
+                g.iter()
+                    .map(|n| geodesy::ring_coords(&firs[*n].boundary_corners))
+                    .collect(),
+            )
+        })
+        .collect();
+    let mut diagnostics = Vec::new();
+    for tracon in tracons {
+        let id = &tracon.properties.id;
+        let airport = match airport(tracon, dat) {
+            Some(a) => a,
+            None => {
+                diagnostics.push(Diagnostic::NoAirport {
+                    tracon: id.clone(),
+                    name: tracon.properties.name.clone(),
+                });
+                continue;
+            }
+        };
+        let position = Coord::from(&airport.position);
+        let containing = groups
+            .iter()
+            .filter(|(_, rings)| rings.iter().any(|r| geodesy::contains(r, position)))
+            .collect_vec();
+        if containing.is_empty() {
+            diagnostics.push(Diagnostic::AirportOutsideFirs {
+                tracon: id.clone(),
+                airport: airport.icao.clone(),
+                name: airport.name.clone(),
+                fir: airport.fir.clone(),
+            });
+            continue;
+        }
+        let points = tracon.geometry.outer_rings().concat();
+        // airports in overlapping FIRs are fine when the TRACON is inside one of them
+        let outside = containing
+            .iter()
+            .map(|(icao, rings)| {
+                let outside = points
+                    .iter()
+                    .filter(|p| !inside(rings, Coord::from(*p)))
+                    .cloned()
+                    .unique()
+                    .collect_vec();
+                (icao, outside)
+            })
+            .min_by_key(|(_, outside)| outside.len())
+            .unwrap();
+        if !outside.1.is_empty() {
+            diagnostics.push(Diagnostic::OutsideFir {
+                tracon: id.clone(),
+                airport: airport.icao.clone(),
+                fir: outside.0.clone(),
+                points: outside.1,
+            });
+        }
+    }
+    diagnostics
+}
+
+/// Validates the TRACONs. With FIR boundaries and VATSpy.dat every TRACON is also checked against the FIR of its airport.
+pub(crate) fn validate(
+    tracons: &[Tracon],
+    firs: Option<(&[FIRBoundary], &VatSpyDat)>,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for tracon in tracons {
+        check_geometry(tracon, &mut diagnostics);
+    }
+    diagnostics.extend(check_prefixes(tracons));
+    if let Some((firs, dat)) = firs {
+        diagnostics.extend(check_firs(tracons, firs, dat));
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fir_boundaries::fir;
+
+    const TRACONS: &str = r#"{"type": "FeatureCollection", "features": [
+        {"type": "Feature", "properties": {"id": "AAA", "prefix": ["AAA", "SHR"], "name": "Alpha Approach"},
+         "geometry": {"type": "MultiPolygon", "coordinates": [[[[1, 1], [1, 2], [2, 2], [2, 1], [1, 1]]]]}},
+        {"type": "Feature", "properties": {"id": "BBB", "prefix": ["SHR"], "name": "Bravo Approach"},
+         "geometry": {"type": "Polygon", "coordinates": [[[3, 1], [4, 1], [4, 2], [3, 2], [3, 1]]]}},
+        {"type": "Feature", "properties": {"id": "CCC", "prefix": [], "name": "Charlie Approach"},
+         "geometry": {"type": "Polygon", "coordinates": [[[1, 3], [1, 5], [2, 3], [2, 5], [1, 3]]]}}
+    ]}"#;
+
+    #[test]
+    fn geometry_and_prefixes() {
+        let tracons: TraconCollection = serde_json::from_str(TRACONS).unwrap();
+        let diagnostics = validate(&tracons.features, None)
+            .iter()
+            .map(|d| d.to_string())
+            .collect_vec();
+        assert_eq!(
+            diagnostics,
+            vec![
+                "TRACON BBB: ring 1 is drawn counterclockwise, it needs to be drawn clockwise like FIRs",
+                "TRACON CCC: ring 1 has no area",
+                "TRACON CCC: crosses itself at: 4|1.5",
+                "Prefix SHR is used by more than one TRACON: AAA, BBB",
+            ]
+        );
+    }
+
+    #[test]
+    fn inside_fir_of_airport() {
+        let tracons: TraconCollection = serde_json::from_str(TRACONS).unwrap();
+        // ZZZZ covers AAA, BBB sticks out of it to the east, and CCC has no airport
+        let firs = vec![fir("ZZZZ", false, &[(0, 0), (3, 0), (3, 3), (0, 3)])];
+        let dat = VatSpyDat::parse(
+            "[Airports]\nZAAA|Alpha|1.5|1.5|AAA|ZZZZ|0\nZBBB|Bravo|1.5|2.9|BBB|ZZZZ|0\n",
+        )
+        .unwrap();
+        let diagnostics = check_firs(&tracons.features, &firs, &dat)
+            .iter()
+            .map(|d| d.to_string())
+            .collect_vec();
+        assert_eq!(
+            diagnostics,
+            vec![
+                "TRACON BBB: 2 point(s) are outside FIR ZZZZ containing its airport ZBBB: 1|4, 2|4",
+                "TRACON CCC (Charlie Approach): no airport in VATSpy.dat matches its id or prefixes",
+            ]
+        );
+    }
+}
@@ -0,0 +1,573 @@
+use std::{
+    collections::HashMap,
+    f64::consts::PI,
+    fmt::{self, Display},
+};
+
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::{
+    fir_boundaries::{polygon_or_hole, FIRBoundary, Fill, Point},
+    geo_json::Crs,
+    geodesy::{self, Coord, METRES_PER_NM},
+    lable,
+    topology::open_ring,
+    vatspy_dat::VatSpyDat,
+};
+
+/// Problems found while building the UIR outlines.
+#[derive(Debug, Clone)]
+pub(crate) enum Diagnostic {
+    /// A member of the UIR is not in the [FIRs] section of VATSpy.dat.
+    UnknownFir { uir: String, fir: String },
+    /// There is no boundary for a member FIR.
+    MissingBoundary {
+        uir: String,
+        fir: String,
+        boundary: String,
+    },
+    /// The members make up more than one area.
+    Disjoint {
+        uir: String,
+        parts: Vec<Vec<String>>,
+    },
+    /// The members leave holes in the UIR.
+    Holes { uir: String, holes: usize },
+    /// Borders between members that are not drawn with the same points, leaving thin slivers.
+    Slivers { uir: String, slivers: usize },
+    /// Two members cover the same area. The outline is not built.
+    Overlap { uir: String, firs: (String, String) },
+    /// Corners of members on a border of another member that has no corner there. The outline is not built.
+    Junctions { uir: String, points: Vec<Point> },
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Diagnostic::UnknownFir { uir, fir } => {
+                write!(
+                    f,
+                    "UIR {}: member {} is not in the [FIRs] section",
+                    uir, fir
+                )
+            }
+            Diagnostic::MissingBoundary { uir, fir, boundary } => write!(
+                f,
+                "UIR {}: member {} uses boundary {}, which is not in the boundary file",
+                uir, fir, boundary
+            ),
+            Diagnostic::Disjoint { uir, parts } => write!(
+                f,
+                "UIR {}: members are split into {} separate areas: {}",
+                uir,
+                parts.len(),
+                parts
+                    .iter()
+                    .map(|p| format!("[{}]", p.join(", ")))
+                    .join(", ")
+            ),
+            Diagnostic::Holes { uir, holes } => {
+                write!(f, "UIR {}: members leave {} hole(s)", uir, holes)
+            }
+            Diagnostic::Slivers { uir, slivers } => write!(
+                f,
+                "UIR {}: {} border(s) between members do not share the same points, run snap first",
+                uir, slivers
+            ),
+            Diagnostic::Overlap { uir, firs } => write!(
+                f,
+                "UIR {}: members {} and {} overlap, no outline is built",
+                uir, firs.0, firs.1
+            ),
+            Diagnostic::Junctions { uir, points } => write!(
+                f,
+                "UIR {}: corners at {} are on a border of another member without a corner there, \
+                 no outline is built, run snap first",
+                uir,
+                points.iter().join(", ")
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct UirCollection {
+    #[serde(rename = "type")]
+    typ: String,
+    name: String,
+    crs: Crs,
+    pub(crate) features: Vec<UirFeature>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct UirFeature {
+    #[serde(rename = "type")]
+    typ: String,
+    pub(crate) properties: UirProperties,
+    pub(crate) geometry: UirGeometry,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct UirProperties {
+    #[serde(rename = "ICAO")]
+    pub(crate) icao: String,
+    pub(crate) name: String,
+    #[serde(rename = "FIRs")]
+    pub(crate) firs: Vec<String>,
+    pub(crate) lable: Point,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct UirGeometry {
+    #[serde(rename = "type")]
+    typ: String,
+    /// Polygons of an outer ring followed by its holes, every ring closed.
+    pub(crate) coordinates: Vec<Vec<Vec<Point>>>,
+}
+
+/// The outline of a set of rings, as outer rings and holes without closing points.
+#[derive(Debug, Default)]
+pub(crate) struct Union {
+    pub(crate) outer: Vec<Vec<Point>>,
+    pub(crate) holes: Vec<Vec<Point>>,
+    /// Leftover rings with next to no area, from borders not drawn with the same points on both sides.
+    pub(crate) slivers: usize,
+}
+
+/// Direction from `a` to `b` in radians, with longitude as x and latitude as y.
+fn direction(a: &Point, b: &Point) -> f64 {
+    let (a, b) = (Coord::from(a), Coord::from(b));
+    let (x, y) = geodesy::project_nm(a, b);
+    y.atan2(x)
+}
+
+/// Joins rings sharing borders into their outline.
+///
+/// Every ring is turned to the draw direction of a FIR, so an edge shared by two rings is drawn
+/// in opposite directions in them. Those edges are removed and the rest are joined into new rings.
+/// Borders must use the same points on both sides to be removed, `conflicts` finds where they do not.
+pub(crate) fn union(rings: &[Vec<Point>]) -> Union {
+    let mut edges: HashMap<(Point, Point), usize> = HashMap::new();
+    for ring in rings {
+        let mut ring = open_ring(ring);
+        if ring.len() < 3 {
+            continue;
+        }
+        if polygon_or_hole(&ring) == Fill::Hole {
+            ring.reverse();
+        }
+        for (a, b) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+            match edges.get_mut(&(b.clone(), a.clone())) {
+                Some(n) if *n > 0 => *n -= 1,
+                _ => *edges.entry((a.clone(), b.clone())).or_insert(0) += 1,
+            }
+        }
+    }
+    let mut outgoing: HashMap<Point, Vec<Point>> = HashMap::new();
+    for ((a, b), n) in edges.into_iter().sorted() {
+        for _ in 0..n {
+            outgoing.entry(a.clone()).or_default().push(b.clone());
+        }
+    }
+
+    let mut result = Union::default();
+    let starts: Vec<Point> = outgoing.keys().cloned().sorted().collect();
+    for start in starts {
+        while let Some(first) = outgoing.get_mut(&start).and_then(|v| v.pop()) {
+            let mut ring = vec![start.clone()];
+            let (mut prev, mut current) = (start.clone(), first);
+            while current != start {
+                ring.push(current.clone());
+                let back = direction(&current, &prev);
+                let candidates = match outgoing.get_mut(&current) {
+                    Some(c) if !c.is_empty() => c,
+                    _ => break, // open chain, can only happen with broken input
+                };
+                // take the sharpest right turn, which keeps rings touching in one point apart
+                let (n, _) = candidates
+                    .iter()
+                    .map(|next| {
+                        let turn = (direction(&current, next) - back).rem_euclid(2.0 * PI);
+                        match turn == 0.0 {
+                            true => 2.0 * PI,
+                            false => turn,
+                        }
+                    })
+                    .enumerate()
+                    .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+                    .unwrap();
+                let next = candidates.swap_remove(n);
+                prev = std::mem::replace(&mut current, next);
+            }
+            if ring.len() < 3 {
+                result.slivers += 1;
+                continue;
+            }
+            let coords = geodesy::ring_coords(&ring);
+            let area = geodesy::signed_area(&coords);
+            let perimeter = geodesy::perimeter(&coords);
+            // thin rings left over along a border, compared with how round they are
+            if 4.0 * PI * area.abs() / (perimeter * perimeter) < 1e-3 {
+                result.slivers += 1;
+            } else if area < 0.0 {
+                // clockwise with longitude as x, the same as polygon_or_hole calls a polygon
+                result.outer.push(ring);
+            } else {
+                result.holes.push(ring);
+            }
+        }
+    }
+    result
+}
+
+/// Corners closer than this to a border of another ring are taken to be on it.
+const JUNCTION_TOLERANCE_NM: f64 = 0.01;
+
+/// Where rings do not meet edge to edge, which `union` can not join into a correct outline.
+#[derive(Debug, Default)]
+pub(crate) struct Conflicts {
+    /// Pairs of rings, as indexes, covering the same area.
+    pub(crate) overlaps: Vec<(usize, usize)>,
+    /// Corners of a ring on an edge of another ring, between the corners of that edge.
+    pub(crate) junctions: Vec<Point>,
+}
+
+impl Conflicts {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.overlaps.is_empty() && self.junctions.is_empty()
+    }
+}
+
+/// Tells if `p` is inside the ring and not on its border.
+fn strictly_inside(ring: &[Coord], p: Coord) -> bool {
+    geodesy::contains(ring, p)
+        && ring
+            .iter()
+            .zip(ring.iter().cycle().skip(1))
+            .all(|(a, b)| geodesy::segment_distance_nm(p, *a, *b) >= JUNCTION_TOLERANCE_NM)
+}
+
+/// Tells if an edge of `a` crosses an edge of `b`, away from the corners of both.
+fn edges_cross(a: &[Coord], b: &[Coord]) -> bool {
+    let edges = |ring: &[Coord]| {
+        ring.iter()
+            .copied()
+            .zip(ring.iter().copied().cycle().skip(1))
+            .collect_vec()
+    };
+    let (edges_a, edges_b) = (edges(a), edges(b));
+    edges_a.iter().any(|(p, q)| {
+        edges_b.iter().any(|(r, s)| {
+            let (rx, ry) = (q.lon - p.lon, q.lat - p.lat);
+            let (sx, sy) = (s.lon - r.lon, s.lat - r.lat);
+            let (qx, qy) = (r.lon - p.lon, r.lat - p.lat);
+            let denominator = rx * sy - ry * sx;
+            if denominator == 0.0 {
+                return false;
+            }
+            let t = (qx * sy - qy * sx) / denominator;
+            let u = (qx * ry - qy * rx) / denominator;
+            let inner = |v: f64| v > 1e-9 && v < 1.0 - 1e-9;
+            inner(t) && inner(u)
+        })
+    })
+}
+
+/// Finds rings that overlap, and corners of one ring on an edge of another.
+///
+/// Rings overlap if their edges cross, or a point inside one of them is inside the other:
+/// the middle of an edge, or the lable point when one ring is inside the other.
+pub(crate) fn conflicts(rings: &[Vec<Point>]) -> Conflicts {
+    let rings: Vec<Vec<Point>> = rings.iter().map(|r| open_ring(r)).collect();
+    let coords: Vec<Vec<Coord>> = rings.iter().map(|r| geodesy::ring_coords(r)).collect();
+    let mut result = Conflicts::default();
+    for (i, j) in (0..rings.len()).tuple_combinations() {
+        let (a, b) = (&coords[i], &coords[j]);
+        if a.len() < 3 || b.len() < 3 {
+            continue;
+        }
+        let inside = |ring: &[Coord], other: &[Coord]| {
+            ring.iter()
+                .zip(ring.iter().cycle().skip(1))
+                .map(|(p, q)| Coord {
+                    lat: (p.lat + q.lat) / 2.0,
+                    lon: (p.lon + q.lon) / 2.0,
+                })
+                .chain(std::iter::once(lable::polylabel(ring, 0.01)))
+                .any(|p| strictly_inside(other, p))
+        };
+        if edges_cross(a, b) || inside(a, b) || inside(b, a) {
+            result.overlaps.push((i, j));
+        }
+    }
+    // a corner can only be on edges spanning its latitude, with the tolerance in degrees
+    let margin = JUNCTION_TOLERANCE_NM / 60.0;
+    let near = |a: Coord, b: Coord| geodesy::distance(a, b) / METRES_PER_NM < JUNCTION_TOLERANCE_NM;
+    for (i, ring) in coords.iter().enumerate() {
+        for (c, corner) in ring.iter().zip(&rings[i]) {
+            let on_edge = coords
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .flat_map(|(_, other)| other.iter().zip(other.iter().cycle().skip(1)))
+                .filter(|(a, b)| {
+                    c.lat >= a.lat.min(b.lat) - margin && c.lat <= a.lat.max(b.lat) + margin
+                })
+                .any(|(a, b)| {
+                    geodesy::segment_distance_nm(*c, *a, *b) < JUNCTION_TOLERANCE_NM
+                        && !near(*c, *a)
+                        && !near(*c, *b)
+                });
+            if on_edge && !result.junctions.contains(corner) {
+                result.junctions.push(corner.clone());
+            }
+        }
+    }
+    result
+}
+
+fn closed(ring: &[Point]) -> Vec<Point> {
+    let mut ring = ring.to_vec();
+    ring.push(ring[0].clone());
+    ring
+}
+
+/// Builds the outline of every UIR in VATSpy.dat, or only those in `only` if it is not empty.
+pub(crate) fn build_uirs(
+    dat: &VatSpyDat,
+    boundaries: &[FIRBoundary],
+    only: &[String],
+) -> (UirCollection, Vec<Diagnostic>) {
+    let mut diagnostics = Vec::new();
+    let mut features = Vec::new();
+    for uir in dat
+        .uirs
+        .iter()
+        .filter(|u| only.is_empty() || only.contains(&u.icao))
+    {
+        let mut members: Vec<(&str, Vec<&FIRBoundary>)> = Vec::new();
+        for fir in &uir.firs {
+            let boundary = match dat.fir(fir) {
+                Some(f) => f.boundary_icao(),
+                None => {
+                    diagnostics.push(Diagnostic::UnknownFir {
+                        uir: uir.icao.clone(),
+                        fir: fir.clone(),
+                    });
+                    continue;
+                }
+            };
+            let parts: Vec<&FIRBoundary> =
+                boundaries.iter().filter(|b| b.icao == boundary).collect();
+            match parts.is_empty() {
+                true => diagnostics.push(Diagnostic::MissingBoundary {
+                    uir: uir.icao.clone(),
+                    fir: fir.clone(),
+                    boundary: boundary.to_string(),
+                }),
+                false => members.push((fir.as_str(), parts)),
+            }
+        }
+        let (owners, rings): (Vec<&str>, Vec<Vec<Point>>) = members
+            .iter()
+            .flat_map(|(icao, parts)| {
+                parts
+                    .iter()
+                    .map(move |p| (*icao, p.boundary_corners.clone()))
+            })
+            .unzip();
+        let conflicts = conflicts(&rings);
+        if !conflicts.is_empty() {
+            for (i, j) in conflicts.overlaps {
+                diagnostics.push(Diagnostic::Overlap {
+                    uir: uir.icao.clone(),
+                    firs: (owners[i].to_string(), owners[j].to_string()),
+                });
+            }
+            if !conflicts.junctions.is_empty() {
+                diagnostics.push(Diagnostic::Junctions {
+                    uir: uir.icao.clone(),
+                    points: conflicts.junctions,
+                });
+            }
+            continue;
+        }
+        let outline = union(&rings);
+        if outline.outer.is_empty() {
+            continue;
+        }
+
+        let outer_coords: Vec<Vec<Coord>> = outline
+            .outer
+            .iter()
+            .map(|r| geodesy::ring_coords(r))
+            .collect();
+        if outline.outer.len() > 1 {
+            // a point well inside every part, the lable may be outside or shared by extentions
+            let inside: Vec<Vec<Coord>> = members
+                .iter()
+                .map(|(_, parts)| {
+                    parts
+                        .iter()
+                        .map(|p| lable::polylabel(&geodesy::ring_coords(&p.boundary_corners), 0.01))
+                        .collect()
+                })
+                .collect();
+            let parts = outer_coords
+                .iter()
+                .map(|ring| {
+                    members
+                        .iter()
+                        .zip(&inside)
+                        .filter(|(_, points)| points.iter().any(|c| geodesy::contains(ring, *c)))
+                        .map(|((icao, _), _)| icao.to_string())
+                        .collect()
+                })
+                .collect();
+            diagnostics.push(Diagnostic::Disjoint {
+                uir: uir.icao.clone(),
+                parts,
+            });
+        }
+        if !outline.holes.is_empty() {
+            diagnostics.push(Diagnostic::Holes {
+                uir: uir.icao.clone(),
+                holes: outline.holes.len(),
+            });
+        }
+        if outline.slivers > 0 {
+            diagnostics.push(Diagnostic::Slivers {
+                uir: uir.icao.clone(),
+                slivers: outline.slivers,
+            });
+        }
+
+        let mut polygons: Vec<Vec<Vec<Point>>> =
+            outline.outer.iter().map(|r| vec![closed(r)]).collect();
+        for hole in &outline.holes {
+            let inside = Coord::from(&hole[0]);
+            if let Some(n) = outer_coords
+                .iter()
+                .position(|ring| geodesy::contains(ring, inside))
+            {
+                polygons[n].push(closed(hole));
+            }
+        }
+        let largest = outer_coords
+            .iter()
+            .max_by(|a, b| geodesy::area(a).partial_cmp(&geodesy::area(b)).unwrap())
+            .unwrap();
+        features.push(UirFeature {
+            typ: "Feature".to_string(),
+            properties: UirProperties {
+                icao: uir.icao.clone(),
+                name: uir.name.clone(),
+                firs: uir.firs.clone(),
+                lable: lable::polylabel(largest, 0.01).to_point(),
+            },
+            geometry: UirGeometry {
+                typ: "MultiPolygon".to_string(),
+                coordinates: polygons,
+            },
+        });
+    }
+    (
+        UirCollection {
+            typ: "FeatureCollection".to_string(),
+            name: String::new(),
+            crs: Crs::default(),
+            features,
+        },
+        diagnostics,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fir_boundaries::fir;
+
+    fn ring(points: &[(i64, i64)]) -> Vec<Point> {
+        points
+            .iter()
+            .map(|(lat, lon)| Point::new((*lat).into(), (*lon).into()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn neighbours_become_one_ring() {
+        let a = ring(&[(58, 5), (62, 5), (62, 10), (60, 10), (58, 10)]);
+        let b = ring(&[(58, 10), (60, 10), (62, 10), (62, 15), (58, 15)]);
+        let outline = union(&[a, b]);
+        assert_eq!(outline.outer.len(), 1);
+        assert!(outline.holes.is_empty());
+        assert_eq!(outline.slivers, 0);
+        let mut expected = ring(&[(58, 5), (62, 5), (62, 10), (62, 15), (58, 15), (58, 10)]);
+        let start = outline.outer[0]
+            .iter()
+            .position(|p| *p == expected[0])
+            .unwrap();
+        expected.rotate_right(start);
+        assert_eq!(outline.outer[0], expected);
+    }
+
+    #[test]
+    fn ring_around_a_hole() {
+        // eight squares around the square 1..2, 1..2
+        let rings: Vec<Vec<Point>> = (0..3)
+            .cartesian_product(0..3)
+            .filter(|c| *c != (1, 1))
+            .map(|(lat, lon)| {
+                ring(&[
+                    (lat, lon),
+                    (lat + 1, lon),
+                    (lat + 1, lon + 1),
+                    (lat, lon + 1),
+                ])
+            })
+            .collect();
+        let outline = union(&rings);
+        assert_eq!(outline.outer.len(), 1);
+        assert_eq!(outline.holes.len(), 1);
+        assert_eq!(outline.outer[0].len(), 12);
+        assert_eq!(outline.holes[0].len(), 4);
+    }
+
+    #[test]
+    fn overlaps_and_junctions() {
+        let a = ring(&[(58, 5), (62, 5), (62, 10), (58, 10)]);
+        let b = ring(&[(58, 10), (60, 10), (62, 10), (62, 15), (58, 15)]);
+        let shared = ring(&[(58, 10), (62, 10), (62, 15), (58, 15)]);
+        assert!(conflicts(&[a.clone(), shared]).is_empty());
+
+        let junction = conflicts(&[a.clone(), b]);
+        assert!(junction.overlaps.is_empty());
+        assert_eq!(junction.junctions, ring(&[(60, 10)]));
+
+        let crossing = ring(&[(60, 8), (64, 8), (64, 12), (60, 12)]);
+        let inner = ring(&[(59, 6), (61, 6), (61, 7), (59, 7)]);
+        let overlaps = conflicts(&[a, crossing, inner]);
+        assert_eq!(overlaps.overlaps, vec![(0, 1), (0, 2)]);
+    }
+
+    #[test]
+    fn no_outline_for_overlapping_members() {
+        let dat = VatSpyDat::parse(
+            "[FIRs]\nAAAA|Alpha||\nBBBB|Bravo||\n[UIRs]\nUUUU|Uniform|AAAA,BBBB\n",
+        )
+        .unwrap();
+        let firs = vec![
+            fir("AAAA", false, &[(0, 0), (2, 0), (2, 2), (0, 2)]),
+            fir("BBBB", false, &[(1, 1), (3, 1), (3, 3), (1, 3)]),
+        ];
+        let (uirs, diagnostics) = build_uirs(&dat, &firs, &[]);
+        assert!(uirs.features.is_empty());
+        let diagnostics: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            diagnostics,
+            vec!["UIR UUUU: members AAAA and BBBB overlap, no outline is built"]
+        );
+    }
+}
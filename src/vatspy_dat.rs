@@ -0,0 +1,192 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use rust_decimal::Decimal;
+
+use crate::fir_boundaries::{FIRParsingError, FIRResult, Point};
+
+/// A line from the [FIRs] section.
+#[derive(Debug, Clone)]
+pub(crate) struct Fir {
+    pub(crate) icao: String,
+    pub(crate) name: String,
+    /// Prefix of the callsigns of its controllers, like EGTT. Often empty, then the ICAO is used.
+    pub(crate) callsign_prefix: String,
+    /// ICAO of the boundary in FIRBoundaries.dat. Empty if it is the same as `icao`.
+    pub(crate) boundary: String,
+}
+
+impl Fir {
+    /// ICAO of the boundary in FIRBoundaries.dat this FIR uses.
+    pub(crate) fn boundary_icao(&self) -> &str {
+        match self.boundary.is_empty() {
+            true => &self.icao,
+            false => &self.boundary,
+        }
+    }
+}
+
+/// A line from the [UIRs] section.
+#[derive(Debug, Clone)]
+pub(crate) struct Uir {
+    pub(crate) icao: String,
+    pub(crate) name: String,
+    /// ICAO of the member FIRs, from the [FIRs] section.
+    pub(crate) firs: Vec<String>,
+}
+
+/// A line from the [Airports] section.
+#[derive(Debug, Clone)]
+pub(crate) struct Airport {
+    pub(crate) icao: String,
+    pub(crate) name: String,
+    pub(crate) position: Point,
+    /// IATA code, or the local identifier (LID) where there is none, like BOS for KBOS.
+    pub(crate) iata: String,
+    /// ICAO of the FIR in the [FIRs] section the airport belongs to.
+    pub(crate) fir: String,
+    /// Pseudo airports are extra positions for an airport, like a helipad or a second name.
+    pub(crate) is_pseudo: bool,
+    /// Line number in the file, starting at 1.
+    pub(crate) line: usize,
+}
+
+/// A line from the [IDL] section, a piece of the International Date Line.
+#[derive(Debug, Clone)]
+pub(crate) struct IdlSegment {
+    pub(crate) start: Point,
+    pub(crate) end: Point,
+    /// Line number in the file, starting at 1.
+    pub(crate) line: usize,
+}
+
+// format:
+// [Airports]
+// ICAO|Name|Latitude|Longitude|IATA/LID|FIR|IsPseudo
+// [FIRs]
+// ICAO|Name|CallsignPrefix|FIRBoundary
+// [UIRs]
+// ICAO|Name|FIRs (comma separated)
+// [IDL]
+// Cord 1 Lat|Cord 1 Lon|Cord 2 Lat|Cord 2 Lon
+
+#[derive(Debug, Default)]
+pub(crate) struct VatSpyDat {
+    pub(crate) airports: Vec<Airport>,
+    pub(crate) firs: Vec<Fir>,
+    pub(crate) uirs: Vec<Uir>,
+    pub(crate) idl: Vec<IdlSegment>,
+}
+
+fn fields(line: &str, expected: usize, linenr: usize) -> FIRResult<Vec<&str>> {
+    let fields: Vec<_> = line.split('|').map(str::trim).collect();
+    match fields.len() >= expected {
+        true => Ok(fields),
+        false => Err(FIRParsingError::FIRParsing(format!(
+            "VATSpy.dat line: {}, Expected {} fields, found: {}, values: {:?}",
+            linenr,
+            expected,
+            fields.len(),
+            fields
+        ))),
+    }
+}
+
+impl VatSpyDat {
+    pub(crate) fn parse(content: &str) -> FIRResult<Self> {
+        let mut dat = Self::default();
+        let mut section = String::new();
+        for (n, line) in content.lines().enumerate() {
+            let linenr = n + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                section = line[1..line.len() - 1].to_string();
+                continue;
+            }
+            match section.as_str() {
+                "Airports" => {
+                    let f = fields(line, 7, linenr)?;
+                    dat.airports.push(Airport {
+                        icao: f[0].to_string(),
+                        name: f[1].to_string(),
+                        position: Point::new(f[2].parse::<Decimal>()?, f[3].parse::<Decimal>()?)?,
+                        iata: f[4].to_string(),
+                        fir: f[5].to_string(),
+                        is_pseudo: f[6] == "1",
+                        line: linenr,
+                    });
+                }
+                "FIRs" => {
+                    let f = fields(line, 4, linenr)?;
+                    dat.firs.push(Fir {
+                        icao: f[0].to_string(),
+                        name: f[1].to_string(),
+                        callsign_prefix: f[2].to_string(),
+                        boundary: f[3].to_string(),
+                    });
+                }
+                "UIRs" => {
+                    let f = fields(line, 3, linenr)?;
+                    dat.uirs.push(Uir {
+                        icao: f[0].to_string(),
+                        name: f[1].to_string(),
+                        firs: f[2]
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|s| !s.is_empty())
+                            .map(String::from)
+                            .collect(),
+                    });
+                }
+                "IDL" => {
+                    let f = fields(line, 4, linenr)?;
+                    let point = |lat: &str, lon: &str| -> FIRResult<Point> {
+                        Point::new(lat.parse()?, lon.parse()?)
+                    };
+                    dat.idl.push(IdlSegment {
+                        start: point(f[0], f[1])?,
+                        end: point(f[2], f[3])?,
+                        line: linenr,
+                    });
+                }
+                _ => (),
+            }
+        }
+        Ok(dat)
+    }
+
+    pub(crate) fn fir(&self, icao: &str) -> Option<&Fir> {
+        self.firs.iter().find(|f| f.icao == icao)
+    }
+
+    /// FIRs drawn with the boundary with this ICAO.
+    pub(crate) fn firs_with_boundary<'a>(&'a self, icao: &'a str) -> impl Iterator<Item = &'a Fir> {
+        self.firs.iter().filter(move |f| f.boundary_icao() == icao)
+    }
+}
+
+/// Replaces the FIR field of [Airports] lines, given by line number, keeping everything else in the file as it is.
+pub(crate) fn set_airport_firs(content: &str, firs: &HashMap<usize, String>) -> String {
+    content
+        .split_inclusive('\n')
+        .enumerate()
+        .map(|(n, line)| match firs.get(&(n + 1)) {
+            Some(fir) => {
+                let (text, end) = match line.find(&['\r', '\n'][..]) {
+                    Some(i) => line.split_at(i),
+                    None => (line, ""),
+                };
+                let mut fields = text.split('|').map(String::from).collect::<Vec<_>>();
+                fields[5] = fir.clone();
+                format!("{}{}", fields.join("|"), end)
+            }
+            None => line.to_string(),
+        })
+        .collect()
+}
+
+pub(crate) fn read_file<P: AsRef<Path>>(p: P) -> FIRResult<VatSpyDat> {
+    VatSpyDat::parse(&fs::read_to_string(p)?)
+}
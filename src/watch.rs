@@ -0,0 +1,90 @@
+use std::{
+    fs,
+    path::Path,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use indexmap::IndexSet;
+
+/// How often the file is checked for a new save.
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Findings that came or went between two runs.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Changes<'a> {
+    pub(crate) new: Vec<&'a str>,
+    pub(crate) resolved: Vec<&'a str>,
+}
+
+pub(crate) fn changes<'a>(
+    previous: &'a IndexSet<String>,
+    current: &'a IndexSet<String>,
+) -> Changes<'a> {
+    Changes {
+        new: current.difference(previous).map(String::as_str).collect(),
+        resolved: previous.difference(current).map(String::as_str).collect(),
+    }
+}
+
+/// Time of the last save. None while the file is gone, like when an editor saves by replacing the file.
+fn modified(p: &Path) -> Option<SystemTime> {
+    fs::metadata(p).and_then(|m| m.modified()).ok()
+}
+
+/// Runs `check` on every save of the file, and prints the findings that are new or resolved since the run before.
+/// The first run prints every finding. The file is looked at again after `check`, so it can write to the same file
+/// without starting another run.
+pub(crate) fn watch<F>(p: &Path, mut check: F) -> !
+where
+    F: FnMut() -> IndexSet<String>,
+{
+    let mut seen = None;
+    let mut previous = IndexSet::new();
+    loop {
+        let saved = modified(p);
+        if saved.is_some() && saved != seen {
+            let current = check();
+            let changes = changes(&previous, &current);
+            for finding in &changes.new {
+                println!("+ {}", finding);
+            }
+            for finding in &changes.resolved {
+                println!("- {}", finding);
+            }
+            println!(
+                "{}: {} findings, {} new, {} resolved",
+                p.display(),
+                current.len(),
+                changes.new.len(),
+                changes.resolved.len()
+            );
+            previous = current;
+            seen = modified(p);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(findings: &[&str]) -> IndexSet<String> {
+        findings.iter().map(|f| f.to_string()).collect()
+    }
+
+    #[test]
+    fn new_and_resolved() {
+        let previous = set(&["ENOR: duplicate point", "ESAA: not closed"]);
+        let current = set(&["ESAA: not closed", "BIRD: self intersection"]);
+        assert_eq!(
+            changes(&previous, &current),
+            Changes {
+                new: vec!["BIRD: self intersection"],
+                resolved: vec!["ENOR: duplicate point"],
+            }
+        );
+        assert_eq!(changes(&current, &current), Changes::default());
+    }
+}
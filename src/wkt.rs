@@ -0,0 +1,436 @@
+use std::{
+    convert::TryInto,
+    fmt::Write as _,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+
+use crate::{
+    csv::{self, quote},
+    fir_boundaries::{
+        try_polygon_or_hole, with_extentions, FIRBoundary, FIRParsingError, FIRResult, Fill, Point,
+    },
+    geodesy::Coord,
+    topology::open_ring,
+};
+
+// one row per FIR, with its extentions as the other polygons of a multipolygon:
+// icao  is_oceanic  lable_lat  lable_lon  geom
+// the default is the text format of PostgreSQL COPY: columns split by tabs, no header and t/f for booleans
+// with --csv it is CSV with a header, for COPY ... WITH (FORMAT csv, HEADER)
+// geom is EWKT, SRID=4326;MULTIPOLYGON(((lon lat, ...))), or hex EWKB, the way PostGIS writes geometries
+// outer rings are written counterclockwise, as the simple features specification wants them
+
+const SRID: u32 = 4326;
+const HEADER: &str = "icao,is_oceanic,lable_lat,lable_lon,geom";
+
+const WKB_POLYGON: u32 = 3;
+const WKB_MULTIPOLYGON: u32 = 6;
+const EWKB_Z: u32 = 0x8000_0000;
+const EWKB_M: u32 = 0x4000_0000;
+const EWKB_SRID: u32 = 0x2000_0000;
+
+/// How the geometry column is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Geometry {
+    Wkt,
+    Wkb,
+}
+
+fn wkt_error<S: AsRef<str>>(linenr: usize, s: S) -> FIRParsingError {
+    FIRParsingError::FIRParsing(format!("WKT/WKB line: {}, {}", linenr, s.as_ref()))
+}
+
+/// The rings of a FIR and its extentions, counterclockwise and closed.
+fn rings(firs: &[FIRBoundary], group: &[usize]) -> Vec<Vec<Point>> {
+    group
+        .iter()
+        .map(|n| {
+            let mut ring = open_ring(&firs[*n].boundary_corners);
+            ring.reverse();
+            ring.push(ring[0].clone());
+            ring
+        })
+        .collect()
+}
+
+fn to_wkt(rings: &[Vec<Point>]) -> String {
+    let polygons = rings
+        .iter()
+        .map(|ring| {
+            let points: Vec<String> = ring
+                .iter()
+                .map(|p| format!("{} {}", p.lon, p.lat))
+                .collect();
+            format!("(({}))", points.join(","))
+        })
+        .collect::<Vec<_>>();
+    format!("SRID={};MULTIPOLYGON({})", SRID, polygons.join(","))
+}
+
+fn to_wkb(rings: &[Vec<Point>]) -> String {
+    let mut wkb = vec![1];
+    wkb.extend((WKB_MULTIPOLYGON | EWKB_SRID).to_le_bytes());
+    wkb.extend(SRID.to_le_bytes());
+    wkb.extend((rings.len() as u32).to_le_bytes());
+    for ring in rings {
+        wkb.push(1);
+        wkb.extend(WKB_POLYGON.to_le_bytes());
+        wkb.extend(1u32.to_le_bytes());
+        wkb.extend((ring.len() as u32).to_le_bytes());
+        for p in ring {
+            wkb.extend(p.lon.to_f64().unwrap().to_le_bytes());
+            wkb.extend(p.lat.to_f64().unwrap().to_le_bytes());
+        }
+    }
+    wkb.iter().fold(String::new(), |mut hex, b| {
+        write!(hex, "{:02X}", b).unwrap();
+        hex
+    })
+}
+
+pub(crate) fn to_writer<W: Write>(
+    firs: &[FIRBoundary],
+    geometry: Geometry,
+    csv: bool,
+    writer: &mut W,
+) -> io::Result<()> {
+    if csv {
+        writeln!(writer, "{}", HEADER)?;
+    }
+    for group in with_extentions(firs) {
+        let fir = &firs[group[0]];
+        let rings = rings(firs, &group);
+        let geom = match geometry {
+            Geometry::Wkt => to_wkt(&rings),
+            Geometry::Wkb => to_wkb(&rings),
+        };
+        match csv {
+            true => writeln!(
+                writer,
+                "{},{},{},{},\"{}\"",
+                quote(&fir.icao),
+                fir.is_oseanic,
+                fir.lable.lat,
+                fir.lable.lon,
+                geom
+            )?,
+            false => writeln!(
+                writer,
+                "{}\t{}\t{}\t{}\t{}",
+                fir.icao,
+                match fir.is_oseanic {
+                    true => "t",
+                    false => "f",
+                },
+                fir.lable.lat,
+                fir.lable.lon,
+                geom
+            )?,
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn write_file<P: AsRef<Path>>(
+    firs: &[FIRBoundary],
+    p: P,
+    geometry: Geometry,
+    csv: bool,
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(p)?);
+    to_writer(firs, geometry, csv, &mut writer)?;
+    writer.flush()
+}
+
+/// Reads the rings of a WKT polygon or multipolygon, with or without an SRID.
+fn parse_wkt(s: &str) -> Result<Vec<Vec<Vec<Coord>>>, String> {
+    let s = match s.split_once(';') {
+        Some((srid, rest)) if srid.trim().to_uppercase().starts_with("SRID=") => rest,
+        _ => s,
+    };
+    let s = s.trim();
+    let open = s.find('(').ok_or_else(|| format!("not a polygon: {}", s))?;
+    let kind = s[..open].trim().to_uppercase();
+    let kind = kind
+        .trim_end_matches(" ZM")
+        .trim_end_matches(" Z")
+        .trim_end_matches(" M");
+    let body = s[open..].trim();
+    // split on the parentheses, collecting the numbers of every ring at depth 2 or 3
+    let ring_depth = match kind {
+        "POLYGON" => 2,
+        "MULTIPOLYGON" => 3,
+        _ => return Err(format!("{} is not a polygon or multipolygon", kind)),
+    };
+    let mut polygons: Vec<Vec<Vec<Coord>>> = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for c in body.chars() {
+        match c {
+            '(' => {
+                depth += 1;
+                if depth == ring_depth - 1 {
+                    polygons.push(Vec::new());
+                }
+                current.clear();
+            }
+            ')' => {
+                if depth == ring_depth {
+                    let ring = current
+                        .split(',')
+                        .map(|point| {
+                            let values: Vec<f64> = point
+                                .split_whitespace()
+                                .map(str::parse)
+                                .collect::<Result<_, _>>()
+                                .map_err(|_| format!("not a point: {}", point.trim()))?;
+                            match values[..] {
+                                [lon, lat, ..] => Ok(Coord { lat, lon }),
+                                _ => Err(format!("not a point: {}", point.trim())),
+                            }
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    polygons
+                        .last_mut()
+                        .ok_or_else(|| "ring outside a polygon".to_string())?
+                        .push(ring);
+                }
+                depth -= 1;
+            }
+            c => current.push(c),
+        }
+    }
+    if depth != 0 {
+        return Err("parentheses do not match".to_string());
+    }
+    Ok(polygons)
+}
+
+/// Reads numbers out of WKB, where every geometry says its own byte order.
+struct Wkb {
+    bytes: Vec<u8>,
+    pos: usize,
+}
+
+impl Wkb {
+    fn take<const N: usize>(&mut self, little: bool) -> Result<[u8; N], String> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + N)
+            .ok_or_else(|| "the geometry ends too early".to_string())?;
+        self.pos += N;
+        let mut bytes: [u8; N] = bytes.try_into().unwrap();
+        if !little {
+            bytes.reverse();
+        }
+        Ok(bytes)
+    }
+
+    fn u32(&mut self, little: bool) -> Result<u32, String> {
+        self.take(little).map(u32::from_le_bytes)
+    }
+
+    fn f64(&mut self, little: bool) -> Result<f64, String> {
+        self.take(little).map(f64::from_le_bytes)
+    }
+
+    /// Reads a geometry header: byte order, type without flags and the number of values per point.
+    fn header(&mut self) -> Result<(bool, u32, usize), String> {
+        let little = self.take::<1>(true)?[0] == 1;
+        let kind = self.u32(little)?;
+        if kind & EWKB_SRID != 0 {
+            self.u32(little)?;
+        }
+        let mut dimensions = 2;
+        dimensions += (kind & EWKB_Z != 0) as usize + (kind & EWKB_M != 0) as usize;
+        // ISO WKB adds 1000 for Z, 2000 for M and 3000 for both
+        let iso = (kind & 0xFFFF) / 1000;
+        dimensions += match iso {
+            1 | 2 => 1,
+            3 => 2,
+            _ => 0,
+        };
+        Ok((little, (kind & 0xFFFF) % 1000, dimensions))
+    }
+
+    fn polygon(&mut self, little: bool, dimensions: usize) -> Result<Vec<Vec<Coord>>, String> {
+        (0..self.u32(little)?)
+            .map(|_| {
+                (0..self.u32(little)?)
+                    .map(|_| {
+                        let lon = self.f64(little)?;
+                        let lat = self.f64(little)?;
+                        for _ in 2..dimensions {
+                            self.f64(little)?;
+                        }
+                        Ok(Coord { lat, lon })
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Reads the rings of a hex WKB or EWKB polygon or multipolygon.
+fn parse_wkb(hex: &str) -> Result<Vec<Vec<Vec<Coord>>>, String> {
+    let bytes = (0..hex.len() / 2)
+        .map(|n| u8::from_str_radix(&hex[n * 2..n * 2 + 2], 16))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| "not hex WKB".to_string())?;
+    let mut wkb = Wkb { bytes, pos: 0 };
+    let (little, kind, dimensions) = wkb.header()?;
+    match kind {
+        WKB_POLYGON => Ok(vec![wkb.polygon(little, dimensions)?]),
+        WKB_MULTIPOLYGON => (0..wkb.u32(little)?)
+            .map(|_| {
+                let (little, kind, dimensions) = wkb.header()?;
+                match kind {
+                    WKB_POLYGON => wkb.polygon(little, dimensions),
+                    k => Err(format!("geometry type {} in a multipolygon", k)),
+                }
+            })
+            .collect(),
+        k => Err(format!(
+            "geometry type {} is not a polygon or multipolygon",
+            k
+        )),
+    }
+}
+
+/// Reads rows written by `to_writer`, as tab separated text or CSV with a header,
+/// with the geometry as WKT or hex WKB. The first polygon of a row is the FIR and the rest are
+/// extentions. Holes are left out.
+pub(crate) fn parse(content: &str) -> FIRResult<Vec<FIRBoundary>> {
+    let mut lines = content.lines().enumerate().peekable();
+    let csv = match lines.peek() {
+        Some((_, header)) if header.trim().eq_ignore_ascii_case(HEADER) => {
+            lines.next();
+            true
+        }
+        _ => false,
+    };
+    let mut firs = Vec::new();
+    for (n, line) in lines {
+        let linenr = n + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<String> = match csv {
+            true => csv::fields(line),
+            false => line.split('\t').map(str::to_string).collect(),
+        };
+        if fields.len() != 5 {
+            return Err(wkt_error(
+                linenr,
+                format!("expected 5 columns ({}), found: {}", HEADER, fields.len()),
+            ));
+        }
+        let is_oseanic = matches!(fields[1].trim(), "t" | "true" | "1");
+        let decimal =
+            |s: &str| -> FIRResult<Decimal> { Ok(s.trim().parse::<Decimal>()?.normalize()) };
+        let lable = Point::new(decimal(&fields[2])?, decimal(&fields[3])?)?;
+        let geom = fields[4].trim();
+        let polygons = match geom.chars().all(|c| c.is_ascii_hexdigit()) {
+            true => parse_wkb(geom),
+            false => parse_wkt(geom),
+        }
+        .map_err(|e| wkt_error(linenr, e))?;
+        // the first ring of a polygon is its outer ring
+        for (part, ring) in polygons.iter().filter_map(|p| p.first()).enumerate() {
+            let mut corners: Vec<Point> = ring.iter().map(|c| c.to_point()).collect();
+            corners = open_ring(&corners);
+            corners.dedup();
+            if corners.len() < 3 {
+                return Err(wkt_error(linenr, "polygon with less than 3 points"));
+            }
+            match try_polygon_or_hole(&corners) {
+                None => return Err(wkt_error(linenr, "polygon without area")),
+                Some(Fill::Hole) => corners.reverse(),
+                Some(Fill::Polygon) => {}
+            }
+            let mut fir = FIRBoundary {
+                id: firs.len(),
+                icao: fields[0].trim().to_string(),
+                is_oseanic,
+                is_extension: part != 0,
+                min_lat: Default::default(),
+                min_lon: Default::default(),
+                max_lat: Default::default(),
+                max_lon: Default::default(),
+                lable: lable.clone(),
+                boundary_corners: corners,
+            };
+            fir.update_bounds();
+            firs.push(fir);
+        }
+    }
+    Ok(firs)
+}
+
+pub(crate) fn read_file<P: AsRef<Path>>(p: P) -> FIRResult<Vec<FIRBoundary>> {
+    parse(&fs::read_to_string(p)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fir_boundaries::{fir, polygon_or_hole};
+
+    #[test]
+    fn round_trip() {
+        let firs = vec![
+            fir("EZZZ", false, &[(0, 0), (2, 0), (2, 2), (0, 2)]),
+            fir("EZZZ", true, &[(2, 2), (4, 2), (4, 4), (2, 4)]),
+            fir("LZZZ", false, &[(0, 2), (2, 2), (2, 4), (0, 4)]),
+        ];
+        for geometry in [Geometry::Wkt, Geometry::Wkb] {
+            for csv in [false, true] {
+                let mut out = Vec::new();
+                to_writer(&firs, geometry, csv, &mut out).unwrap();
+                let read = parse(&String::from_utf8(out).unwrap()).unwrap();
+                assert_eq!(read.len(), 3);
+                for (read, fir) in read.iter().zip(&firs) {
+                    assert_eq!(read.icao, fir.icao);
+                    assert_eq!(read.is_extension, fir.is_extension);
+                    assert_eq!(read.boundary_corners, fir.boundary_corners);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn postgis_output() {
+        // a polygon with a hole the way ST_AsText writes it, without an SRID and clockwise
+        let wkt = "EZZZ\tf\t0.5\t0.3\tPOLYGON((0 0,0 1,1 1,0 0),(0.1 0.2,0.1 0.3,0.2 0.3,0.1 0.2))";
+        let read = parse(wkt).unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].boundary_corners.len(), 3);
+        assert_eq!(polygon_or_hole(&read[0].boundary_corners), Fill::Polygon);
+
+        // big endian ISO WKB polygon Z with one ring
+        let mut wkb = vec![0];
+        wkb.extend(1003u32.to_be_bytes());
+        wkb.extend(1u32.to_be_bytes());
+        wkb.extend(4u32.to_be_bytes());
+        for (x, y) in [(0.0f64, 0.0f64), (1.0, 0.0), (1.0, 1.0), (0.0, 0.0)] {
+            wkb.extend(x.to_be_bytes());
+            wkb.extend(y.to_be_bytes());
+            wkb.extend(100.0f64.to_be_bytes());
+        }
+        let hex: String = wkb.iter().map(|b| format!("{:02x}", b)).collect();
+        let rings = parse_wkb(&hex).unwrap();
+        assert_eq!(rings[0][0].len(), 4);
+        assert_eq!(rings[0][0][2], Coord { lat: 1.0, lon: 1.0 });
+    }
+
+    #[test]
+    fn polygon_without_area() {
+        let wkt = "EZZZ\tf\t0.5\t0.3\tPOLYGON((0 0,1 1,2 2,0 0))";
+        let err = parse(wkt).unwrap_err().to_string();
+        assert!(err.contains("WKT/WKB line: 1, polygon without area"));
+    }
+}